@@ -18,3 +18,6 @@ cpr_fn --print-args --split-and-print-inner-cmdline --program '.\p.bat' --prepen
 
 cpr_fn --print-args --split-and-print-inner-cmdline --program '.\p.bat' --prepend-program \
 	--cmd-line-in-arg '"hello World" "\" x &calc.exe"'
+
+cpr_fn --batbadbut --program '.\p.bat' --args-are-rest \
+	'.\p.bat' 'hello World' '" x &calc.exe' || true
@@ -0,0 +1,135 @@
+#!/usr/bin/env python3
+
+# Generates src/codepage_tables.rs from the codecs of Python, and the best fit tables from Microsoft's
+# bestfit<code page>.txt files, which are at https://www.unicode.org/Public/MAPPINGS/VENDORS/MICSFT/WindowsBestFit/
+# Run it from the root of the repository:
+#   python3 scripts/gen-codepage-tables.py [<directory with the bestfit files>] > src/codepage_tables.rs
+# Without the directory the best fit tables are empty, and best fit is approximated.
+
+import os
+import sys
+import unicodedata
+
+SINGLE_BYTE = [(437, 'cp437'), (850, 'cp850'), (1250, 'cp1250'), (1252, 'cp1252')]
+# Python's `gbk` is code page 936 without the euro sign at 0x80.
+DOUBLE_BYTE = [(932, 'cp932', {}), (936, 'gbk', {0x80: '€'})]
+
+
+def decode_single(codec, byte, extra={}):
+    if byte in extra:
+        return extra[byte]
+    try:
+        s = bytes([byte]).decode(codec)
+        return s if len(s) == 1 else None
+    except UnicodeDecodeError:
+        return None
+
+
+def read_best_fit(number, decoded):
+    """The mappings of the WCTABLE of bestfit<number>.txt, whose bytes decode to another character."""
+    if len(sys.argv) < 2:
+        return []
+    pairs = []
+    in_wctable = False
+    with open(os.path.join(sys.argv[1], 'bestfit%d.txt' % number), encoding='latin-1') as file:
+        for line in file:
+            fields = line.split(';')[0].split()
+            if not fields:
+                continue
+            if not fields[0].lower().startswith('0x'):
+                in_wctable = fields[0].upper() == 'WCTABLE'
+                continue
+            if in_wctable:
+                wide, encoded = int(fields[0], 16), int(fields[1], 16)
+                if decoded.get(encoded) != wide:
+                    pairs.append((wide, encoded))
+    return sorted(pairs)
+
+
+def print_best_fit(number, decoded):
+    pairs = read_best_fit(number, decoded)
+    print('/// The best fit mappings of code page %d: characters, that the code page lacks, and the bytes, that' % number)
+    print('/// `WideCharToMultiByte` writes for them. Two bytes are `lead << 8 | trail`. Sorted by the first element.')
+    print('/// Empty, if the script ran without bestfit%d.txt.' % number)
+    print('pub(super) const CP%d_BEST_FIT: [(u16, u16); %d] = [' % (number, len(pairs)))
+    for i in range(0, len(pairs), 6):
+        print('    ' + ' '.join('(0x%04x, 0x%04x),' % pair for pair in pairs[i:i + 6]))
+    print('];')
+    print()
+
+
+def u16_array(values):
+    lines = []
+    for i in range(0, len(values), 8):
+        lines.append('    ' + ' '.join('0x%04x,' % v for v in values[i:i + 8]))
+    return '\n'.join(lines)
+
+
+def rust_char(c):
+    if c == '\0':
+        return '\\0'
+    if c in '\\"':
+        return '\\' + c
+    if unicodedata.category(c)[0] in 'CZM':
+        return '\\u{%04x}' % ord(c)
+    return c
+
+
+print('// Generated by scripts/gen-codepage-tables.py from the codecs of Python and the best fit tables of Microsoft. Do not edit.')
+print()
+for number, codec in SINGLE_BYTE:
+    # Windows maps bytes without a character to the code point with the same number.
+    values = [ord(decode_single(codec, b) or chr(b)) for b in range(0x80, 0x100)]
+    print('/// The characters of the bytes 0x80..=0xFF of code page %d.' % number)
+    print('pub(super) const CP%d_HIGH: [u16; 128] = [' % number)
+    print(u16_array(values))
+    print('];')
+    print()
+    decoded = {b: b for b in range(0x80)}
+    decoded.update((0x80 + i, v) for i, v in enumerate(values))
+    print_best_fit(number, decoded)
+
+for number, codec, extra in DOUBLE_BYTE:
+    leads = []
+    for lead in range(0x81, 0xff):
+        chars = []
+        for trail in range(0x40, 0xff):
+            try:
+                s = bytes([lead, trail]).decode(codec)
+                chars.append(s if len(s) == 1 else '\0')
+            except UnicodeDecodeError:
+                chars.append('\0')
+        leads.append(chars if any(c != '\0' for c in chars) else [])
+    singles = []
+    for b in range(0x80, 0x100):
+        is_lead = 0x81 <= b <= 0xfe and leads[b - 0x81]
+        singles.append(0 if is_lead else ord(decode_single(codec, b, extra) or '\0'))
+    print('/// The characters of the single bytes 0x80..=0xFF of code page %d. Zero means no character.' % number)
+    print('pub(super) const CP%d_HIGH: [u16; 128] = [' % number)
+    print(u16_array(singles))
+    print('];')
+    print()
+    print('/// For each lead byte 0x81..=0xFE of code page %d the characters of the trail bytes 0x40..=0xFE.' % number)
+    print('/// `\\0` means no character. The string is empty, if the byte is no lead byte.')
+    print('pub(super) const CP%d_DOUBLE: [&str; 126] = [' % number)
+    for chars in leads:
+        print('    "%s",' % ''.join(rust_char(c) for c in chars))
+    print('];')
+    print()
+    decoded = {b: b for b in range(0x80)}
+    decoded.update((0x80 + i, v) for i, v in enumerate(singles) if v != 0)
+    for i, chars in enumerate(leads):
+        decoded.update(((0x81 + i) << 8 | (0x40 + j), ord(c)) for j, c in enumerate(chars) if c != '\0')
+    print_best_fit(number, decoded)
+
+# Latin letters with diacritics are written as the letter without them, if the code page lacks the letter.
+latin = []
+for cp in list(range(0xc0, 0x250)) + list(range(0x1e00, 0x1f00)):
+    base = unicodedata.normalize('NFD', chr(cp))[0]
+    if base != chr(cp) and base.isascii() and base.isalpha():
+        latin.append((cp, ord(base)))
+print('/// Latin letters with diacritics and the ASCII letters without them. Sorted by the first element.')
+print('pub(super) const LATIN_BASE_LETTERS: [(u16, u8); %d] = [' % len(latin))
+for i in range(0, len(latin), 6):
+    print('    ' + ' '.join('(0x%04x, b\'%s\'),' % (cp, chr(base)) for cp, base in latin[i:i + 6]))
+print('];')
@@ -0,0 +1,131 @@
+#!/usr/bin/env python3
+
+# Generates the shortcuts in tests/fixtures, that the tests of src/shelllink.rs read.
+# The layout follows MS-SHLLINK. Run it from the root of the repository:
+#   python3 scripts/gen-lnk-fixtures.py
+
+import os
+import struct
+
+LINK_CLSID = bytes.fromhex('0114020000000000c000000000000046')
+
+HAS_LINK_TARGET_ID_LIST = 0x1
+HAS_LINK_INFO = 0x2
+HAS_NAME = 0x4
+HAS_WORKING_DIR = 0x10
+HAS_ARGUMENTS = 0x20
+IS_UNICODE = 0x80
+HAS_EXP_STRING = 0x200
+
+MAX_PATH = 260
+
+
+def header(flags):
+    return (struct.pack('<I', 0x4c) + LINK_CLSID + struct.pack('<II', flags, 0x20)
+            + bytes(24) + struct.pack('<IIIHHII', 0, 0, 1, 0, 0, 0, 0))
+
+
+def id_list():
+    # Only the terminal item.
+    return struct.pack('<HH', 2, 0)
+
+
+def ansi_z(text, codec):
+    return text.encode(codec) + b'\0'
+
+
+def unicode_z(text):
+    return text.encode('utf-16-le') + b'\0\0'
+
+
+def link_info(local_base_path, network_name, suffix, unicode, codec):
+    """A LinkInfo with a VolumeID and a local base path, and a CommonNetworkRelativeLink."""
+    header_size = 0x24 if unicode else 0x1c
+
+    volume_id = struct.pack('<IIII', 0x11, 3, 0x12345678, 0x10) + b'\0'
+
+    # NetNameOffset > 0x14 means, that the unicode offsets are present.
+    net_header_size = 0x1c if unicode else 0x14
+    net_name = ansi_z(network_name, codec)
+    device_name = ansi_z('Z:', codec)
+    net_body = net_name + device_name
+    if unicode:
+        net_name_unicode_offset = net_header_size + len(net_body)
+        net_body += unicode_z(network_name)
+        device_name_unicode_offset = net_header_size + len(net_body)
+        net_body += unicode_z('Z:')
+    net_fields = struct.pack('<IIIII', net_header_size + len(net_body), 1, net_header_size,
+                             net_header_size + len(net_name), 0x20000)
+    if unicode:
+        net_fields += struct.pack('<II', net_name_unicode_offset, device_name_unicode_offset)
+    network = net_fields + net_body
+
+    body = b''
+    volume_id_offset = header_size + len(body)
+    body += volume_id
+    local_base_path_offset = header_size + len(body)
+    body += ansi_z(local_base_path, codec)
+    network_offset = header_size + len(body)
+    body += network
+    suffix_offset = header_size + len(body)
+    body += ansi_z(suffix, codec)
+    fields = [volume_id_offset, local_base_path_offset, network_offset, suffix_offset]
+    if unicode:
+        local_base_path_unicode_offset = header_size + len(body)
+        body += unicode_z(local_base_path)
+        suffix_unicode_offset = header_size + len(body)
+        body += unicode_z(suffix)
+        fields += [local_base_path_unicode_offset, suffix_unicode_offset]
+    return struct.pack('<III', header_size + len(body), header_size, 0x3) + struct.pack('<%dI' % len(fields), *fields) + body
+
+
+def string_data(text, unicode, codec):
+    if unicode:
+        return struct.pack('<H', len(text)) + text.encode('utf-16-le')
+    encoded = text.encode(codec)
+    return struct.pack('<H', len(encoded)) + encoded
+
+
+def environment_variable_block(target_ansi, target_unicode, codec):
+    ansi = target_ansi.encode(codec).ljust(MAX_PATH, b'\0')
+    unicode = target_unicode.encode('utf-16-le').ljust(MAX_PATH * 2, b'\0')
+    return struct.pack('<II', 8 + MAX_PATH * 3, 0xa0000001) + ansi + unicode
+
+
+def shortcut(unicode, codec, local_base_path, network_name, suffix, strings, env_ansi, env_unicode):
+    flags = HAS_LINK_TARGET_ID_LIST | HAS_LINK_INFO | HAS_NAME | HAS_WORKING_DIR | HAS_ARGUMENTS | HAS_EXP_STRING
+    if unicode:
+        flags |= IS_UNICODE
+    data = header(flags) + id_list() + link_info(local_base_path, network_name, suffix, unicode, codec)
+    for text in strings:
+        data += string_data(text, unicode, codec)
+    data += environment_variable_block(env_ansi, env_unicode, codec)
+    # The terminal block.
+    data += struct.pack('<I', 0)
+    return data
+
+
+FIXTURES = {
+    # Unicode strings, the unicode offsets of the LinkInfo, and the TargetUnicode of the environment block.
+    'unicode.lnk': shortcut(
+        True, 'cp1252', 'C:\\Program Files\\', '\\\\server\\share', 'App\\app.exe',
+        ['Start the app', '%USERPROFILE%\\Documents', '--open "a b.txt" --ünïcödé'],
+        '', '%ProgramFiles%\\App\\app.exe'),
+    # Strings in code page 1252, the ANSI offsets, and only the TargetAnsi of the environment block.
+    'ansi.lnk': shortcut(
+        False, 'cp1252', 'C:\\Café\\', '\\\\server\\share', 'tool.exe',
+        ['Café tool', 'C:\\Café', '/x "é"'],
+        '%SystemDrive%\\Café\\tool.exe', ''),
+}
+
+
+def main():
+    directory = os.path.join('tests', 'fixtures')
+    os.makedirs(directory, exist_ok=True)
+    for name, data in FIXTURES.items():
+        with open(os.path.join(directory, name), 'wb') as file:
+            file.write(data)
+
+
+if __name__ == '__main__':
+    main()
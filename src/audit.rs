@@ -0,0 +1,389 @@
+use crate::commandline::parse_lp_cmd_line;
+use crate::envblock::same_name;
+use crate::exesearch::prefixes;
+use crate::shellcmd::expand_variables;
+
+const QUOTE: u16 = b'"' as u16;
+const SPACE: u16 = b' ' as u16;
+const BACKSLASH: u16 = b'\\' as u16;
+const SLASH: u16 = b'/' as u16;
+const DOT: u16 = b'.' as u16;
+
+/// The variables, that are expanded, if they are not given with `--env`.
+const DEFAULT_ENV: [(&str, &str); 7] = [
+    ("SystemRoot", "C:\\Windows"),
+    ("windir", "C:\\Windows"),
+    ("SystemDrive", "C:"),
+    ("ProgramFiles", "C:\\Program Files"),
+    ("ProgramFiles(x86)", "C:\\Program Files (x86)"),
+    ("CommonProgramFiles", "C:\\Program Files\\Common Files"),
+    ("ProgramData", "C:\\ProgramData"),
+];
+
+/// The extensions, that `CreateProcessW` can run.
+const EXECUTABLE_EXTENSIONS: [&str; 4] = [".EXE", ".COM", ".BAT", ".CMD"];
+
+/// The types of services, that are drivers. Their `ImagePath` is not started with `CreateProcessW`.
+const SERVICE_KERNEL_DRIVER: u32 = 1;
+const SERVICE_FILE_SYSTEM_DRIVER: u32 = 2;
+
+pub(super) enum Source {
+    /// The value `ImagePath` of a service in a `.reg` file.
+    Service { key: String },
+    /// An `<Exec>` action of a scheduled task.
+    Task { uri: String, action: usize },
+}
+
+impl Source {
+    pub(super) fn as_str(&self) -> &'static str {
+        match self {
+            Source::Service { .. } => "service",
+            Source::Task { .. } => "task",
+        }
+    }
+
+    pub(super) fn describe(&self) -> String {
+        match self {
+            Source::Service { key } => format!("{}\\ImagePath", key),
+            Source::Task { uri, action } => format!("{}, action {}", uri, action),
+        }
+    }
+}
+
+pub(super) struct AuditEntry {
+    pub(super) file: String,
+    pub(super) source: Source,
+    /// The command line, after the environment variables are expanded.
+    pub(super) cmdline: Vec<u16>,
+    pub(super) args: Vec<Vec<u16>>,
+    /// Whether arg 0 is in quotes.
+    pub(super) quoted: bool,
+    /// The program, that is most likely meant: the first name with an extension.
+    pub(super) program: Vec<u16>,
+    /// The names, that `CreateProcessW` tries before the program. Creating one of them hijacks the launch.
+    pub(super) hijack_names: Vec<Vec<u16>>,
+}
+
+impl AuditEntry {
+    pub(super) fn vulnerable(&self) -> bool {
+        !self.hijack_names.is_empty()
+    }
+}
+
+/// A value of a `.reg` file.
+enum RegData {
+    Sz(Vec<u16>),
+    ExpandSz(Vec<u16>),
+    Dword(u32),
+    Other,
+}
+
+/// Decodes a file with a UTF-16LE, UTF-16BE or UTF-8 byte order mark. Without one, the file must be UTF-8.
+pub(super) fn decode(bytes: &[u8]) -> Result<Vec<u16>, String> {
+    let utf16 = |bytes: &[u8], from: fn([u8; 2]) -> u16| bytes.chunks_exact(2).map(|c| from([c[0], c[1]])).collect();
+    match bytes {
+        [0xff, 0xfe, rest @ ..] => Ok(utf16(rest, u16::from_le_bytes)),
+        [0xfe, 0xff, rest @ ..] => Ok(utf16(rest, u16::from_be_bytes)),
+        [0xef, 0xbb, 0xbf, rest @ ..] | rest => match std::str::from_utf8(rest) {
+            Ok(text) => Ok(text.encode_utf16().collect()),
+            Err(error) => Err(format!("The file is neither UTF-16 with a byte order mark, nor UTF-8: {}", error)),
+        },
+    }
+}
+
+fn starts_with(text: &[u16], prefix: &str) -> bool {
+    let prefix : Vec<u16> = prefix.encode_utf16().collect();
+    text.starts_with(&prefix)
+}
+
+pub(super) fn trim(mut text: &[u16]) -> &[u16] {
+    while let [first, rest @ ..] = text {
+        if !char::from_u32(u32::from(*first)).is_some_and(char::is_whitespace) { break; }
+        text = rest;
+    }
+    while let [rest @ .., last] = text {
+        if !char::from_u32(u32::from(*last)).is_some_and(char::is_whitespace) { break; }
+        text = rest;
+    }
+    text
+}
+
+/// Lines of a `.reg` file, with lines ending in `\` joined to the next line.
+fn reg_lines(text: &[u16]) -> Vec<Vec<u16>> {
+    let mut lines : Vec<Vec<u16>> = vec![];
+    let mut continued = false;
+    for line in text.split(|&c| c == b'\n' as u16) {
+        let line = trim(line);
+        let (line, continues) = match line.split_last() {
+            Some((&BACKSLASH, rest)) => (rest, true),
+            _ => (line, false),
+        };
+        match lines.last_mut() {
+            Some(last) if continued => last.extend_from_slice(line),
+            _ => lines.push(line.to_vec()),
+        }
+        continued = continues;
+    }
+    lines
+}
+
+/// Parses `"..."` with the escapes `\\` and `\"`, and returns the string and the rest.
+fn reg_string(text: &[u16]) -> Option<(Vec<u16>, &[u16])> {
+    if text.first() != Some(&QUOTE) {
+        return None;
+    }
+    let mut string : Vec<u16> = vec![];
+    let mut i = 1;
+    while i < text.len() {
+        match text[i] {
+            BACKSLASH if i + 1 < text.len() => {
+                string.push(text[i + 1]);
+                i += 2;
+            },
+            QUOTE => return Some((string, &text[i + 1..])),
+            c => {
+                string.push(c);
+                i += 1;
+            },
+        }
+    }
+    None
+}
+
+fn reg_hex_bytes(text: &[u16]) -> Vec<u8> {
+    String::from_utf16_lossy(text)
+        .split(',')
+        .filter_map(|byte| u8::from_str_radix(byte.trim(), 16).ok())
+        .collect()
+}
+
+/// Parses the data after `=`. `REGEDIT4` files store `hex(2)` in the ANSI code page, newer ones in UTF-16LE.
+fn reg_data(text: &[u16], unicode: bool) -> RegData {
+    if let Some((string, _)) = reg_string(text) {
+        return RegData::Sz(string);
+    }
+    let text_str = String::from_utf16_lossy(text);
+    if let Some(hex) = text_str.strip_prefix("hex(2):") {
+        let bytes = reg_hex_bytes(&hex.encode_utf16().collect::<Vec<u16>>());
+        let mut units : Vec<u16> =
+            if unicode { bytes.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect() }
+            else { bytes.iter().map(|&b| u16::from(b)).collect() };
+        while units.last() == Some(&0) {
+            units.pop();
+        }
+        return RegData::ExpandSz(units);
+    }
+    if let Some(dword) = text_str.strip_prefix("dword:") {
+        if let Ok(dword) = u32::from_str_radix(dword.trim(), 16) {
+            return RegData::Dword(dword);
+        }
+    }
+    RegData::Other
+}
+
+fn is_service_key(key: &str) -> bool {
+    let upper = key.to_uppercase();
+    match upper.rfind("\\SERVICES\\") {
+        Some(index) => !upper[index + "\\SERVICES\\".len()..].contains('\\'),
+        None => false,
+    }
+}
+
+/// The command lines of the services in a `.reg` file: the `ImagePath` of each key below `...\Services`,
+/// unless the service is a driver.
+fn reg_command_lines(text: &[u16], env: &[(Vec<u16>, Vec<u16>)]) -> Vec<(Source, Vec<u16>)> {
+    let unicode = !starts_with(text, "REGEDIT4");
+    let mut found : Vec<(Source, Vec<u16>)> = vec![];
+    let mut key : Option<String> = None;
+    let mut image_path : Option<Vec<u16>> = None;
+    let mut service_type : Option<u32> = None;
+    let mut flush = |key: &Option<String>, image_path: Option<Vec<u16>>, service_type: Option<u32>| {
+        let is_driver = matches!(service_type, Some(SERVICE_KERNEL_DRIVER) | Some(SERVICE_FILE_SYSTEM_DRIVER));
+        if let (Some(key), Some(image_path), false) = (key, image_path, is_driver) {
+            found.push((Source::Service { key: key.clone() }, image_path));
+        }
+    };
+    for line in reg_lines(text) {
+        if line.first() == Some(&(b'[' as u16)) && line.last() == Some(&(b']' as u16)) {
+            flush(&key, image_path.take(), service_type.take());
+            let name = String::from_utf16_lossy(&line[1..line.len() - 1]);
+            key = if is_service_key(&name) { Some(name) } else { None };
+            continue;
+        }
+        if key.is_none() {
+            continue;
+        }
+        let (name, rest) = match reg_string(&line) {
+            Some(parsed) => parsed,
+            None => continue,
+        };
+        let data = match rest.split_first() {
+            Some((&c, data)) if c == b'=' as u16 => reg_data(trim(data), unicode),
+            _ => continue,
+        };
+        let name = String::from_utf16_lossy(&name);
+        match data {
+            RegData::Sz(value) if name.eq_ignore_ascii_case("ImagePath") => image_path = Some(value),
+            RegData::ExpandSz(value) if name.eq_ignore_ascii_case("ImagePath") => {
+                image_path = Some(expand_variables(&value, env, &mut vec![]));
+            },
+            RegData::Dword(value) if name.eq_ignore_ascii_case("Type") => service_type = Some(value),
+            _ => {},
+        }
+    }
+    flush(&key, image_path, service_type);
+    found
+}
+
+/// Replaces the entities of XML.
+pub(super) fn xml_unescape(text: &[u16]) -> Vec<u16> {
+    let text = String::from_utf16_lossy(text);
+    let mut unescaped = String::new();
+    let mut rest = text.as_str();
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = match rest.find(';') {
+            Some(end) => end,
+            None => break,
+        };
+        let entity = &rest[1..end];
+        let c = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => match (entity.strip_prefix("#x"), entity.strip_prefix('#')) {
+                (Some(hex), _) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+                (None, Some(decimal)) => decimal.parse::<u32>().ok().and_then(char::from_u32),
+                (None, None) => None,
+            },
+        };
+        match c {
+            Some(c) => {
+                unescaped.push(c);
+                rest = &rest[end + 1..];
+            },
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            },
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped.encode_utf16().collect()
+}
+
+fn find(text: &[u16], needle: &[u16], from: usize) -> Option<usize> {
+    text.get(from..)?.windows(needle.len()).position(|window| window == needle).map(|position| from + position)
+}
+
+/// The attributes and the text of each element `<name ...>...</name>` in the text.
+pub(super) fn xml_elements_with_attributes<'a>(text: &'a [u16], name: &str) -> Vec<(&'a [u16], &'a [u16])> {
+    let open : Vec<u16> = format!("<{}", name).encode_utf16().collect();
+    let close : Vec<u16> = format!("</{}>", name).encode_utf16().collect();
+    let mut elements : Vec<(&[u16], &[u16])> = vec![];
+    let mut offset = 0;
+    while let Some(start) = find(text, &open, offset) {
+        let after_name = text.get(start + open.len()).copied();
+        let tag_end = match find(text, &[b'>' as u16], start) {
+            Some(tag_end) => tag_end,
+            None => break,
+        };
+        offset = tag_end + 1;
+        if !matches!(after_name.and_then(|c| char::from_u32(u32::from(c))), Some('>' | ' ' | '\t' | '\r' | '\n' | '/')) {
+            continue;
+        }
+        let attributes = &text[start + open.len()..tag_end];
+        if attributes.last() == Some(&SLASH) {
+            elements.push((&attributes[..attributes.len() - 1], &text[0..0]));
+            continue;
+        }
+        let end = match find(text, &close, offset) {
+            Some(end) => end,
+            None => break,
+        };
+        elements.push((attributes, &text[offset..end]));
+        offset = end + close.len();
+    }
+    elements
+}
+
+/// The text of each element `<name>...</name>` in the text. Attributes are allowed.
+fn xml_elements<'a>(text: &'a [u16], name: &str) -> Vec<&'a [u16]> {
+    xml_elements_with_attributes(text, name).into_iter().map(|(_, text)| text).collect()
+}
+
+/// The command lines of the `<Exec>` actions of a scheduled task. The Task Scheduler puts `<Command>` and
+/// `<Arguments>` together with a space, and doesn't add quotes, so the command must be quoted in the XML.
+fn task_command_lines(text: &[u16], file: &str, env: &[(Vec<u16>, Vec<u16>)]) -> Vec<(Source, Vec<u16>)> {
+    let uri = match xml_elements(text, "URI").first() {
+        Some(uri) => String::from_utf16_lossy(&xml_unescape(uri)),
+        None => file.to_owned(),
+    };
+    xml_elements(text, "Exec").into_iter().enumerate().filter_map(|(action, exec)| {
+        let command = xml_unescape(trim(xml_elements(exec, "Command").first()?));
+        let mut cmdline = expand_variables(&command, env, &mut vec![]);
+        if let Some(arguments) = xml_elements(exec, "Arguments").first() {
+            let arguments = xml_unescape(trim(arguments));
+            if !arguments.is_empty() {
+                cmdline.push(SPACE);
+                cmdline.extend(expand_variables(&arguments, env, &mut vec![]));
+            }
+        }
+        Some((Source::Task { uri: uri.clone(), action }, cmdline))
+    }).collect()
+}
+
+fn file_name(name: &[u16]) -> &[u16] {
+    let file_name_start = name.iter().rposition(|&c| c == BACKSLASH || c == SLASH).map_or(0, |p| p + 1);
+    &name[file_name_start..]
+}
+
+fn has_extension(name: &[u16]) -> bool {
+    file_name(name).contains(&DOT)
+}
+
+fn has_executable_extension(name: &[u16]) -> bool {
+    let file_name = String::from_utf16_lossy(file_name(name)).to_uppercase();
+    EXECUTABLE_EXTENSIONS.iter().any(|extension| file_name.ends_with(extension))
+}
+
+/// Splits the command line and finds the names, that are tried before the program.
+fn audit_cmdline(file: &str, source: Source, cmdline: Vec<u16>) -> AuditEntry {
+    let args : Vec<Vec<u16>> = parse_lp_cmd_line(&cmdline, true).iter().map(|arg| arg.to_utf16()).collect();
+    let (quoted, prefixes) = prefixes(&cmdline);
+    // `C:\Program Files\App 2.0\app.exe` has a dot in `App 2.0`, so an executable extension is preferred.
+    let intended = prefixes.iter().position(|prefix| has_executable_extension(&cmdline[prefix.range.clone()]))
+        .or_else(|| prefixes.iter().position(|prefix| has_extension(&cmdline[prefix.range.clone()])))
+        .unwrap_or(0);
+    let program = prefixes.get(intended).map(|prefix| prefix.name.clone()).unwrap_or_default();
+    let hijack_names = prefixes[..intended].iter().map(|prefix| prefix.name.clone()).collect();
+    AuditEntry { file: file.to_owned(), source, cmdline, args, quoted, program, hijack_names }
+}
+
+/// Audits a `.reg` export or a Task Scheduler XML file. `env` is used to expand `REG_EXPAND_SZ` values and
+/// the commands of tasks. Variables, that are not in `env`, get a default value like `C:\Windows`.
+pub(super) fn audit_file(bytes: &[u8], file: &str, env: &[(Vec<u16>, Vec<u16>)]) -> Result<Vec<AuditEntry>, String> {
+    let text = decode(bytes)?;
+    let mut env : Vec<(Vec<u16>, Vec<u16>)> = env.to_vec();
+    for (name, value) in DEFAULT_ENV {
+        let name : Vec<u16> = name.encode_utf16().collect();
+        if !env.iter().any(|(n, _)| same_name(n, &name)) {
+            env.push((name, value.encode_utf16().collect()));
+        }
+    }
+    let trimmed = trim(&text);
+    let command_lines =
+        if starts_with(trimmed, "Windows Registry Editor") || starts_with(trimmed, "REGEDIT4") {
+            reg_command_lines(trimmed, &env)
+        }
+        else if !xml_elements(trimmed, "Task").is_empty() {
+            task_command_lines(trimmed, file, &env)
+        }
+        else {
+            return Err(format!("»{}« is neither a .reg export nor the XML of a scheduled task", file));
+        };
+    Ok(command_lines.into_iter().map(|(source, cmdline)| audit_cmdline(file, source, cmdline)).collect())
+}
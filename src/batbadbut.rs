@@ -0,0 +1,287 @@
+use std::ops::Range;
+
+use crate::commandline::parse_lp_cmd_line;
+use crate::invocation::bash_word;
+use crate::lint::cmd_tokens;
+
+const BACKSLASH: u16 = b'\\' as u16;
+const QUOTE: u16 = b'"' as u16;
+const CARET: u16 = b'^' as u16;
+const PERCENT: u16 = b'%' as u16;
+const AMPERSAND: u16 = b'&' as u16;
+const PIPE: u16 = b'|' as u16;
+const LESS: u16 = b'<' as u16;
+const GREATER: u16 = b'>' as u16;
+const LF: u16 = b'\n' as u16;
+const CR: u16 = b'\r' as u16;
+
+/// The value of `%ComSpec%` on a typical installation.
+pub(super) const DEFAULT_COMSPEC: &str = "C:\\Windows\\system32\\cmd.exe";
+
+/// How cmd.exe reinterprets a code unit of an argument.
+pub(super) enum IssueKind {
+    /// `&` or `|` outside of quotes ends the batch file call and starts another command.
+    CommandSeparator,
+    /// `<` or `>` outside of quotes redirects input or output.
+    Redirection,
+    /// `^` outside of quotes escapes the next character and is removed.
+    Caret,
+    /// `%NAME%` is expanded, even inside of quotes.
+    Variable(String),
+    /// A literal quote toggles the quoting of cmd.exe for the following text.
+    QuoteToggle,
+    /// The rest of the command line after a line feed is ignored.
+    Newline,
+}
+
+impl IssueKind {
+    pub(super) fn as_str(&self) -> &'static str {
+        match self {
+            IssueKind::CommandSeparator => "command-separator",
+            IssueKind::Redirection => "redirection",
+            IssueKind::Caret => "caret",
+            IssueKind::Variable(_) => "variable",
+            IssueKind::QuoteToggle => "quote-toggle",
+            IssueKind::Newline => "newline",
+        }
+    }
+
+    /// A quote only changes, how the following text is seen. The other kinds change what is executed.
+    pub(super) fn is_unsafe(&self) -> bool {
+        !matches!(self, IssueKind::QuoteToggle)
+    }
+
+    pub(super) fn describe(&self) -> String {
+        match self {
+            IssueKind::CommandSeparator => "is outside of quotes for cmd.exe and starts another command".to_owned(),
+            IssueKind::Redirection => "is outside of quotes for cmd.exe and redirects input or output".to_owned(),
+            IssueKind::Caret => "is outside of quotes for cmd.exe and escapes the next character".to_owned(),
+            IssueKind::Variable(name) => format!("expands the environment variable »{}«, even inside of quotes", name),
+            IssueKind::QuoteToggle => "is a literal quote, but toggles the quoting of cmd.exe".to_owned(),
+            IssueKind::Newline => "ends the command, the rest of the command line is ignored".to_owned(),
+        }
+    }
+}
+
+pub(super) struct Issue {
+    pub(super) kind: IssueKind,
+    /// The index of the UTF-16 code unit in the command line.
+    pub(super) position: usize,
+}
+
+pub(super) struct ArgReport {
+    pub(super) number: usize,
+    pub(super) arg: Vec<u16>,
+    pub(super) range: Range<usize>,
+    pub(super) issues: Vec<Issue>,
+}
+
+pub(super) struct BatchReport {
+    pub(super) cmdline: Vec<u16>,
+    /// The command line of cmd.exe, that runs the batch file.
+    pub(super) cmd_cmdline: Vec<u16>,
+    pub(super) args: Vec<ArgReport>,
+    /// The issues in argument zero or between the arguments.
+    pub(super) other_issues: Vec<Issue>,
+    /// The commands after the first command separator, with the carets removed.
+    pub(super) injected_commands: Vec<Vec<u16>>,
+    /// True, if no issue anywhere in the command line changes what is executed.
+    pub(super) safe: bool,
+    /// An invocation of create-process-rust for git-bash, that starts the batch file with this command line.
+    pub(super) reproduction: String,
+}
+
+/// How CreateProcessW starts a batch file: it runs cmd.exe with a new command line.
+pub(super) struct CmdWrapping {
+    pub(super) batch_file: Vec<u16>,
+    /// The program, that is actually started.
+    pub(super) comspec: Vec<u16>,
+    /// The command line, that cmd.exe gets.
+    pub(super) cmd_cmdline: Vec<u16>,
+    /// What cmd.exe runs after removing the first and the last quote after `/c`.
+    pub(super) command: Vec<u16>,
+    /// The commands, that cmd.exe sees, with the carets removed.
+    pub(super) commands: Vec<Vec<u16>>,
+    /// The parameters `%0`, `%1`, ... of the batch file, taken from the first command.
+    pub(super) parameters: Vec<Vec<u16>>,
+    /// The positions are indices into `command`.
+    pub(super) issues: Vec<Issue>,
+}
+
+pub(super) fn is_batch_file(path: &[u16]) -> bool {
+    let name = String::from_utf16_lossy(path).to_lowercase();
+    name.ends_with(".bat") || name.ends_with(".cmd")
+}
+
+/// Emulates, how cmd.exe reads the command line of the batch file.
+/// Percent signs are expanded before anything else, so variables are found regardless of quotes.
+/// Then quotes toggle the quoting, and `^`, `&`, `|`, `<` and `>` are special outside of quotes.
+/// Returns the issues and all commands, trimmed.
+fn find_issues(cmdline: &[u16]) -> (Vec<Issue>, Vec<Vec<u16>>) {
+    let mut issues : Vec<Issue> = vec![];
+
+    let mut i = 0;
+    while i < cmdline.len() {
+        if cmdline[i] == PERCENT {
+            if let Some(len) = cmdline[i + 1..].iter().position(|&w| w == PERCENT) {
+                let name = &cmdline[i + 1..i + 1 + len];
+                if !name.is_empty() && !name.iter().any(|&w| w == LF || w == CR) {
+                    issues.push(Issue { kind: IssueKind::Variable(String::from_utf16_lossy(name)), position: i });
+                    i += len + 2;
+                    continue;
+                }
+            }
+        }
+        i += 1;
+    }
+
+    let mut commands : Vec<Vec<u16>> = vec![vec![]];
+    let mut in_quotes = false;
+    let mut i = 0;
+    while i < cmdline.len() {
+        let w = cmdline[i];
+        match w {
+            LF => {
+                issues.push(Issue { kind: IssueKind::Newline, position: i });
+                break;
+            },
+            CR => {},
+            QUOTE => {
+                in_quotes = !in_quotes;
+                commands.last_mut().unwrap().push(w);
+            },
+            CARET if !in_quotes => {
+                issues.push(Issue { kind: IssueKind::Caret, position: i });
+                i += 1;
+                if let Some(&next) = cmdline.get(i) {
+                    commands.last_mut().unwrap().push(next);
+                }
+            },
+            AMPERSAND | PIPE if !in_quotes => {
+                issues.push(Issue { kind: IssueKind::CommandSeparator, position: i });
+                // `&&` and `||` are one separator
+                if cmdline.get(i + 1) == Some(&w) {
+                    i += 1;
+                }
+                commands.push(vec![]);
+            },
+            LESS | GREATER if !in_quotes => {
+                issues.push(Issue { kind: IssueKind::Redirection, position: i });
+                commands.last_mut().unwrap().push(w);
+            },
+            _ => commands.last_mut().unwrap().push(w),
+        }
+        i += 1;
+    }
+
+    let trim = |command: &Vec<u16>| -> Vec<u16> {
+        let is_blank = |w: &u16| *w == b' ' as u16 || *w == b'\t' as u16;
+        let start = command.iter().position(|w| !is_blank(w)).unwrap_or(command.len());
+        let end = command.iter().rposition(|w| !is_blank(w)).map_or(start, |end| end + 1);
+        command[start..end].to_vec()
+    };
+    (issues, commands.iter().map(trim).collect())
+}
+
+/// `CreateProcessW` runs `%ComSpec% /c "<command line>"`.
+pub(super) fn wrap_in_cmd(comspec: &[u16], cmdline: &[u16]) -> Vec<u16> {
+    let mut cmd_cmdline : Vec<u16> = comspec.to_vec();
+    cmd_cmdline.extend(" /c \"".encode_utf16());
+    cmd_cmdline.extend_from_slice(cmdline);
+    cmd_cmdline.push(QUOTE);
+    cmd_cmdline
+}
+
+/// Predicts, how CreateProcessW and cmd.exe run a batch file.
+/// The image is the program, or the file found for it. If it is NULL, argument zero of the command line is taken.
+/// Returns `None`, if the image is not a batch file.
+///
+/// cmd.exe keeps the quotes after `/c` only, if there are exactly two of them and the text between them
+/// is the name of an existing executable file with whitespace in it. This is not emulated: the first and
+/// the last quote are removed, so cmd.exe runs the original command line.
+pub(super) fn predict_cmd_wrapping(image: Option<&[u16]>, cmdline: &[u16], comspec: &[u16]) -> Option<CmdWrapping> {
+    let batch_file : Vec<u16> = match image {
+        Some(image) => image.to_vec(),
+        None => parse_lp_cmd_line(cmdline, true).first()?.to_utf16(),
+    };
+    if !is_batch_file(&batch_file) {
+        return None;
+    }
+    let (mut issues, commands) = find_issues(cmdline);
+    issues.sort_by_key(|issue| issue.position);
+    let parameters = match commands.first() {
+        Some(first) => cmd_tokens(first).into_iter().map(|range| first[range].to_vec()).collect(),
+        None => vec![],
+    };
+    Some(CmdWrapping {
+        batch_file,
+        comspec: comspec.to_vec(),
+        cmd_cmdline: wrap_in_cmd(comspec, cmdline),
+        command: cmdline.to_vec(),
+        commands,
+        parameters,
+        issues,
+    })
+}
+
+/// Analyzes the command line, that is passed to CreateProcessW to run a batch file.
+/// The intended arguments are those, that `parse_lp_cmd_line` gets from the command line.
+/// If the arguments are given instead, the command line is put together with `cmdline_from_args` before.
+/// `comspec` is the value of `%ComSpec%`, that `CreateProcessW` runs.
+pub(super) fn analyze_cmdline(program: Option<&[u16]>, cmdline: &[u16], comspec: &[u16]) -> Result<BatchReport, String> {
+    let parsed = parse_lp_cmd_line(cmdline, true);
+    let arg_zero = parsed.first().ok_or_else(|| "The command line is empty".to_owned())?.to_utf16();
+    match program {
+        Some(program) if !is_batch_file(program) => return Err(format!("The program »{}« is not a batch file",
+                                                                     String::from_utf16_lossy(program))),
+        None if !is_batch_file(&arg_zero) => return Err(format!("Argument zero »{}« is not a batch file",
+                                                              String::from_utf16_lossy(&arg_zero))),
+        _ => {},
+    }
+
+    let (issues, commands) = find_issues(cmdline);
+    let injected_commands : Vec<Vec<u16>> = commands.into_iter().skip(1).filter(|c| !c.is_empty()).collect();
+    let mut args : Vec<ArgReport> = parsed.iter().skip(1).map(|arg| {
+        let mut issues : Vec<Issue> = vec![];
+        if arg.to_utf16().contains(&QUOTE) {
+            // A quote is literal for the C-Runtime, if an odd number of backslashes is in front of it.
+            let mut backslashes = 0;
+            for (i, &w) in arg.raw.iter().enumerate() {
+                if w == QUOTE && backslashes % 2 == 1 {
+                    issues.push(Issue { kind: IssueKind::QuoteToggle, position: arg.range.start + i });
+                }
+                backslashes = if w == BACKSLASH { backslashes + 1 } else { 0 };
+            }
+            // Two quotes inside of quotes are also a literal quote.
+            if issues.is_empty() {
+                issues.push(Issue { kind: IssueKind::QuoteToggle, position: arg.range.start });
+            }
+        }
+        ArgReport { number: arg.number, arg: arg.to_utf16(), range: arg.range.clone(), issues }
+    }).collect();
+    let mut other_issues : Vec<Issue> = vec![];
+    for issue in issues {
+        match args.iter_mut().find(|a| a.range.contains(&issue.position)) {
+            Some(report) => report.issues.push(issue),
+            None => other_issues.push(issue),
+        }
+    }
+    for report in &mut args {
+        report.issues.sort_by_key(|issue| issue.position);
+    }
+    other_issues.sort_by_key(|issue| issue.position);
+    let safe = injected_commands.is_empty()
+        && !args.iter().flat_map(|a| &a.issues).chain(&other_issues).any(|issue| issue.kind.is_unsafe());
+
+    let cmd_cmdline = wrap_in_cmd(comspec, cmdline);
+
+    let program_option = match program {
+        Some(program) => format!("--program {}", bash_word(&String::from_utf16_lossy(program))),
+        None => "--program-is-null".to_owned(),
+    };
+    let reproduction = format!("MSYS_NO_PATHCONV=1 ./create-process-rust.exe {} --cmd-line-in-arg {}",
+                               program_option, bash_word(&String::from_utf16_lossy(cmdline)));
+
+    Ok(BatchReport { cmdline: cmdline.to_vec(), cmd_cmdline, args, other_issues, injected_commands, safe, reproduction })
+}
+
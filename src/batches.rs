@@ -0,0 +1,67 @@
+use std::{
+    ffi::OsString,
+    ops::Range,
+    os::windows::ffi::OsStrExt,
+};
+
+use crate::commandline::{
+    append_arg,
+    parse_lp_cmd_line,
+};
+
+/// The maximum length of `lpCommandLine` including the terminating NUL character.
+pub(super) const MAX_CMDLINE_LEN: usize = 32767;
+
+pub(super) struct Batch {
+    pub(super) cmdline: Vec<u16>,
+    /// The indices of the variable arguments in this batch.
+    pub(super) args: Range<usize>,
+}
+
+/// Puts the variable arguments after the prefix into as few command lines as possible.
+/// Each command line including the terminating NUL character is at most `limit` UTF-16 code units long.
+/// The order of the arguments is kept, so filling each command line as much as possible gives the fewest command lines.
+/// Each command line is split again, to check that it has the arguments of the prefix and of the batch.
+pub(super) fn pack_args(prefix: &[u16], args: &[OsString], limit: usize) -> Result<Vec<Batch>, String> {
+    if prefix.is_empty() {
+        return Err("The prefix is empty, it must contain at least the program".to_owned());
+    }
+    if prefix.len() + 1 > limit {
+        return Err(format!("The prefix has {} characters, so it does not fit into the limit of {} characters \
+                            including the terminating NUL character", prefix.len(), limit));
+    }
+    let prefix_args : Vec<Vec<u16>> = parse_lp_cmd_line(prefix, true).iter().map(|a| a.to_utf16()).collect();
+
+    let mut batches : Vec<Batch> = vec![];
+    let mut cmdline : Vec<u16> = prefix.to_vec();
+    let mut start = 0;
+    for (index, arg) in args.iter().enumerate() {
+        let mut quoted : Vec<u16> = vec![b' ' as u16];
+        append_arg(&mut quoted, arg, false, false)?;
+        if prefix.len() + quoted.len() + 1 > limit {
+            return Err(format!("Argument {} »{}« does not fit into the limit of {} characters: \
+                                with the prefix, the command line has {} characters including the terminating NUL character",
+                               index, arg.to_string_lossy(), limit, prefix.len() + quoted.len() + 1));
+        }
+        if cmdline.len() + quoted.len() + 1 > limit {
+            batches.push(Batch { cmdline: std::mem::replace(&mut cmdline, prefix.to_vec()), args: start..index });
+            start = index;
+        }
+        cmdline.extend_from_slice(&quoted);
+    }
+    if start < args.len() {
+        batches.push(Batch { cmdline, args: start..args.len() });
+    }
+
+    for (n, batch) in batches.iter().enumerate() {
+        let expected : Vec<Vec<u16>> = prefix_args.iter().cloned()
+            .chain(args[batch.args.clone()].iter().map(|a| a.encode_wide().collect()))
+            .collect();
+        let actual : Vec<Vec<u16>> = parse_lp_cmd_line(&batch.cmdline, true).iter().map(|a| a.to_utf16()).collect();
+        if expected != actual {
+            return Err(format!("Command line {} is not split into the prefix and its arguments. \
+                                Does the prefix end inside of quotes?", n + 1));
+        }
+    }
+    Ok(batches)
+}
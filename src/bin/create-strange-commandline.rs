@@ -1,3 +1,6 @@
+// Like the main program, this only builds on Windows.
+#![cfg(windows)]
+
 use std::ffi::{OsStr,OsString};
 use std::os::windows::ffi::{OsStrExt,OsStringExt};
 use std::path::PathBuf;
@@ -0,0 +1,320 @@
+use std::{
+    collections::HashMap,
+    sync::OnceLock,
+};
+
+use crate::codepage_tables::*;
+
+const BACKSLASH: u8 = b'\\';
+const QUOTE: u8 = b'"';
+const TAB: u8 = b'\t';
+const SPACE: u8 = b' ';
+
+pub(super) const SUPPORTED_CODE_PAGES: [u32; 7] = [437, 850, 932, 936, 1250, 1252, 65001];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum SubstitutionKind {
+    BestFit,
+    DefaultChar,
+}
+
+impl SubstitutionKind {
+    pub(super) fn as_str(&self) -> &'static str {
+        match self {
+            SubstitutionKind::BestFit => "best-fit",
+            SubstitutionKind::DefaultChar => "default-char",
+        }
+    }
+}
+
+/// A character, that has no exact mapping in the code page.
+pub(super) struct Substitution {
+    /// The index of the first UTF-16 code unit of the character.
+    pub(super) position: usize,
+    /// The UTF-16 code units of the character. A lone surrogate is one code unit.
+    pub(super) units: Vec<u16>,
+    pub(super) bytes: Vec<u8>,
+    pub(super) kind: SubstitutionKind,
+}
+
+pub(super) struct Narrow {
+    pub(super) bytes: Vec<u8>,
+    pub(super) substitutions: Vec<Substitution>,
+}
+
+/// The character of each byte. Two-byte characters are stored as `lead << 8 | trail`.
+struct Table {
+    decode: HashMap<u16, u16>,
+    encode: HashMap<u16, u16>,
+    lead_bytes: [bool; 256],
+}
+
+fn build_table(high: &[u16; 128], double: Option<&[&str; 126]>) -> Table {
+    let mut decode : HashMap<u16, u16> = (0u16..0x80).map(|b| (b, b)).collect();
+    let mut lead_bytes = [false; 256];
+    for (i, &w) in high.iter().enumerate() {
+        if w != 0 {
+            decode.insert(0x80 + i as u16, w);
+        }
+    }
+    for (i, trails) in double.into_iter().flatten().enumerate() {
+        let lead = 0x81 + i as u16;
+        lead_bytes[usize::from(lead)] = !trails.is_empty();
+        for (j, c) in trails.chars().enumerate() {
+            if c != '\0' {
+                decode.insert(lead << 8 | (0x40 + j as u16), c as u16);
+            }
+        }
+    }
+    // If several bytes have the same character, the first is used. That is the case for some characters of code page 932.
+    let mut encode : HashMap<u16, u16> = HashMap::new();
+    let mut sorted : Vec<(&u16, &u16)> = decode.iter().collect();
+    sorted.sort();
+    for (&bytes, &w) in sorted {
+        encode.entry(w).or_insert(bytes);
+    }
+    Table { decode, encode, lead_bytes }
+}
+
+fn table(code_page: u32) -> Option<&'static Table> {
+    static CP437: OnceLock<Table> = OnceLock::new();
+    static CP850: OnceLock<Table> = OnceLock::new();
+    static CP932: OnceLock<Table> = OnceLock::new();
+    static CP936: OnceLock<Table> = OnceLock::new();
+    static CP1250: OnceLock<Table> = OnceLock::new();
+    static CP1252: OnceLock<Table> = OnceLock::new();
+    match code_page {
+        437 => Some(CP437.get_or_init(|| build_table(&CP437_HIGH, None))),
+        850 => Some(CP850.get_or_init(|| build_table(&CP850_HIGH, None))),
+        932 => Some(CP932.get_or_init(|| build_table(&CP932_HIGH, Some(&CP932_DOUBLE)))),
+        936 => Some(CP936.get_or_init(|| build_table(&CP936_HIGH, Some(&CP936_DOUBLE)))),
+        1250 => Some(CP1250.get_or_init(|| build_table(&CP1250_HIGH, None))),
+        1252 => Some(CP1252.get_or_init(|| build_table(&CP1252_HIGH, None))),
+        _ => None,
+    }
+}
+
+fn check_code_page(code_page: u32) -> Result<(), String> {
+    if SUPPORTED_CODE_PAGES.contains(&code_page) {
+        Ok(())
+    } else {
+        Err(format!("The code page {} is not supported. Supported are: {:?}", code_page, SUPPORTED_CODE_PAGES))
+    }
+}
+
+fn push_bytes(bytes: &mut Vec<u8>, encoded: u16) {
+    if encoded > 0xff {
+        bytes.push((encoded >> 8) as u8);
+    }
+    bytes.push(encoded as u8);
+}
+
+/// The best fit table of Microsoft for the code page. It is empty, if it was not generated.
+fn best_fit_table(code_page: u32) -> &'static [(u16, u16)] {
+    match code_page {
+        437 => &CP437_BEST_FIT,
+        850 => &CP850_BEST_FIT,
+        932 => &CP932_BEST_FIT,
+        936 => &CP936_BEST_FIT,
+        1250 => &CP1250_BEST_FIT,
+        1252 => &CP1252_BEST_FIT,
+        _ => &[],
+    }
+}
+
+/// True, if best fit is approximated by `approximate_best_fit_candidates`, because the table of the code page is empty.
+/// Then some characters are replaced, that Windows does not replace, and the other way around.
+pub(super) fn is_best_fit_approximate(code_page: u32) -> bool {
+    table(code_page).is_some() && best_fit_table(code_page).is_empty()
+}
+
+/// The bytes, that Windows writes for a character, that the code page lacks, if best fit is on.
+fn encode_best_fit(code_page: u32, table: &Table, w: u16) -> Option<u16> {
+    if is_best_fit_approximate(code_page) {
+        return approximate_best_fit_candidates(code_page, w).into_iter().find_map(|candidate| table.encode.get(&candidate).copied());
+    }
+    let best_fit = best_fit_table(code_page);
+    best_fit.binary_search_by_key(&w, |&(c, _)| c).ok().map(|i| best_fit[i].1)
+}
+
+/// Characters, that Windows writes as a similar character, if the code page lacks them.
+/// This is the same small set for every code page, that covers the characters, which are often abused.
+fn approximate_best_fit_candidates(code_page: u32, w: u16) -> Vec<u16> {
+    let mut candidates : Vec<u16> = vec![];
+    match (code_page, w) {
+        (932, 0x00a5) => candidates.push(b'\\' as u16),
+        (932, 0x203e) => candidates.push(b'~' as u16),
+        _ => {},
+    }
+    let similar : Option<u8> = match w {
+        0x02ba | 0x02dd | 0x201c | 0x201d | 0x201e | 0x2033 | 0x301d | 0x301e => Some(b'"'),
+        0x02b9 | 0x02bc | 0x2018 | 0x2019 | 0x201a | 0x2032 => Some(b'\''),
+        0x00ad | 0x2010 | 0x2011 | 0x2012 | 0x2013 | 0x2014 | 0x2212 => Some(b'-'),
+        0x2000..=0x200a | 0x3000 => Some(b' '),
+        0x2039 => Some(b'<'),
+        0x203a => Some(b'>'),
+        0x2044 | 0x2215 => Some(b'/'),
+        0x2216 => Some(b'\\'),
+        // The fullwidth forms of ASCII
+        0xff01..=0xff5e => Some((w - 0xfee0) as u8),
+        _ => LATIN_BASE_LETTERS.binary_search_by_key(&w, |&(c, _)| c).ok().map(|i| LATIN_BASE_LETTERS[i].1),
+    };
+    candidates.extend(similar.map(u16::from));
+    candidates
+}
+
+/// Emulates `WideCharToMultiByte`. Characters without a mapping are replaced by a best fit character, if `best_fit`
+/// is true and there is one, or else by `default_char`. A surrogate pair is replaced by two default characters.
+/// For code page 65001 (UTF-8) a lone surrogate is replaced by U+FFFD.
+pub(super) fn wide_to_multibyte(code_page: u32, text: &[u16], best_fit: bool, default_char: u8) -> Result<Narrow, String> {
+    check_code_page(code_page)?;
+    let mut bytes : Vec<u8> = vec![];
+    let mut substitutions : Vec<Substitution> = vec![];
+    let mut position = 0;
+
+    for c in char::decode_utf16(text.iter().copied()) {
+        let units : Vec<u16> = match &c {
+            Ok(c) => c.encode_utf16(&mut [0u16; 2]).to_vec(),
+            Err(e) => vec![e.unpaired_surrogate()],
+        };
+        let start = bytes.len();
+        let kind = match (table(code_page), c) {
+            (None, Ok(c)) => {
+                bytes.extend_from_slice(c.encode_utf8(&mut [0u8; 4]).as_bytes());
+                None
+            },
+            (None, Err(_)) => {
+                bytes.extend_from_slice('\u{fffd}'.encode_utf8(&mut [0u8; 4]).as_bytes());
+                Some(SubstitutionKind::DefaultChar)
+            },
+            (Some(table), _) if units.len() == 1 => {
+                let w = units[0];
+                let best_fit_bytes = if best_fit { encode_best_fit(code_page, table, w) } else { None };
+                match (table.encode.get(&w), best_fit_bytes) {
+                    (Some(&encoded), _) => {
+                        push_bytes(&mut bytes, encoded);
+                        None
+                    },
+                    (None, Some(encoded)) => {
+                        push_bytes(&mut bytes, encoded);
+                        Some(SubstitutionKind::BestFit)
+                    },
+                    (None, None) => {
+                        bytes.push(default_char);
+                        Some(SubstitutionKind::DefaultChar)
+                    },
+                }
+            },
+            (Some(_), _) => {
+                bytes.extend(units.iter().map(|_| default_char));
+                Some(SubstitutionKind::DefaultChar)
+            },
+        };
+        if let Some(kind) = kind {
+            substitutions.push(Substitution { position, units: units.clone(), bytes: bytes[start..].to_vec(), kind });
+        }
+        position += units.len();
+    }
+    Ok(Narrow { bytes, substitutions })
+}
+
+/// Emulates `MultiByteToWideChar`. Bytes without a mapping become U+FFFD.
+pub(super) fn multibyte_to_wide(code_page: u32, bytes: &[u8]) -> Result<Vec<u16>, String> {
+    check_code_page(code_page)?;
+    let table = match table(code_page) {
+        None => return Ok(String::from_utf8_lossy(bytes).encode_utf16().collect()),
+        Some(table) => table,
+    };
+    let mut wide : Vec<u16> = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        let key : u16 = match bytes.get(i + 1) {
+            Some(&trail) if table.lead_bytes[usize::from(b)] => {
+                i += 1;
+                u16::from(b) << 8 | u16::from(trail)
+            },
+            _ => u16::from(b),
+        };
+        wide.push(table.decode.get(&key).copied().unwrap_or(0xfffd));
+        i += 1;
+    }
+    Ok(wide)
+}
+
+fn is_lead_byte(code_page: u32, b: u8) -> bool {
+    table(code_page).is_some_and(|table| table.lead_bytes[usize::from(b)])
+}
+
+/// Splits a narrow command line like the C-Runtime splits it for `main(int argc, char *argv[])`.
+/// The rules are the same as in `parse_lp_cmd_line`, but a lead byte and its trail byte are always taken literally.
+/// So a trail byte, that is a backslash or a quote, has no special meaning.
+pub(super) fn parse_narrow_cmd_line(code_page: u32, cmd_line: &[u8]) -> Vec<Vec<u8>> {
+    let mut args : Vec<Vec<u8>> = vec![];
+    if cmd_line.is_empty() {
+        return args;
+    }
+    let mut cur : Vec<u8> = vec![];
+    let mut iter = cmd_line.iter().copied().peekable();
+    let mut in_quotes = false;
+
+    // The executable name at the beginning is special.
+    while let Some(b) = iter.next() {
+        match b {
+            QUOTE => in_quotes = !in_quotes,
+            SPACE | TAB if !in_quotes => break,
+            _ => {
+                cur.push(b);
+                if is_lead_byte(code_page, b) {
+                    cur.extend(iter.next());
+                }
+            },
+        }
+    }
+    args.push(std::mem::take(&mut cur));
+    while iter.next_if(|&b| b == SPACE || b == TAB).is_some() {}
+
+    in_quotes = false;
+    while let Some(b) = iter.next() {
+        match b {
+            SPACE | TAB if !in_quotes => {
+                args.push(std::mem::take(&mut cur));
+                while iter.next_if(|&b| b == SPACE || b == TAB).is_some() {}
+            },
+            BACKSLASH => {
+                let mut backslash_count = 1;
+                while iter.next_if_eq(&BACKSLASH).is_some() {
+                    backslash_count += 1;
+                }
+                if iter.peek() == Some(&QUOTE) {
+                    cur.extend(std::iter::repeat_n(BACKSLASH, backslash_count / 2));
+                    if backslash_count % 2 == 1 {
+                        iter.next();
+                        cur.push(QUOTE);
+                    }
+                } else {
+                    cur.extend(std::iter::repeat_n(BACKSLASH, backslash_count));
+                }
+            },
+            QUOTE if in_quotes => match iter.peek() {
+                Some(&QUOTE) => {
+                    iter.next();
+                    cur.push(QUOTE);
+                },
+                Some(_) => in_quotes = false,
+                None => {},
+            },
+            QUOTE => in_quotes = true,
+            _ => {
+                cur.push(b);
+                if is_lead_byte(code_page, b) {
+                    cur.extend(iter.next());
+                }
+            },
+        }
+    }
+    if !cur.is_empty() || in_quotes {
+        args.push(cur);
+    }
+    args
+}
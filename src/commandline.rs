@@ -13,6 +13,34 @@ pub(super) struct Arg<'lifetime_of_slice> {
     pub(super) range: std::ops::Range<usize>,
     pub(super) raw: &'lifetime_of_slice[u16],
     pub(super) number: usize,
+    pub(super) info: ArgInfo,
+}
+
+/// How the parser got the argument from its raw text.
+#[derive(Debug, Clone, Default)]
+pub(super) struct ArgInfo {
+    /// At least one quote started quoting.
+    pub(super) quoted: bool,
+    /// The number of quotes, that were escaped with a backslash.
+    pub(super) escaped_quotes: usize,
+    /// The number of quotes, that were escaped with another quote inside of quotes (`""`).
+    pub(super) doubled_quotes: usize,
+    /// The command line ended inside of quotes, because the closing quote is missing.
+    pub(super) unterminated: bool,
+    /// The argument was parsed with the rules for the executable name: quotes only toggle quoting, backslashes are literal.
+    pub(super) arg_zero_form: bool,
+    /// The range of the whitespace between the previous argument and this one.
+    pub(super) whitespace: std::ops::Range<usize>,
+    /// The spaces and tabs in `whitespace`.
+    pub(super) whitespace_units: Vec<u16>,
+    /// The number of backslashes, that were removed, because they were in front of a quote.
+    pub(super) backslashes_collapsed: usize,
+}
+
+impl Arg<'_> {
+    pub(super) fn to_utf16(&self) -> Vec<u16> {
+        self.arg.encode_wide().collect()
+    }
 }
 
 struct ArgListBuilder<'a> {
@@ -20,7 +48,71 @@ struct ArgListBuilder<'a> {
     cur: Vec<u16>,
     start_index: usize,
     end_index: usize,
+    whitespace_start: usize,
+    info: ArgInfo,
     arg_list: Vec<Arg<'a>>,
+    tracing: bool,
+    trace: Vec<TraceStep>,
+}
+
+/// What the parser did with one or more code units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum TraceAction {
+    /// The code unit is taken literally.
+    Literal,
+    /// Space or tab outside of quotes ends the argument. The following whitespace is skipped.
+    Separator,
+    /// In argument zero, a quote always toggles the quoting.
+    ToggleQuotes,
+    OpenQuotes,
+    CloseQuotes,
+    /// Two quotes inside of quotes are one literal quote.
+    DoubledQuote,
+    /// An odd number of backslashes and a quote: half of the backslashes and a literal quote.
+    EscapedQuote,
+    /// An even number of backslashes before a quote: half of them are kept, the quote is handled in the next step.
+    HalvedBackslashes,
+    /// Backslashes, that are not followed by a quote, are taken literally.
+    LiteralBackslashes,
+    /// The end of the command line ends the last argument.
+    EndOfCmdLine,
+}
+
+impl TraceAction {
+    pub(super) fn as_str(&self) -> &'static str {
+        match self {
+            TraceAction::Literal => "literal",
+            TraceAction::Separator => "separator",
+            TraceAction::ToggleQuotes => "toggle-quotes",
+            TraceAction::OpenQuotes => "open-quotes",
+            TraceAction::CloseQuotes => "close-quotes",
+            TraceAction::DoubledQuote => "doubled-quote",
+            TraceAction::EscapedQuote => "escaped-quote",
+            TraceAction::HalvedBackslashes => "halved-backslashes",
+            TraceAction::LiteralBackslashes => "literal-backslashes",
+            TraceAction::EndOfCmdLine => "end-of-cmd-line",
+        }
+    }
+}
+
+/// One state transition of the parser.
+pub(super) struct TraceStep {
+    /// The code units, that were consumed in this step.
+    pub(super) range: std::ops::Range<usize>,
+    pub(super) in_quotes_before: bool,
+    pub(super) in_quotes_after: bool,
+    pub(super) action: TraceAction,
+    /// The length of a run of backslashes and how many of them are kept.
+    pub(super) backslashes: Option<(usize, usize)>,
+    /// The number of the argument, that was ended in this step.
+    pub(super) arg_ended: Option<usize>,
+}
+
+/// The state at the beginning of a step: where it started and how many arguments there were.
+struct StepStart {
+    index: usize,
+    in_quotes: bool,
+    arg_count: usize,
 }
 
 impl<'a> ArgListBuilder<'a> {
@@ -31,20 +123,57 @@ impl<'a> ArgListBuilder<'a> {
             cur: vec![],
             start_index: 0,
             end_index: 0,
-            arg_list: vec![]
+            whitespace_start: 0,
+            info: ArgInfo::default(),
+            arg_list: vec![],
+            tracing: false,
+            trace: vec![],
         }
     }
 
     fn push_arg(&mut self){
         let range = self.start_index..(self.end_index-1); // TODO try ..=
+        let mut info = std::mem::take(&mut self.info);
+        info.whitespace = self.whitespace_start..self.start_index;
+        info.whitespace_units = self.cmd_line[info.whitespace.clone()].to_vec();
+        self.whitespace_start = range.end;
         self.arg_list.push(Arg{
             arg: OsString::from_wide(&self.cur),
             range:range.clone(),
             raw: &self.cmd_line[range],
             number: self.arg_list.len(),
+            info,
         });
         self.cur.truncate(0);
     }
+
+    /// Called after the first code unit of a step was consumed with `next`, or before pushing the last argument.
+    fn begin_step(&self, in_quotes: bool) -> StepStart {
+        StepStart {
+            index: self.end_index.saturating_sub(1).min(self.cmd_line.len()),
+            in_quotes,
+            arg_count: self.arg_list.len(),
+        }
+    }
+
+    fn end_step(&mut self, start: StepStart, in_quotes: bool, action: TraceAction, backslashes: Option<(usize, usize)>) {
+        if !self.tracing {
+            return;
+        }
+        let end = self.end_index.min(self.cmd_line.len());
+        self.trace.push(TraceStep {
+            range: start.index..end.max(start.index),
+            in_quotes_before: start.in_quotes,
+            in_quotes_after: in_quotes,
+            action,
+            backslashes,
+            arg_ended: (self.arg_list.len() > start.arg_count).then_some(start.arg_count),
+        });
+    }
+
+    fn get_info(&mut self) -> &mut ArgInfo {
+        &mut self.info
+    }
     fn peek(&self) -> Option<u16> {
         self.cmd_line.get(self.end_index).map(|w:&u16| *w)
     }
@@ -76,8 +205,8 @@ impl<'a> ArgListBuilder<'a> {
         counter
     }
 
-    fn get_arg_list(self) -> Vec<Arg<'a>> {
-        self.arg_list
+    fn get_arg_list(self) -> (Vec<Arg<'a>>, Vec<TraceStep>) {
+        (self.arg_list, self.trace)
     }
 }
 
@@ -114,6 +243,15 @@ pub(super) fn get_rest<'a>(cmd_line:&'a[u16], arg: &Arg<'a>) -> &'a[u16]{
 /// extensive test suite available at
 /// <https://github.com/ChrisDenton/winarg/tree/std>.
 pub(super) fn parse_lp_cmd_line<'a>(cmd_line: &'a [u16], handle_first_special: bool) -> Vec<Arg<'a>> {
+    parse_and_trace(cmd_line, handle_first_special, false).0
+}
+
+/// Splits the command line like `parse_lp_cmd_line` and records each step of the parser.
+pub(super) fn trace_lp_cmd_line<'a>(cmd_line: &'a [u16], handle_first_special: bool) -> (Vec<Arg<'a>>, Vec<TraceStep>) {
+    parse_and_trace(cmd_line, handle_first_special, true)
+}
+
+fn parse_and_trace<'a>(cmd_line: &'a [u16], handle_first_special: bool, tracing: bool) -> (Vec<Arg<'a>>, Vec<TraceStep>) {
     const BACKSLASH: u16 = b'\\' as u16;
     const QUOTE: u16 = b'"' as u16;
     const TAB: u16 = b'\t' as u16;
@@ -122,29 +260,47 @@ pub(super) fn parse_lp_cmd_line<'a>(cmd_line: &'a [u16], handle_first_special: b
     // If the cmd line pointer is null or it points to an empty string then
     // return an empty vector.
     if cmd_line.is_empty() {
-        return Vec::<Arg<'a>>::new();
+        return (Vec::<Arg<'a>>::new(), vec![]);
     }
 
     let mut builder = ArgListBuilder::new(cmd_line);
+    builder.tracing = tracing;
     let mut in_quotes = false;
 
     // The executable name at the beginning is special.
     if handle_first_special {
+        builder.get_info().arg_zero_form = true;
+        let mut ended_by = TraceAction::EndOfCmdLine;
         while let Some(w) = builder.next() {
-            match w {
+            let step = builder.begin_step(in_quotes);
+            let action = match w {
                 // A quote mark always toggles `in_quotes` no matter what because
                 // there are no escape characters when parsing the executable name.
-                QUOTE => in_quotes = !in_quotes,
+                QUOTE => {
+                    in_quotes = !in_quotes;
+                    builder.get_info().quoted = true;
+                    TraceAction::ToggleQuotes
+                },
                 // If not `in_quotes` then whitespace ends argv[0].
-                SPACE | TAB if !in_quotes => break,
+                SPACE | TAB if !in_quotes => {
+                    ended_by = TraceAction::Separator;
+                    break;
+                },
                 // In all other cases the code unit is taken literally.
-                _ => builder.get_current().push(w),
-            }
+                _ => {
+                    builder.get_current().push(w);
+                    TraceAction::Literal
+                },
+            };
+            builder.end_step(step, in_quotes, action, None);
         }
+        let step = builder.begin_step(in_quotes);
+        builder.get_info().unterminated = in_quotes;
         builder.push_arg();
         // Skip whitespace.
         builder.advance_while(|w| w == SPACE || w == TAB);
         builder.set_start_index();
+        builder.end_step(step, in_quotes, ended_by, None);
     }
 
     // Parse the arguments according to these rules:
@@ -160,8 +316,12 @@ pub(super) fn parse_lp_cmd_line<'a>(cmd_line: &'a [u16], handle_first_special: b
     // * If `in_quotes` then a quote can also be escaped using another quote
     // (i.e. two consecutive quotes become one literal quote).
     in_quotes = false;
+    // True, if the last code unit was a quote, that would close the quoting.
+    let mut closed_at_end = false;
     while let Some(w) = builder.next() {
-        match w {
+        let step = builder.begin_step(in_quotes);
+        let mut backslashes : Option<(usize, usize)> = None;
+        let action = match w {
             // If not `in_quotes`, a space or tab ends the argument.
             SPACE | TAB if !in_quotes => {
                 builder.push_arg();
@@ -169,20 +329,29 @@ pub(super) fn parse_lp_cmd_line<'a>(cmd_line: &'a [u16], handle_first_special: b
                 // Skip whitespace.
                 builder.advance_while(|w| w == SPACE || w == TAB);
                 builder.set_start_index();
+                TraceAction::Separator
             }
             // Backslashes can escape quotes or backslashes but only if consecutive backslashes are followed by a quote.
             BACKSLASH => {
                 let backslash_count = builder.advance_while(|w| w == BACKSLASH) + 1;
                 if builder.peek() == Some(QUOTE) {
                     builder.get_current().extend(std::iter::repeat(BACKSLASH).take(backslash_count / 2));
+                    builder.get_info().backslashes_collapsed += backslash_count - backslash_count / 2;
+                    backslashes = Some((backslash_count, backslash_count / 2));
                     // The quote is escaped if there are an odd number of backslashes.
                     if backslash_count % 2 == 1 {
                         builder.next(); // consume the peeked quote
                         builder.get_current().push(QUOTE);
+                        builder.get_info().escaped_quotes += 1;
+                        TraceAction::EscapedQuote
+                    } else {
+                        TraceAction::HalvedBackslashes
                     }
                 } else {
                     // If there is no quote on the end then there is no escaping.
                     builder.get_current().extend(std::iter::repeat(BACKSLASH).take(backslash_count));
+                    backslashes = Some((backslash_count, backslash_count));
+                    TraceAction::LiteralBackslashes
                 }
             }
             // If `in_quotes` and not backslash escaped (see above) then a quote either
@@ -192,22 +361,43 @@ pub(super) fn parse_lp_cmd_line<'a>(cmd_line: &'a [u16], handle_first_special: b
                 Some(QUOTE) => {
                     builder.next(); // consume the peeked quote
                     builder.get_current().push(QUOTE);
+                    builder.get_info().doubled_quotes += 1;
+                    TraceAction::DoubledQuote
                 }
                 // Otherwise set `in_quotes`.
-                Some(_) => in_quotes = false,
+                Some(_) => {
+                    in_quotes = false;
+                    TraceAction::CloseQuotes
+                },
                 // The end of the command line, so this is the cycle/pass of the loop.
                 // After the loop, the current argument gets pushed, because `in_quotes` is true.
-                None => {}
+                // The trace shows the quoting as closed.
+                None => {
+                    closed_at_end = true;
+                    TraceAction::CloseQuotes
+                },
             },
             // If not `in_quotes` and not BACKSLASH escaped (see above) then a quote sets `in_quotes`.
-            QUOTE => in_quotes = true,
+            QUOTE => {
+                in_quotes = true;
+                builder.get_info().quoted = true;
+                TraceAction::OpenQuotes
+            },
             // Everything else is always taken literally.
-            _ => builder.get_current().push(w),
-        }
+            _ => {
+                builder.get_current().push(w);
+                TraceAction::Literal
+            },
+        };
+        builder.end_step(step, in_quotes && !closed_at_end, action, backslashes);
     }
     // Push the final argument, if any.
     if !builder.get_current().is_empty() || in_quotes {
+        let unterminated = in_quotes && !closed_at_end;
+        let step = builder.begin_step(unterminated);
+        builder.get_info().unterminated = unterminated;
         builder.push_arg();
+        builder.end_step(step, unterminated, TraceAction::EndOfCmdLine, None);
     }
     builder.get_arg_list()
 }
@@ -326,3 +516,20 @@ pub(super) fn append_arg<O: AsRef<OsStr>>(cmdline: &mut Vec<u16>, arg: O, force_
 
     Ok(())
 }
+
+/// Puts a command line together, so that `parse_lp_cmd_line` splits it into `args` again.
+/// The first argument is argument zero.
+pub(super) fn cmdline_from_args<O: AsRef<OsStr>>(args: &[O]) -> Result<Vec<u16>, String> {
+    let mut cmdline : Vec<u16> = vec![];
+    let mut args_iter = args.iter();
+    if let Some(arg_zero) = args_iter.next() {
+        ensure_no_nuls(arg_zero)?;
+        let arg_zero : Vec<u16> = arg_zero.as_ref().encode_wide().collect();
+        cmdline.extend_from_slice(&escape_arg_zero(&arg_zero, false)?.escaped);
+    }
+    for arg in args_iter {
+        cmdline.push(b' ' as u16);
+        append_arg(&mut cmdline, arg, false, false)?;
+    }
+    Ok(cmdline)
+}
@@ -0,0 +1,152 @@
+use crate::commandline::Arg;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum Category {
+    /// Looks like a separator, but `parse_lp_cmd_line` only splits at SPACE and TAB.
+    Whitespace,
+    /// Looks like `"` or `'`, but does not quote.
+    Quote,
+    /// Looks like `-`, so `–v` looks like an option, but is none.
+    Dash,
+    /// Is not visible at all.
+    ZeroWidth,
+    /// Changes the order, in which the following text is displayed.
+    Bidi,
+}
+
+impl Category {
+    pub(super) fn as_str(&self) -> &'static str {
+        match self {
+            Category::Whitespace => "whitespace",
+            Category::Quote => "quote",
+            Category::Dash => "dash",
+            Category::ZeroWidth => "zero-width",
+            Category::Bidi => "bidi",
+        }
+    }
+
+    pub(super) fn describe(&self) -> &'static str {
+        match self {
+            Category::Whitespace => "looks like whitespace, but does not separate arguments",
+            Category::Quote => "looks like a quote, but does not quote",
+            Category::Dash => "looks like a hyphen-minus, but is none",
+            Category::ZeroWidth => "is invisible",
+            Category::Bidi => "changes the display order of the following text",
+        }
+    }
+}
+
+const CHARACTERS: [(u16, &str, Category); 92] = [
+    (0x000a, "LINE FEED", Category::Whitespace),
+    (0x000b, "LINE TABULATION", Category::Whitespace),
+    (0x000c, "FORM FEED", Category::Whitespace),
+    (0x000d, "CARRIAGE RETURN", Category::Whitespace),
+    (0x0085, "NEXT LINE", Category::Whitespace),
+    (0x00a0, "NO-BREAK SPACE", Category::Whitespace),
+    (0x1680, "OGHAM SPACE MARK", Category::Whitespace),
+    (0x2000, "EN QUAD", Category::Whitespace),
+    (0x2001, "EM QUAD", Category::Whitespace),
+    (0x2002, "EN SPACE", Category::Whitespace),
+    (0x2003, "EM SPACE", Category::Whitespace),
+    (0x2004, "THREE-PER-EM SPACE", Category::Whitespace),
+    (0x2005, "FOUR-PER-EM SPACE", Category::Whitespace),
+    (0x2006, "SIX-PER-EM SPACE", Category::Whitespace),
+    (0x2007, "FIGURE SPACE", Category::Whitespace),
+    (0x2008, "PUNCTUATION SPACE", Category::Whitespace),
+    (0x2009, "THIN SPACE", Category::Whitespace),
+    (0x200a, "HAIR SPACE", Category::Whitespace),
+    (0x2028, "LINE SEPARATOR", Category::Whitespace),
+    (0x2029, "PARAGRAPH SEPARATOR", Category::Whitespace),
+    (0x202f, "NARROW NO-BREAK SPACE", Category::Whitespace),
+    (0x205f, "MEDIUM MATHEMATICAL SPACE", Category::Whitespace),
+    (0x3000, "IDEOGRAPHIC SPACE", Category::Whitespace),
+
+    (0x00ab, "LEFT-POINTING DOUBLE ANGLE QUOTATION MARK", Category::Quote),
+    (0x00bb, "RIGHT-POINTING DOUBLE ANGLE QUOTATION MARK", Category::Quote),
+    (0x02b9, "MODIFIER LETTER PRIME", Category::Quote),
+    (0x02ba, "MODIFIER LETTER DOUBLE PRIME", Category::Quote),
+    (0x02bc, "MODIFIER LETTER APOSTROPHE", Category::Quote),
+    (0x02dd, "DOUBLE ACUTE ACCENT", Category::Quote),
+    (0x02ee, "MODIFIER LETTER DOUBLE APOSTROPHE", Category::Quote),
+    (0x2018, "LEFT SINGLE QUOTATION MARK", Category::Quote),
+    (0x2019, "RIGHT SINGLE QUOTATION MARK", Category::Quote),
+    (0x201a, "SINGLE LOW-9 QUOTATION MARK", Category::Quote),
+    (0x201b, "SINGLE HIGH-REVERSED-9 QUOTATION MARK", Category::Quote),
+    (0x201c, "LEFT DOUBLE QUOTATION MARK", Category::Quote),
+    (0x201d, "RIGHT DOUBLE QUOTATION MARK", Category::Quote),
+    (0x201e, "DOUBLE LOW-9 QUOTATION MARK", Category::Quote),
+    (0x201f, "DOUBLE HIGH-REVERSED-9 QUOTATION MARK", Category::Quote),
+    (0x2032, "PRIME", Category::Quote),
+    (0x2033, "DOUBLE PRIME", Category::Quote),
+    (0x2035, "REVERSED PRIME", Category::Quote),
+    (0x2036, "REVERSED DOUBLE PRIME", Category::Quote),
+    (0x2039, "SINGLE LEFT-POINTING ANGLE QUOTATION MARK", Category::Quote),
+    (0x203a, "SINGLE RIGHT-POINTING ANGLE QUOTATION MARK", Category::Quote),
+    (0x3003, "DITTO MARK", Category::Quote),
+    (0x301d, "REVERSED DOUBLE PRIME QUOTATION MARK", Category::Quote),
+    (0x301e, "DOUBLE PRIME QUOTATION MARK", Category::Quote),
+    (0x301f, "LOW DOUBLE PRIME QUOTATION MARK", Category::Quote),
+    (0xff02, "FULLWIDTH QUOTATION MARK", Category::Quote),
+    (0xff07, "FULLWIDTH APOSTROPHE", Category::Quote),
+
+    (0x00ad, "SOFT HYPHEN", Category::Dash),
+    (0x058a, "ARMENIAN HYPHEN", Category::Dash),
+    (0x1806, "MONGOLIAN TODO SOFT HYPHEN", Category::Dash),
+    (0x2010, "HYPHEN", Category::Dash),
+    (0x2011, "NON-BREAKING HYPHEN", Category::Dash),
+    (0x2012, "FIGURE DASH", Category::Dash),
+    (0x2013, "EN DASH", Category::Dash),
+    (0x2014, "EM DASH", Category::Dash),
+    (0x2015, "HORIZONTAL BAR", Category::Dash),
+    (0x2043, "HYPHEN BULLET", Category::Dash),
+    (0x2212, "MINUS SIGN", Category::Dash),
+    (0x2796, "HEAVY MINUS SIGN", Category::Dash),
+    (0xfe58, "SMALL EM DASH", Category::Dash),
+    (0xfe63, "SMALL HYPHEN-MINUS", Category::Dash),
+    (0xff0d, "FULLWIDTH HYPHEN-MINUS", Category::Dash),
+
+    (0x034f, "COMBINING GRAPHEME JOINER", Category::ZeroWidth),
+    (0x115f, "HANGUL CHOSEONG FILLER", Category::ZeroWidth),
+    (0x1160, "HANGUL JUNGSEONG FILLER", Category::ZeroWidth),
+    (0x180e, "MONGOLIAN VOWEL SEPARATOR", Category::ZeroWidth),
+    (0x200b, "ZERO WIDTH SPACE", Category::ZeroWidth),
+    (0x200c, "ZERO WIDTH NON-JOINER", Category::ZeroWidth),
+    (0x200d, "ZERO WIDTH JOINER", Category::ZeroWidth),
+    (0x2060, "WORD JOINER", Category::ZeroWidth),
+    (0x2061, "FUNCTION APPLICATION", Category::ZeroWidth),
+    (0x2062, "INVISIBLE TIMES", Category::ZeroWidth),
+    (0x2063, "INVISIBLE SEPARATOR", Category::ZeroWidth),
+    (0x2064, "INVISIBLE PLUS", Category::ZeroWidth),
+    (0x3164, "HANGUL FILLER", Category::ZeroWidth),
+    (0xfeff, "ZERO WIDTH NO-BREAK SPACE", Category::ZeroWidth),
+    (0xffa0, "HALFWIDTH HANGUL FILLER", Category::ZeroWidth),
+
+    (0x061c, "ARABIC LETTER MARK", Category::Bidi),
+    (0x200e, "LEFT-TO-RIGHT MARK", Category::Bidi),
+    (0x200f, "RIGHT-TO-LEFT MARK", Category::Bidi),
+    (0x202a, "LEFT-TO-RIGHT EMBEDDING", Category::Bidi),
+    (0x202b, "RIGHT-TO-LEFT EMBEDDING", Category::Bidi),
+    (0x202c, "POP DIRECTIONAL FORMATTING", Category::Bidi),
+    (0x202d, "LEFT-TO-RIGHT OVERRIDE", Category::Bidi),
+    (0x202e, "RIGHT-TO-LEFT OVERRIDE", Category::Bidi),
+    (0x2066, "LEFT-TO-RIGHT ISOLATE", Category::Bidi),
+    (0x2067, "RIGHT-TO-LEFT ISOLATE", Category::Bidi),
+    (0x2068, "FIRST STRONG ISOLATE", Category::Bidi),
+    (0x2069, "POP DIRECTIONAL ISOLATE", Category::Bidi),
+];
+
+pub(super) struct Confusable {
+    /// The index of the UTF-16 code unit in the command line.
+    pub(super) position: usize,
+    pub(super) code_point: u16,
+    pub(super) name: &'static str,
+    pub(super) category: Category,
+}
+
+/// Finds the characters in the raw text of the argument, that look like other characters or are invisible.
+pub(super) fn find_confusables(arg: &Arg<'_>) -> Vec<Confusable> {
+    arg.raw.iter().enumerate().filter_map(|(i, &w)| {
+        CHARACTERS.iter().find(|(code_point, _, _)| *code_point == w)
+            .map(|&(code_point, name, category)| Confusable { position: arg.range.start + i, code_point, name, category })
+    }).collect()
+}
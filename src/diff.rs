@@ -0,0 +1,134 @@
+use std::ops::Range;
+
+use crate::commandline::{
+    parse_lp_cmd_line,
+    Arg,
+};
+
+pub(super) enum DiffOp {
+    /// The argument is in both command lines. The raw text may differ.
+    Same(usize, usize),
+    Removed(usize),
+    Added(usize),
+    /// The argument at this place is different. The ranges are the code units of each argument, that differ.
+    Changed(usize, usize, Vec<Range<usize>>, Vec<Range<usize>>),
+}
+
+impl DiffOp {
+    pub(super) fn as_str(&self) -> &'static str {
+        match self {
+            DiffOp::Same(..) => "same",
+            DiffOp::Removed(..) => "removed",
+            DiffOp::Added(..) => "added",
+            DiffOp::Changed(..) => "changed",
+        }
+    }
+}
+
+pub(super) struct CmdlineDiff<'a> {
+    pub(super) first: Vec<Arg<'a>>,
+    pub(super) second: Vec<Arg<'a>>,
+    pub(super) ops: Vec<DiffOp>,
+}
+
+impl CmdlineDiff<'_> {
+    pub(super) fn is_equal(&self) -> bool {
+        self.ops.iter().all(|op| matches!(op, DiffOp::Same(..)))
+    }
+}
+
+/// The pairs of indices of a longest common subsequence. The common prefix and suffix are paired directly,
+/// the rest with Hirschberg's algorithm, so that only linear space is needed even for long arguments.
+fn lcs<T: PartialEq>(a: &[T], b: &[T]) -> Vec<(usize, usize)> {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..].iter().rev().zip(b[prefix..].iter().rev()).take_while(|(x, y)| x == y).count();
+    let mut pairs : Vec<(usize, usize)> = (0..prefix).map(|i| (i, i)).collect();
+    hirschberg(&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix], prefix, prefix, &mut pairs);
+    pairs.extend((0..suffix).map(|k| (a.len() - suffix + k, b.len() - suffix + k)));
+    pairs
+}
+
+fn element<T>(s: &[T], i: usize, reverse: bool) -> &T {
+    if reverse { &s[s.len() - 1 - i] } else { &s[i] }
+}
+
+/// The last row of the table of lengths: for each `j` the length of the longest common subsequence of `a` and
+/// the first `j` elements of `b`. With `reverse` both are read from the end, so it is the last `j` elements of `b`.
+fn lcs_lengths<T: PartialEq>(a: &[T], b: &[T], reverse: bool) -> Vec<usize> {
+    let mut row = vec![0usize; b.len() + 1];
+    for i in 0..a.len() {
+        let x = element(a, i, reverse);
+        let mut diagonal = 0;
+        for j in 0..b.len() {
+            let above = row[j + 1];
+            row[j + 1] = if x == element(b, j, reverse) { diagonal + 1 } else { above.max(row[j]) };
+            diagonal = above;
+        }
+    }
+    row
+}
+
+/// Appends the pairs of a longest common subsequence of `a` and `b` in order. The offsets are added to the indices.
+fn hirschberg<T: PartialEq>(a: &[T], b: &[T], a_offset: usize, b_offset: usize, pairs: &mut Vec<(usize, usize)>) {
+    if a.is_empty() || b.is_empty() {
+        return;
+    }
+    if a.len() == 1 {
+        if let Some(j) = b.iter().position(|y| *y == a[0]) {
+            pairs.push((a_offset, b_offset + j));
+        }
+        return;
+    }
+    let middle = a.len() / 2;
+    let forward = lcs_lengths(&a[..middle], b, false);
+    let backward = lcs_lengths(&a[middle..], b, true);
+    let split = (0..=b.len()).max_by_key(|&j| forward[j] + backward[b.len() - j]).unwrap_or(0);
+    hirschberg(&a[..middle], &b[..split], a_offset, b_offset, pairs);
+    hirschberg(&a[middle..], &b[split..], a_offset + middle, b_offset + split, pairs);
+}
+
+/// The ranges of the indices, that are not in `common`.
+fn ranges_not_in(len: usize, common: impl Iterator<Item = usize>) -> Vec<Range<usize>> {
+    let mut in_common = vec![false; len];
+    for i in common {
+        in_common[i] = true;
+    }
+    let mut ranges : Vec<Range<usize>> = vec![];
+    for (i, _) in in_common.iter().enumerate().filter(|(_, &c)| !c) {
+        match ranges.last_mut() {
+            Some(range) if range.end == i => range.end += 1,
+            _ => ranges.push(i..i + 1),
+        }
+    }
+    ranges
+}
+
+/// Compares the arguments of two command lines. The arguments are aligned with a longest common subsequence.
+/// Between two aligned arguments, removed and added arguments are paired as changed arguments, as far as possible.
+pub(super) fn diff_cmdlines<'a>(first: &'a [u16], second: &'a [u16]) -> CmdlineDiff<'a> {
+    let first_args = parse_lp_cmd_line(first, true);
+    let second_args = parse_lp_cmd_line(second, true);
+    let a : Vec<Vec<u16>> = first_args.iter().map(|arg| arg.to_utf16()).collect();
+    let b : Vec<Vec<u16>> = second_args.iter().map(|arg| arg.to_utf16()).collect();
+
+    let mut ops : Vec<DiffOp> = vec![];
+    let (mut i, mut j) = (0, 0);
+    for (next_i, next_j) in lcs(&a, &b).into_iter().chain([(a.len(), b.len())]) {
+        while i < next_i && j < next_j {
+            let pairs = lcs(&a[i], &b[j]);
+            ops.push(DiffOp::Changed(i, j,
+                                     ranges_not_in(a[i].len(), pairs.iter().map(|&(x, _)| x)),
+                                     ranges_not_in(b[j].len(), pairs.iter().map(|&(_, y)| y))));
+            i += 1;
+            j += 1;
+        }
+        ops.extend((i..next_i).map(DiffOp::Removed));
+        ops.extend((j..next_j).map(DiffOp::Added));
+        if next_i < a.len() {
+            ops.push(DiffOp::Same(next_i, next_j));
+        }
+        i = next_i + 1;
+        j = next_j + 1;
+    }
+    CmdlineDiff { first: first_args, second: second_args, ops }
+}
@@ -0,0 +1,133 @@
+use std::{
+    ffi::OsStr,
+    os::windows::ffi::OsStrExt,
+};
+
+use crate::commandline::{
+    append_arg,
+    escape_arg_zero,
+    parse_lp_cmd_line,
+};
+use crate::options::CmdlineEdit;
+
+const SPACE: u16 = b' ' as u16;
+
+/// Quotes a new argument. Argument zero has its own rules.
+fn quote_new_arg(arg: &OsStr, is_arg_zero: bool, warnings: &mut Vec<String>) -> Result<Vec<u16>, String> {
+    let mut quoted : Vec<u16> = vec![];
+    if is_arg_zero {
+        let arg_vec : Vec<u16> = arg.encode_wide().collect();
+        let escaped_arg_zero = escape_arg_zero(&arg_vec, false)?;
+        if let Some(warning) = escaped_arg_zero.warning {
+            warnings.push(warning.to_string());
+        }
+        quoted.extend_from_slice(&escaped_arg_zero.escaped);
+    } else {
+        append_arg(&mut quoted, arg, false, false)?;
+    }
+    Ok(quoted)
+}
+
+/// Inserts `arg`, so that it becomes argument `index`.
+/// The new argument and a space are put in front of the argument, that is currently at `index`.
+/// At the end, a space and the new argument are put after the last argument, so trailing whitespace stays at the end.
+fn insert(cmdline: &[u16], index: usize, arg: &OsStr, warnings: &mut Vec<String>) -> Result<Vec<u16>, String> {
+    let args = parse_lp_cmd_line(cmdline, true);
+    if index > args.len() {
+        return Err(format!("Cannot insert argument {}, there are only {} arguments", index, args.len()));
+    }
+    let quoted = quote_new_arg(arg, index == 0, warnings)?;
+    let mut new_cmdline : Vec<u16> = vec![];
+    match (args.get(index), args.last()) {
+        (Some(next), _) => {
+            new_cmdline.extend_from_slice(&cmdline[..next.range.start]);
+            new_cmdline.extend_from_slice(&quoted);
+            new_cmdline.push(SPACE);
+            new_cmdline.extend_from_slice(&cmdline[next.range.start..]);
+        },
+        (None, Some(last)) => {
+            new_cmdline.extend_from_slice(&cmdline[..last.range.end]);
+            new_cmdline.push(SPACE);
+            new_cmdline.extend_from_slice(&quoted);
+            new_cmdline.extend_from_slice(&cmdline[last.range.end..]);
+        },
+        (None, None) => new_cmdline = quoted,
+    }
+    Ok(new_cmdline)
+}
+
+/// Deletes argument `index` together with the whitespace in front of it.
+/// Argument zero is deleted together with the whitespace after it.
+fn delete(cmdline: &[u16], index: usize) -> Result<Vec<u16>, String> {
+    let args = parse_lp_cmd_line(cmdline, true);
+    let arg = args.get(index)
+        .ok_or_else(|| format!("Cannot delete argument {}, there are only {} arguments", index, args.len()))?;
+    let range = match index {
+        0 => arg.range.start..args.get(1).map_or(cmdline.len(), |next| next.range.start),
+        _ => args[index - 1].range.end..arg.range.end,
+    };
+    let mut new_cmdline : Vec<u16> = cmdline[..range.start].to_vec();
+    new_cmdline.extend_from_slice(&cmdline[range.end..]);
+    Ok(new_cmdline)
+}
+
+/// Replaces the raw text of argument `index`.
+fn replace(cmdline: &[u16], index: usize, arg: &OsStr, warnings: &mut Vec<String>) -> Result<Vec<u16>, String> {
+    let args = parse_lp_cmd_line(cmdline, true);
+    let old = args.get(index)
+        .ok_or_else(|| format!("Cannot replace argument {}, there are only {} arguments", index, args.len()))?;
+    let mut new_cmdline : Vec<u16> = cmdline[..old.range.start].to_vec();
+    new_cmdline.extend_from_slice(&quote_new_arg(arg, index == 0, warnings)?);
+    new_cmdline.extend_from_slice(&cmdline[old.range.end..]);
+    Ok(new_cmdline)
+}
+
+fn find_arg(cmdline: &[u16], matching: &OsStr) -> Result<usize, String> {
+    parse_lp_cmd_line(cmdline, true).iter()
+        .position(|arg| arg.arg == matching)
+        .ok_or_else(|| format!("There is no argument »{}«", matching.to_string_lossy()))
+}
+
+/// The arguments, that the command line should be split into after the edit.
+fn expected_args(cmdline: &[u16], edit: &CmdlineEdit) -> Result<Vec<Vec<u16>>, String> {
+    let mut args : Vec<Vec<u16>> = parse_lp_cmd_line(cmdline, true).iter().map(|a| a.to_utf16()).collect();
+    let encode = |arg: &OsStr| -> Vec<u16> { arg.encode_wide().collect() };
+    match edit {
+        CmdlineEdit::Insert(index, arg) if *index <= args.len() => args.insert(*index, encode(arg)),
+        CmdlineEdit::Delete(index) if *index < args.len() => { args.remove(*index); },
+        CmdlineEdit::Replace(index, arg) if *index < args.len() => args[*index] = encode(arg),
+        CmdlineEdit::InsertBefore(matching, arg) => args.insert(find_arg(cmdline, matching)?, encode(arg)),
+        CmdlineEdit::InsertAfter(matching, arg) => args.insert(find_arg(cmdline, matching)? + 1, encode(arg)),
+        _ => {},
+    }
+    Ok(args)
+}
+
+/// Applies one edit. Arguments, that are not touched, keep their raw text and the whitespace around them.
+/// The result is split again, to check that the other arguments did not change.
+pub(super) fn apply_edit(cmdline: &[u16], edit: &CmdlineEdit, warnings: &mut Vec<String>) -> Result<Vec<u16>, String> {
+    let new_cmdline = match edit {
+        CmdlineEdit::Insert(index, arg) => insert(cmdline, *index, arg, warnings)?,
+        CmdlineEdit::Delete(index) => delete(cmdline, *index)?,
+        CmdlineEdit::Replace(index, arg) => replace(cmdline, *index, arg, warnings)?,
+        CmdlineEdit::InsertBefore(matching, arg) => insert(cmdline, find_arg(cmdline, matching)?, arg, warnings)?,
+        CmdlineEdit::InsertAfter(matching, arg) => insert(cmdline, find_arg(cmdline, matching)? + 1, arg, warnings)?,
+    };
+    let expected = expected_args(cmdline, edit)?;
+    let actual : Vec<Vec<u16>> = parse_lp_cmd_line(&new_cmdline, true).iter().map(|a| a.to_utf16()).collect();
+    if let Some(n) = (0..expected.len().max(actual.len())).find(|&n| expected.get(n) != actual.get(n)) {
+        let show = |arg: Option<&Vec<u16>>| arg.map_or("nothing".to_owned(), |a| format!("»{}«", String::from_utf16_lossy(a)));
+        return Err(format!("The edited command line »{}« is not split as intended: argument {} is {} instead of {}",
+                           String::from_utf16_lossy(&new_cmdline), n, show(actual.get(n)), show(expected.get(n))));
+    }
+    Ok(new_cmdline)
+}
+
+/// Applies the edits one after another. Each edit sees the result of the previous one.
+pub(super) fn apply_edits(cmdline: &[u16], edits: &[CmdlineEdit], warnings: &mut Vec<String>) -> Result<Vec<u16>, String> {
+    let mut new_cmdline : Vec<u16> = cmdline.to_vec();
+    for edit in edits {
+        new_cmdline = apply_edit(&new_cmdline, edit, warnings)?;
+    }
+    Ok(new_cmdline)
+}
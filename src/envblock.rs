@@ -0,0 +1,189 @@
+use std::cmp::Ordering;
+
+const EQUALS: u16 = b'=' as u16;
+const COLON: u16 = b':' as u16;
+
+/// The maximum length of the value of an environment variable.
+const MAX_VALUE_LEN: usize = 32767;
+
+/// Names and values of environment variables.
+pub type EnvVars = Vec<(Vec<u16>, Vec<u16>)>;
+
+/// A change of the environment, applied in the order of the options.
+pub enum EnvEdit {
+    Set(Vec<u16>, Vec<u16>),
+    Unset(Vec<u16>),
+    Clear,
+}
+
+pub struct EnvBlock {
+    /// The variables in the order of the block.
+    pub vars: EnvVars,
+    /// `NAME=VALUE` strings, each terminated by NUL, and another NUL at the end.
+    pub block: Vec<u16>,
+}
+
+fn upper(c: u16) -> u16 {
+    match char::from_u32(c as u32) {
+        Some(ch) => {
+            let mut upper = ch.to_uppercase();
+            match (upper.next(), upper.next()) {
+                (Some(u), None) if (u as u32) <= 0xffff => u as u32 as u16,
+                _ => c,
+            }
+        },
+        None => c,
+    }
+}
+
+/// Names of environment variables are compared without regard to case.
+pub fn same_name(a: &[u16], b: &[u16]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(&x, &y)| upper(x) == upper(y))
+}
+
+/// Like the environment block of Windows is sorted: the current directories of drives (`=C:`) first,
+/// then all others by the upper case of their names.
+fn compare_names(a: &[u16], b: &[u16]) -> Ordering {
+    let is_drive = |name: &[u16]| name.first() == Some(&EQUALS);
+    is_drive(b).cmp(&is_drive(a))
+        .then_with(|| a.iter().map(|&c| upper(c)).cmp(b.iter().map(|&c| upper(c))))
+}
+
+/// Splits `NAME=VALUE` at the first `=` after the first character, so that `=C:=C:\x` has the name `=C:`.
+pub fn split_assignment(assignment: &[u16]) -> Result<(Vec<u16>, Vec<u16>), String> {
+    match assignment.iter().skip(1).position(|&c| c == EQUALS) {
+        Some(p) => Ok((assignment[..p + 1].to_vec(), assignment[p + 2..].to_vec())),
+        None => Err(format!("»{}« is not of the form NAME=VALUE", String::from_utf16_lossy(assignment))),
+    }
+}
+
+fn validate(name: &[u16], value: &[u16]) -> Result<(), String> {
+    let name_str = String::from_utf16_lossy(name);
+    if name.is_empty() {
+        return Err("The name of an environment variable is empty".to_owned());
+    }
+    if name.contains(&0) || value.contains(&0) {
+        return Err(format!("The environment variable »{}« contains a NUL character", name_str));
+    }
+    if name[1..].contains(&EQUALS) {
+        return Err(format!("The name of the environment variable »{}« contains »=«", name_str));
+    }
+    if name[0] == EQUALS && !(name.len() == 3 && name[2] == COLON) {
+        return Err(format!("The name »{}« starts with »=«, but is not the current directory of a drive like »=C:«", name_str));
+    }
+    if value.len() > MAX_VALUE_LEN {
+        return Err(format!("The value of the environment variable »{}« has {} code units, more than {}",
+                           name_str, value.len(), MAX_VALUE_LEN));
+    }
+    Ok(())
+}
+
+/// Applies the edits to the initial variables and puts the block for `CreateProcessW` together.
+/// Names are compared without regard to case. The block is passed with `CREATE_UNICODE_ENVIRONMENT`.
+pub fn build_env_block(initial: EnvVars, edits: &[EnvEdit]) -> Result<EnvBlock, String> {
+    let mut vars = initial;
+    for edit in edits {
+        match edit {
+            EnvEdit::Set(name, value) => {
+                validate(name, value)?;
+                match vars.iter_mut().find(|(n, _)| same_name(n, name)) {
+                    Some(var) => var.1 = value.clone(),
+                    None => vars.push((name.clone(), value.clone())),
+                }
+            },
+            EnvEdit::Unset(name) => vars.retain(|(n, _)| !same_name(n, name)),
+            EnvEdit::Clear => vars.clear(),
+        }
+    }
+    vars.sort_by(|(a, _), (b, _)| compare_names(a, b));
+
+    let mut block : Vec<u16> = vec![];
+    for (name, value) in &vars {
+        block.extend_from_slice(name);
+        block.push(EQUALS);
+        block.extend_from_slice(value);
+        block.push(0);
+    }
+    // An empty block still needs two NUL characters.
+    if vars.is_empty() {
+        block.push(0);
+    }
+    block.push(0);
+    Ok(EnvBlock { vars, block })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u(s: &str) -> Vec<u16> {
+        s.encode_utf16().collect()
+    }
+
+    fn var(name: &str, value: &str) -> (Vec<u16>, Vec<u16>) {
+        (u(name), u(value))
+    }
+
+    fn names(block: &EnvBlock) -> Vec<String> {
+        block.vars.iter().map(|(name, _)| String::from_utf16_lossy(name)).collect()
+    }
+
+    #[test]
+    fn sorts_names_without_regard_to_case() {
+        let block = build_env_block(vec![var("b", "1"), var("C", "2"), var("a", "3")], &[]).unwrap();
+        assert_eq!(names(&block), ["a", "b", "C"]);
+    }
+
+    #[test]
+    fn keeps_drive_directories_first() {
+        let block = build_env_block(vec![var("A", "1"), var("=D:", "D:\\x"), var("=C:", "C:\\y")], &[]).unwrap();
+        assert_eq!(names(&block), ["=C:", "=D:", "A"]);
+    }
+
+    #[test]
+    fn terminates_the_block_with_two_nuls() {
+        let block = build_env_block(vec![var("A", "1"), var("B", "")], &[]).unwrap();
+        assert_eq!(block.block, u("A=1\0B=\0\0"));
+        let empty = build_env_block(vec![], &[]).unwrap();
+        assert_eq!(empty.block, [0, 0]);
+    }
+
+    #[test]
+    fn sets_after_clear() {
+        let edits = [EnvEdit::Clear, EnvEdit::Set(u("B"), u("2"))];
+        let block = build_env_block(vec![var("A", "1")], &edits).unwrap();
+        assert_eq!(block.vars, [var("B", "2")]);
+    }
+
+    #[test]
+    fn set_replaces_the_value_of_the_same_name() {
+        let block = build_env_block(vec![var("Path", "x")], &[EnvEdit::Set(u("PATH"), u("y"))]).unwrap();
+        assert_eq!(block.vars, [var("Path", "y")]);
+    }
+
+    #[test]
+    fn unset_of_a_missing_name_changes_nothing() {
+        let edits = [EnvEdit::Unset(u("MISSING")), EnvEdit::Unset(u("a"))];
+        let block = build_env_block(vec![var("A", "1"), var("B", "2")], &edits).unwrap();
+        assert_eq!(block.vars, [var("B", "2")]);
+    }
+
+    #[test]
+    fn rejects_invalid_variables() {
+        for (name, value) in [("", "1"), ("A=B", "1"), ("A\0", "1"), ("A", "1\0"), ("=X", "1")] {
+            let edits = [EnvEdit::Set(u(name), u(value))];
+            assert!(build_env_block(vec![], &edits).is_err(), "{:?}={:?}", name, value);
+        }
+        let too_long = [EnvEdit::Set(u("A"), vec![b'x' as u16; MAX_VALUE_LEN + 1])];
+        assert!(build_env_block(vec![], &too_long).is_err());
+    }
+
+    #[test]
+    fn splits_assignments_at_the_first_equals_sign() {
+        assert_eq!(split_assignment(&u("A=1=2")).unwrap(), var("A", "1=2"));
+        assert_eq!(split_assignment(&u("=C:=C:\\x")).unwrap(), var("=C:", "C:\\x"));
+        assert_eq!(split_assignment(&u("A=")).unwrap(), var("A", ""));
+        assert!(split_assignment(&u("A")).is_err());
+        assert!(split_assignment(&u("=A")).is_err());
+    }
+}
@@ -0,0 +1,265 @@
+use std::collections::HashSet;
+use std::ops::Range;
+
+use crate::input::VirtualFs;
+
+const QUOTE: u16 = b'"' as u16;
+const SPACE: u16 = b' ' as u16;
+const TAB: u16 = b'\t' as u16;
+const BACKSLASH: u16 = b'\\' as u16;
+const SLASH: u16 = b'/' as u16;
+const COLON: u16 = b':' as u16;
+const DOT: u16 = b'.' as u16;
+
+const DEFAULT_CWD: &str = "C:\\";
+const DEFAULT_SYSTEM_DIR: &str = "C:\\Windows\\System32";
+const DEFAULT_SYSTEM16_DIR: &str = "C:\\Windows\\System";
+const DEFAULT_WINDOWS_DIR: &str = "C:\\Windows";
+
+/// Where a candidate comes from.
+#[derive(Clone, Copy)]
+pub(super) enum Location {
+    /// The name contains a path, so no directory is searched.
+    AsGiven,
+    AppDir,
+    CurrentDir,
+    SystemDir,
+    System16Dir,
+    WindowsDir,
+    /// The index of the entry in `PATH`.
+    Path(usize),
+}
+
+impl Location {
+    pub(super) fn as_str(&self) -> &'static str {
+        match self {
+            Location::AsGiven => "as-given",
+            Location::AppDir => "app-dir",
+            Location::CurrentDir => "cwd",
+            Location::SystemDir => "system-dir",
+            Location::System16Dir => "system16-dir",
+            Location::WindowsDir => "windows-dir",
+            Location::Path(_) => "path",
+        }
+    }
+}
+
+/// A part of the command line, that `CreateProcessW` takes as the name of the program.
+pub(super) struct Prefix {
+    /// The range of UTF-16 code units in the command line.
+    pub(super) range: Range<usize>,
+    /// The name with `.exe` appended, if it has no extension.
+    pub(super) name: Vec<u16>,
+}
+
+pub(super) struct Candidate {
+    /// The index of the prefix.
+    pub(super) prefix: usize,
+    pub(super) location: Location,
+    pub(super) path: Vec<u16>,
+    pub(super) exists: bool,
+}
+
+pub(super) struct ExeSearch {
+    /// Whether arg 0 is in quotes. Then there is only one prefix.
+    pub(super) quoted: bool,
+    pub(super) prefixes: Vec<Prefix>,
+    /// The candidates in the order they are tried, up to the file that is launched.
+    pub(super) candidates: Vec<Candidate>,
+    /// The index of the candidate, that is launched.
+    pub(super) found: Option<usize>,
+    /// The indices of the candidates of shorter prefixes, that are tried before the launched file.
+    /// Creating one of these files hijacks the launch.
+    pub(super) hijackable: Vec<usize>,
+    /// Existing files of longer prefixes, which are never tried, because a shorter prefix was found.
+    pub(super) shadowed: Vec<Candidate>,
+}
+
+fn is_blank(c: u16) -> bool {
+    c == SPACE || c == TAB
+}
+
+fn is_separator(c: u16) -> bool {
+    c == BACKSLASH || c == SLASH
+}
+
+/// Replaces slashes with backslashes and removes empty components, `.` and `..`.
+pub(super) fn normalize(path: &[u16]) -> Vec<u16> {
+    let path: Vec<u16> = path.iter().map(|&c| if c == SLASH { BACKSLASH } else { c }).collect();
+    let root_len =
+        if path.starts_with(&[BACKSLASH, BACKSLASH]) { 2 }
+        else if path.len() >= 3 && path[1] == COLON && path[2] == BACKSLASH { 3 }
+        else if path.len() >= 2 && path[1] == COLON { 2 }
+        else if path.first() == Some(&BACKSLASH) { 1 }
+        else { 0 };
+    let mut components: Vec<&[u16]> = Vec::new();
+    for component in path[root_len..].split(|&c| c == BACKSLASH) {
+        match component {
+            [] | [DOT] => {},
+            [DOT, DOT] => { components.pop(); },
+            _ => components.push(component),
+        }
+    }
+    let mut result = path[..root_len].to_vec();
+    result.extend(components.join(&BACKSLASH));
+    result
+}
+
+pub(super) fn join(dir: &[u16], name: &[u16]) -> Vec<u16> {
+    let mut path = dir.to_vec();
+    if path.last().is_some_and(|&c| !is_separator(c)) {
+        path.push(BACKSLASH);
+    }
+    path.extend_from_slice(name);
+    normalize(&path)
+}
+
+/// Paths are compared without regard to case.
+pub(super) fn key(path: &[u16]) -> String {
+    String::from_utf16_lossy(&normalize(path)).to_uppercase()
+}
+
+pub(super) fn has_path(name: &[u16]) -> bool {
+    name.iter().any(|&c| is_separator(c)) || (name.len() >= 2 && name[1] == COLON)
+}
+
+/// Resolves a name, that contains a path, relative to the current directory.
+pub(super) fn resolve(name: &[u16], cwd: &[u16]) -> Vec<u16> {
+    let is_absolute = (name.len() >= 2 && is_separator(name[0]) && is_separator(name[1]))
+        || (name.len() >= 3 && name[1] == COLON && is_separator(name[2]));
+    if is_absolute {
+        normalize(name)
+    } else if name.first().is_some_and(|&c| is_separator(c)) {
+        let drive = if cwd.len() >= 2 && cwd[1] == COLON { &cwd[..2] } else { &[][..] };
+        join(drive, name)
+    } else if name.len() >= 2 && name[1] == COLON {
+        if key(&name[..2]) == key(cwd.get(..2).unwrap_or_default()) {
+            join(cwd, &name[2..])
+        } else {
+            join(&[name[0], COLON, BACKSLASH], &name[2..])
+        }
+    } else {
+        join(cwd, name)
+    }
+}
+
+/// Appends `.exe`, if the file name has no extension.
+fn with_exe(name: &[u16]) -> Vec<u16> {
+    let file_name_start = name.iter().rposition(|&c| is_separator(c)).map_or(0, |p| p + 1);
+    let mut name = name.to_vec();
+    if !name[file_name_start..].contains(&DOT) {
+        name.extend(".exe".encode_utf16());
+    }
+    name
+}
+
+/// The names, that are tried one after another. If arg 0 is not quoted, every part of the command line
+/// that ends before a space or tab is a name, and finally the whole command line.
+pub(super) fn prefixes(cmdline: &[u16]) -> (bool, Vec<Prefix>) {
+    if cmdline.first() == Some(&QUOTE) {
+        let end = cmdline[1..].iter().position(|&c| c == QUOTE).map_or(cmdline.len(), |p| p + 1);
+        return (true, vec![Prefix { range: 1..end, name: with_exe(&cmdline[1..end]) }]);
+    }
+    let mut ends: Vec<usize> = (1..cmdline.len())
+        .filter(|&i| is_blank(cmdline[i]) && !is_blank(cmdline[i - 1]))
+        .collect();
+    if cmdline.last().is_some_and(|&c| !is_blank(c)) {
+        ends.push(cmdline.len());
+    }
+    let prefixes = ends.into_iter()
+        .map(|end| Prefix { range: 0..end, name: with_exe(&cmdline[..end]) })
+        .collect();
+    (false, prefixes)
+}
+
+/// Looks up an environment variable of the virtual filesystem.
+pub(super) fn env_var<'a>(fs: &'a VirtualFs, name: &str) -> Option<&'a str> {
+    fs.env.iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+pub(super) fn cwd(fs: &VirtualFs) -> Vec<u16> {
+    fs.cwd.as_deref().unwrap_or(DEFAULT_CWD).encode_utf16().collect()
+}
+
+/// The directories in `PATH`, without empty entries and quotes.
+pub(super) fn path_entries(fs: &VirtualFs) -> Vec<Vec<u16>> {
+    let path = fs.path.as_deref().or_else(|| env_var(fs, "PATH")).unwrap_or_default();
+    path.split(';')
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| entry.trim_matches('"').encode_utf16().collect())
+        .collect()
+}
+
+fn search_dirs(fs: &VirtualFs) -> Vec<(Location, Vec<u16>)> {
+    let dir = |value: &Option<String>, default: &str| -> Vec<u16> {
+        value.as_deref().unwrap_or(default).encode_utf16().collect()
+    };
+    let mut dirs = Vec::new();
+    if let Some(app_dir) = &fs.app_dir {
+        dirs.push((Location::AppDir, app_dir.encode_utf16().collect()));
+    }
+    dirs.push((Location::CurrentDir, cwd(fs)));
+    dirs.push((Location::SystemDir, dir(&fs.system_dir, DEFAULT_SYSTEM_DIR)));
+    dirs.push((Location::System16Dir, dir(&fs.system16_dir, DEFAULT_SYSTEM16_DIR)));
+    dirs.push((Location::WindowsDir, dir(&fs.windows_dir, DEFAULT_WINDOWS_DIR)));
+    for (index, entry) in path_entries(fs).into_iter().enumerate() {
+        dirs.push((Location::Path(index), entry));
+    }
+    dirs
+}
+
+fn candidates_of(prefix: usize, name: &[u16], cwd: &[u16], dirs: &[(Location, Vec<u16>)], files: &HashSet<String>)
+    -> Vec<Candidate>
+{
+    let paths: Vec<(Location, Vec<u16>)> = if has_path(name) {
+        vec![(Location::AsGiven, resolve(name, cwd))]
+    } else {
+        dirs.iter().map(|(location, dir)| (*location, join(dir, name))).collect()
+    };
+    paths.into_iter()
+        .map(|(location, path)| Candidate { prefix, exists: files.contains(&key(&path)), location, path })
+        .collect()
+}
+
+/// The keys of all files of the virtual filesystem.
+pub(super) fn file_keys(fs: &VirtualFs) -> HashSet<String> {
+    fs.files.iter()
+        .map(|file| key(&file.encode_utf16().collect::<Vec<u16>>()))
+        .collect()
+}
+
+/// Emulates how `CreateProcessW` finds the program, if `lpApplicationName` is NULL.
+pub(super) fn search_program(cmdline: &[u16], fs: &VirtualFs) -> ExeSearch {
+    let files = file_keys(fs);
+    let cwd = cwd(fs);
+    let dirs = search_dirs(fs);
+    let (quoted, prefixes) = prefixes(cmdline);
+
+    let mut candidates: Vec<Candidate> = Vec::new();
+    let mut found: Option<usize> = None;
+    let mut shadowed: Vec<Candidate> = Vec::new();
+    for (index, prefix) in prefixes.iter().enumerate() {
+        for candidate in candidates_of(index, &prefix.name, &cwd, &dirs, &files) {
+            if found.is_some() {
+                if candidate.exists {
+                    shadowed.push(candidate);
+                }
+                continue;
+            }
+            if candidate.exists {
+                found = Some(candidates.len());
+            }
+            candidates.push(candidate);
+        }
+    }
+
+    let hijack_limit = found.map_or(0, |found| candidates[found].prefix);
+    let hijackable = candidates.iter().enumerate()
+        .filter(|(_, candidate)| candidate.prefix < hijack_limit)
+        .map(|(index, _)| index)
+        .collect();
+
+    ExeSearch { quoted, prefixes, candidates, found, hijackable, shadowed }
+}
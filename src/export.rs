@@ -0,0 +1,243 @@
+use std::fmt::Write;
+
+use crate::commandline::{
+    get_rest,
+    parse_lp_cmd_line,
+};
+use crate::options::ExportLanguage;
+
+const QUOTE: u16 = b'"' as u16;
+const SPACE: u16 = b' ' as u16;
+
+/// The program and the command line, that are passed to CreateProcessW.
+pub(super) struct Launch<'a> {
+    pub(super) program: Option<&'a [u16]>,
+    pub(super) cmdline: Option<&'a [u16]>,
+}
+
+/// Splits the command line into argument zero and the rest, like `--strip-program` does.
+fn split_off_arg_zero(cmdline: &[u16]) -> (Vec<u16>, &[u16]) {
+    let parsed = parse_lp_cmd_line(cmdline, true);
+    match parsed.first() {
+        Some(arg) => (arg.to_utf16(), get_rest(cmdline, arg)),
+        None => (vec![], &[]),
+    }
+}
+
+/// Rust's `Command` and .NET's `ProcessStartInfo` put the program in quotes and append the raw arguments.
+fn quoted_program_cmdline(program: &[u16], raw_args: &[u16]) -> Vec<u16> {
+    let mut cmdline : Vec<u16> = vec![QUOTE];
+    cmdline.extend_from_slice(program);
+    cmdline.push(QUOTE);
+    if !raw_args.is_empty() {
+        cmdline.push(SPACE);
+        cmdline.extend_from_slice(raw_args);
+    }
+    cmdline
+}
+
+/// Notes for the cases, where the snippet does not exactly reproduce the launch.
+fn differences(launch: &Launch<'_>, cmdline: Option<&[u16]>, program_is_null: bool) -> Vec<String> {
+    let mut notes : Vec<String> = vec![];
+    if cmdline != launch.cmdline {
+        notes.push(format!("The command line is »{}« instead of »{}«.",
+                           cmdline.map_or("NULL".into(), String::from_utf16_lossy),
+                           launch.cmdline.map_or("NULL".into(), String::from_utf16_lossy)));
+    }
+    if program_is_null && launch.program.is_some() {
+        notes.push("The program (1st argument to CreateProcessW) is NULL.".to_owned());
+    }
+    notes
+}
+
+fn hex_units(units: &[u16]) -> String {
+    units.iter().map(|u| format!("0x{:04x}", u)).collect::<Vec<String>>().join(", ")
+}
+
+fn rust_literal(units: &[u16]) -> String {
+    match String::from_utf16(units) {
+        // `escape_default` escapes everything, that is not printable ASCII, with `\u{...}`.
+        Ok(string) => format!("\"{}\"", string.escape_default()),
+        // A Rust string cannot contain lone surrogates.
+        Err(_) => format!("OsString::from_wide(&[{}])", hex_units(units)),
+    }
+}
+
+fn export_rust(launch: &Launch<'_>) -> (String, Vec<String>) {
+    let mut snippet = String::new();
+    let cmdline = launch.cmdline.unwrap_or(&[]);
+    let (arg_zero, rest) = split_off_arg_zero(cmdline);
+    let program : &[u16] = launch.program.unwrap_or(&arg_zero);
+    let needs_os_string = String::from_utf16(program).is_err() || String::from_utf16(rest).is_err();
+
+    writeln!(snippet, "use std::os::windows::process::CommandExt;").unwrap();
+    if needs_os_string {
+        writeln!(snippet, "use std::ffi::OsString;").unwrap();
+        writeln!(snippet, "use std::os::windows::ffi::OsStringExt;").unwrap();
+    }
+    writeln!(snippet, "use std::process::Command;").unwrap();
+    writeln!(snippet).unwrap();
+    writeln!(snippet, "fn main() -> std::io::Result<()> {{").unwrap();
+    writeln!(snippet, "    let status = Command::new({})", rust_literal(program)).unwrap();
+    if !rest.is_empty() {
+        writeln!(snippet, "        .raw_arg({})", rust_literal(rest)).unwrap();
+    }
+    writeln!(snippet, "        .status()?;").unwrap();
+    writeln!(snippet, "    std::process::exit(status.code().unwrap_or(1));").unwrap();
+    writeln!(snippet, "}}").unwrap();
+
+    let produced = quoted_program_cmdline(program, rest);
+    let mut notes = differences(launch, Some(&produced), false);
+    if launch.cmdline.is_none() {
+        notes.push("`Command` always passes a command line.".to_owned());
+    }
+    notes.push("`Command` searches the program itself, and passes the path it found as the 1st argument to CreateProcessW.".to_owned());
+    (snippet, notes)
+}
+
+/// A wide string literal for C. Every code unit, that is not printable ASCII, is written as `\x`.
+/// Because `\x` takes all following hex digits, the literal is split after such an escape, if a hex digit follows.
+fn c_literal(units: &[u16]) -> String {
+    let mut literal = String::from("L\"");
+    let mut after_hex_escape = false;
+    for &u in units {
+        let c = char::from_u32(u32::from(u)).filter(|c| c.is_ascii() && !c.is_ascii_control());
+        match c {
+            Some(c) if after_hex_escape && c.is_ascii_hexdigit() => {
+                literal.push_str("\" L\"");
+                literal.push(c);
+            },
+            Some('\\') => literal.push_str("\\\\"),
+            Some('"') => literal.push_str("\\\""),
+            // avoid trigraphs
+            Some('?') => literal.push_str("\\?"),
+            Some(c) => literal.push(c),
+            None => {
+                write!(literal, "\\x{:04x}", u).unwrap();
+                after_hex_escape = true;
+                continue;
+            },
+        }
+        after_hex_escape = false;
+    }
+    literal.push('"');
+    literal
+}
+
+fn export_c(launch: &Launch<'_>) -> (String, Vec<String>) {
+    let mut snippet = String::new();
+    writeln!(snippet, "#include <windows.h>").unwrap();
+    writeln!(snippet, "#include <stdio.h>").unwrap();
+    writeln!(snippet).unwrap();
+    writeln!(snippet, "int main(void)").unwrap();
+    writeln!(snippet, "{{").unwrap();
+    match launch.program {
+        Some(program) => writeln!(snippet, "    const wchar_t *program = {};", c_literal(program)).unwrap(),
+        None => writeln!(snippet, "    const wchar_t *program = NULL;").unwrap(),
+    }
+    match launch.cmdline {
+        Some(cmdline) => {
+            writeln!(snippet, "    /* CreateProcessW may modify the command line, so it must not be a constant. */").unwrap();
+            writeln!(snippet, "    wchar_t cmdline[] = {};", c_literal(cmdline)).unwrap();
+        },
+        None => writeln!(snippet, "    wchar_t *cmdline = NULL;").unwrap(),
+    }
+    writeln!(snippet, "    STARTUPINFOW startup_info = {{ sizeof(startup_info) }};").unwrap();
+    writeln!(snippet, "    PROCESS_INFORMATION process_information;").unwrap();
+    writeln!(snippet, "    DWORD exit_code = 0;").unwrap();
+    writeln!(snippet).unwrap();
+    writeln!(snippet, "    if (!CreateProcessW(program, cmdline, NULL, NULL, FALSE, 0, NULL, NULL,").unwrap();
+    writeln!(snippet, "                        &startup_info, &process_information)) {{").unwrap();
+    writeln!(snippet, "        fprintf(stderr, \"CreateProcessW failed with %lu\\n\", GetLastError());").unwrap();
+    writeln!(snippet, "        return 1;").unwrap();
+    writeln!(snippet, "    }}").unwrap();
+    writeln!(snippet, "    CloseHandle(process_information.hThread);").unwrap();
+    writeln!(snippet, "    WaitForSingleObject(process_information.hProcess, INFINITE);").unwrap();
+    writeln!(snippet, "    GetExitCodeProcess(process_information.hProcess, &exit_code);").unwrap();
+    writeln!(snippet, "    CloseHandle(process_information.hProcess);").unwrap();
+    writeln!(snippet, "    return (int)exit_code;").unwrap();
+    writeln!(snippet, "}}").unwrap();
+    (snippet, vec![])
+}
+
+/// A Python string literal. Python strings can contain lone surrogates.
+fn python_literal(units: &[u16]) -> String {
+    let mut literal = String::from("\"");
+    for c in char::decode_utf16(units.iter().copied()) {
+        match c {
+            Ok('\\') => literal.push_str("\\\\"),
+            Ok('"') => literal.push_str("\\\""),
+            Ok(c) if c.is_ascii() && !c.is_ascii_control() => literal.push(c),
+            Ok(c) if u32::from(c) > 0xffff => write!(literal, "\\U{:08x}", u32::from(c)).unwrap(),
+            Ok(c) => write!(literal, "\\u{:04x}", u32::from(c)).unwrap(),
+            Err(e) => write!(literal, "\\u{:04x}", e.unpaired_surrogate()).unwrap(),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+fn export_python(launch: &Launch<'_>) -> (String, Vec<String>) {
+    let mut snippet = String::new();
+    let cmdline = launch.cmdline.unwrap_or(&[]);
+    writeln!(snippet, "import subprocess").unwrap();
+    writeln!(snippet, "import sys").unwrap();
+    writeln!(snippet).unwrap();
+    writeln!(snippet, "# On Windows a string is passed unchanged as the command line.").unwrap();
+    writeln!(snippet, "completed = subprocess.run(").unwrap();
+    writeln!(snippet, "    {},", python_literal(cmdline)).unwrap();
+    match launch.program {
+        Some(program) => writeln!(snippet, "    executable={},", python_literal(program)).unwrap(),
+        None => writeln!(snippet, "    executable=None,").unwrap(),
+    }
+    writeln!(snippet, ")").unwrap();
+    writeln!(snippet, "sys.exit(completed.returncode)").unwrap();
+    (snippet, differences(launch, Some(cmdline), false))
+}
+
+/// A double quoted PowerShell string. Everything, that is not printable ASCII, is written as `$([char]0x...)`,
+/// because Windows PowerShell 5.1 knows no other escape for it. Each code unit is written on its own.
+fn powershell_literal(units: &[u16]) -> String {
+    let mut literal = String::from("\"");
+    for &u in units {
+        match char::from_u32(u32::from(u)).filter(|c| c.is_ascii() && !c.is_ascii_control()) {
+            Some(c @ ('`' | '"' | '$')) => {
+                literal.push('`');
+                literal.push(c);
+            },
+            Some(c) => literal.push(c),
+            None => write!(literal, "$([char]0x{:04x})", u).unwrap(),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+fn export_powershell(launch: &Launch<'_>) -> (String, Vec<String>) {
+    let mut snippet = String::new();
+    let cmdline = launch.cmdline.unwrap_or(&[]);
+    let (arg_zero, rest) = split_off_arg_zero(cmdline);
+    let program : &[u16] = launch.program.unwrap_or(&arg_zero);
+
+    writeln!(snippet, "$startInfo = New-Object System.Diagnostics.ProcessStartInfo").unwrap();
+    writeln!(snippet, "$startInfo.FileName = {}", powershell_literal(program)).unwrap();
+    writeln!(snippet, "$startInfo.Arguments = {}", powershell_literal(rest)).unwrap();
+    writeln!(snippet, "$startInfo.UseShellExecute = $false").unwrap();
+    writeln!(snippet, "$process = [System.Diagnostics.Process]::Start($startInfo)").unwrap();
+    writeln!(snippet, "$process.WaitForExit()").unwrap();
+    writeln!(snippet, "exit $process.ExitCode").unwrap();
+
+    let produced = quoted_program_cmdline(program, rest);
+    (snippet, differences(launch, Some(&produced), true))
+}
+
+/// Creates code, that calls CreateProcessW like create-process-rust would.
+/// The notes describe where the code differs from the launch.
+pub(super) fn export_snippet(language: ExportLanguage, launch: &Launch<'_>) -> (String, Vec<String>) {
+    match language {
+        ExportLanguage::Rust => export_rust(launch),
+        ExportLanguage::C => export_c(launch),
+        ExportLanguage::Python => export_python(launch),
+        ExportLanguage::PowerShell => export_powershell(launch),
+    }
+}
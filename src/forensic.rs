@@ -0,0 +1,240 @@
+use base64::{engine::general_purpose::STANDARD as base64_STANDARD, Engine as _};
+
+use crate::audit::{
+    decode,
+    trim,
+    xml_elements_with_attributes,
+    xml_unescape,
+};
+use crate::commandline::{
+    parse_lp_cmd_line,
+    Arg,
+};
+use crate::confusables::find_confusables;
+use crate::exesearch::key;
+
+const QUOTE: u16 = b'"' as u16;
+const BACKSLASH: u16 = b'\\' as u16;
+const SLASH: u16 = b'/' as u16;
+
+/// Arguments shorter than this are not checked for Base64, to avoid flagging words and numbers.
+const MIN_ENCODED_LEN: usize = 32;
+
+/// The names of the fields, in the XML `<Data Name="...">` or as keys in JSON.
+const RECORD_ID_KEYS: [&str; 4] = ["EventRecordID", "RecordId", "record_id", "RecordNumber"];
+const EVENT_ID_KEYS: [&str; 4] = ["EventID", "EventId", "event_id", "Id"];
+/// Sysmon uses `Image`, the Security event 4688 uses `NewProcessName`.
+const IMAGE_KEYS: [&str; 2] = ["Image", "NewProcessName"];
+const CMDLINE_KEYS: [&str; 1] = ["CommandLine"];
+
+pub(super) enum Anomaly {
+    /// Argument zero names another file than the image.
+    Arg0Mismatch,
+    /// The closing quote of the argument is missing.
+    UnterminatedQuote(usize),
+    /// The argument contains quotes escaped with `\"` or `""`.
+    EscapedQuotes(usize),
+    /// Quotes inside of a word, like `c"m"d`, which don't change the argument.
+    InnerQuotes(usize),
+    /// Characters, that look like others or are invisible, like `–enc` with an en dash.
+    Confusables(usize, Vec<u16>),
+    /// A Base64 argument. The decoded text, if it is UTF-16LE like for `-EncodedCommand` of PowerShell.
+    EncodedPayload(usize, Option<Vec<u16>>),
+}
+
+impl Anomaly {
+    pub(super) fn as_str(&self) -> &'static str {
+        match self {
+            Anomaly::Arg0Mismatch => "arg0-mismatch",
+            Anomaly::UnterminatedQuote(_) => "unterminated-quote",
+            Anomaly::EscapedQuotes(_) => "escaped-quotes",
+            Anomaly::InnerQuotes(_) => "inner-quotes",
+            Anomaly::Confusables(_, _) => "confusables",
+            Anomaly::EncodedPayload(_, _) => "encoded-payload",
+        }
+    }
+
+    /// The number of the argument.
+    pub(super) fn arg(&self) -> usize {
+        match self {
+            Anomaly::Arg0Mismatch => 0,
+            Anomaly::UnterminatedQuote(n) | Anomaly::EscapedQuotes(n) | Anomaly::InnerQuotes(n)
+                | Anomaly::Confusables(n, _) | Anomaly::EncodedPayload(n, _) => *n,
+        }
+    }
+
+    pub(super) fn describe(&self) -> String {
+        match self {
+            Anomaly::Arg0Mismatch => "Argument zero does not name the image".to_owned(),
+            Anomaly::UnterminatedQuote(n) => format!("Argument {} has no closing quote", n),
+            Anomaly::EscapedQuotes(n) => format!("Argument {} contains escaped quotes", n),
+            Anomaly::InnerQuotes(n) => format!("Argument {} has quotes inside of a word", n),
+            Anomaly::Confusables(n, code_points) => format!("Argument {} contains {}", n,
+                code_points.iter().map(|c| format!("U+{:04X}", c)).collect::<Vec<String>>().join(", ")),
+            Anomaly::EncodedPayload(n, None) => format!("Argument {} is Base64", n),
+            Anomaly::EncodedPayload(n, Some(decoded)) => format!("Argument {} is Base64 of »{}«", n, String::from_utf16_lossy(decoded)),
+        }
+    }
+}
+
+pub(super) struct ProcessEvent {
+    pub(super) record_id: Option<String>,
+    pub(super) event_id: Option<String>,
+    pub(super) image: Option<Vec<u16>>,
+    pub(super) cmdline: Vec<u16>,
+    pub(super) args: Vec<Vec<u16>>,
+    pub(super) anomalies: Vec<Anomaly>,
+}
+
+/// The value of the first field with one of the names.
+fn xml_field(event: &[u16], element: &str, names: &[&str]) -> Option<Vec<u16>> {
+    let elements = xml_elements_with_attributes(event, element);
+    names.iter().find_map(|name| {
+        let attributes = [format!("Name=\"{}\"", name), format!("Name='{}'", name)];
+        elements.iter()
+            .find(|(element_attributes, _)| {
+                let element_attributes = String::from_utf16_lossy(element_attributes);
+                attributes.iter().any(|attribute| element_attributes.contains(attribute.as_str()))
+            })
+            .map(|(_, text)| xml_unescape(text))
+    })
+}
+
+/// The text of the first element with one of the names.
+fn xml_text(event: &[u16], names: &[&str]) -> Option<String> {
+    names.iter().find_map(|name| {
+        xml_elements_with_attributes(event, name).first().map(|(_, text)| String::from_utf16_lossy(&xml_unescape(trim(text))))
+    })
+}
+
+/// Searches the object and the objects in it for the first key with one of the names.
+fn json_field<'a>(value: &'a serde_json::Value, names: &[&str]) -> Option<&'a serde_json::Value> {
+    let object = value.as_object()?;
+    names.iter().find_map(|name| object.get(*name))
+        .or_else(|| object.values().find_map(|value| json_field(value, names)))
+}
+
+fn json_string(value: Option<&serde_json::Value>) -> Option<String> {
+    match value? {
+        serde_json::Value::String(string) => Some(string.clone()),
+        serde_json::Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    }
+}
+
+fn file_name(path: &[u16]) -> &[u16] {
+    let start = path.iter().rposition(|&c| c == BACKSLASH || c == SLASH).map_or(0, |p| p + 1);
+    &path[start..]
+}
+
+/// Argument zero may be the full path, a relative path or only the name of the file, maybe without `.exe`.
+fn arg0_matches(arg0: &[u16], image: &[u16]) -> bool {
+    let is_absolute = arg0.len() >= 3 && arg0[1] == b':' as u16 || arg0.starts_with(&[BACKSLASH, BACKSLASH]);
+    if is_absolute {
+        return key(arg0) == key(image);
+    }
+    let name = key(file_name(arg0));
+    let image_name = key(file_name(image));
+    name == image_name || format!("{}.EXE", name) == image_name
+}
+
+/// Quotes inside of a word, that protect no whitespace, like `c"m"d` or `pow""ershell`.
+/// `--opt="a b"` and `"C:\x"` are usual.
+fn has_inner_quotes(raw: &[u16]) -> bool {
+    let segments : Vec<&[u16]> = raw.split(|&c| c == QUOTE).collect();
+    let in_word = segments.iter().step_by(2).any(|segment| !segment.is_empty());
+    let useless = segments.iter().skip(1).step_by(2).any(|segment| !segment.iter().any(|&c| c == b' ' as u16 || c == b'\t' as u16));
+    in_word && useless
+}
+
+fn decode_base64(arg: &[u16]) -> Option<Option<Vec<u16>>> {
+    if arg.len() < MIN_ENCODED_LEN || !arg.len().is_multiple_of(4) {
+        return None;
+    }
+    let text = String::from_utf16(arg).ok()?;
+    if !text.chars().all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '/' || c == '=') {
+        return None;
+    }
+    // Hashes in hex are not Base64.
+    if text.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let bytes = base64_STANDARD.decode(text).ok()?;
+    // Text in UTF-16LE has mostly zero high bytes.
+    let units : Vec<u16> = bytes.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+    let is_utf16 = bytes.len().is_multiple_of(2) && !units.is_empty() && units.iter().all(|&u| u >= 0x20 || u == 0x09 || u == 0x0a || u == 0x0d);
+    Some(if is_utf16 { Some(units) } else { None })
+}
+
+fn find_anomalies(args: &[Arg<'_>], image: Option<&[u16]>) -> Vec<Anomaly> {
+    let mut anomalies : Vec<Anomaly> = vec![];
+    if let (Some(arg0), Some(image)) = (args.first(), image) {
+        if !arg0_matches(&arg0.to_utf16(), image) {
+            anomalies.push(Anomaly::Arg0Mismatch);
+        }
+    }
+    for arg in args {
+        if arg.info.unterminated {
+            anomalies.push(Anomaly::UnterminatedQuote(arg.number));
+        }
+        if arg.info.escaped_quotes != 0 || arg.info.doubled_quotes != 0 {
+            anomalies.push(Anomaly::EscapedQuotes(arg.number));
+        }
+        else if has_inner_quotes(arg.raw) {
+            anomalies.push(Anomaly::InnerQuotes(arg.number));
+        }
+        let confusables = find_confusables(arg);
+        if !confusables.is_empty() {
+            anomalies.push(Anomaly::Confusables(arg.number, confusables.iter().map(|c| c.code_point).collect()));
+        }
+        if let Some(decoded) = decode_base64(&arg.to_utf16()) {
+            anomalies.push(Anomaly::EncodedPayload(arg.number, decoded));
+        }
+    }
+    anomalies
+}
+
+fn process_event(record_id: Option<String>, event_id: Option<String>, image: Option<Vec<u16>>, cmdline: Vec<u16>) -> ProcessEvent {
+    let parsed = parse_lp_cmd_line(&cmdline, true);
+    let anomalies = find_anomalies(&parsed, image.as_deref());
+    let args = parsed.iter().map(|arg| arg.to_utf16()).collect();
+    ProcessEvent { record_id, event_id, image, cmdline, args, anomalies }
+}
+
+/// Reads the process creation events, Sysmon event 1 or Security event 4688, from an export of events as XML,
+/// or from JSON lines. Events without a command line are skipped.
+pub(super) fn read_process_events(bytes: &[u8]) -> Result<Vec<ProcessEvent>, String> {
+    let text = decode(bytes)?;
+    let text = trim(&text);
+    if text.first() == Some(&(b'<' as u16)) {
+        return Ok(xml_elements_with_attributes(text, "Event").into_iter().filter_map(|(_, event)| {
+            let cmdline = xml_field(event, "Data", &CMDLINE_KEYS)?;
+            Some(process_event(
+                xml_text(event, &RECORD_ID_KEYS),
+                xml_text(event, &EVENT_ID_KEYS),
+                xml_field(event, "Data", &IMAGE_KEYS),
+                cmdline,
+            ))
+        }).collect());
+    }
+
+    let mut events : Vec<ProcessEvent> = vec![];
+    for (number, line) in String::from_utf16_lossy(text).lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let value : serde_json::Value = serde_json::from_str(line)
+            .map_err(|error| format!("Line {} is not JSON: {}", number + 1, error))?;
+        let cmdline = match json_field(&value, &CMDLINE_KEYS).and_then(|v| v.as_str()) {
+            Some(cmdline) => cmdline.encode_utf16().collect(),
+            None => continue,
+        };
+        events.push(process_event(
+            json_string(json_field(&value, &RECORD_ID_KEYS)),
+            json_string(json_field(&value, &EVENT_ID_KEYS)),
+            json_field(&value, &IMAGE_KEYS).and_then(|v| v.as_str()).map(|image| image.encode_utf16().collect()),
+            cmdline,
+        ));
+    }
+    Ok(events)
+}
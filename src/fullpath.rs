@@ -0,0 +1,313 @@
+const BACKSLASH: u16 = b'\\' as u16;
+const SLASH: u16 = b'/' as u16;
+const COLON: u16 = b':' as u16;
+const DOT: u16 = b'.' as u16;
+const SPACE: u16 = b' ' as u16;
+const QUESTION_MARK: u16 = b'?' as u16;
+
+/// Paths with this length or more don't fit into a buffer of `MAX_PATH` code units with the terminating NUL.
+pub const MAX_PATH: usize = 260;
+/// The maximum length of a path in UTF-16 code units.
+pub const MAX_LONG_PATH: usize = 32767;
+
+/// The device names, that are reserved in every directory. `COM¹`, `COM²`, `COM³` and the same for `LPT` are also reserved.
+const DEVICE_NAMES: [&str; 4] = ["CON", "PRN", "AUX", "NUL"];
+const NUMBERED_DEVICE_NAMES: [&str; 2] = ["COM", "LPT"];
+const SUPERSCRIPT_DIGITS: [u16; 3] = [0x00b9, 0x00b2, 0x00b3];
+
+/// The type of a path, like `RtlDetermineDosPathNameType_U` determines it.
+#[derive(Clone, Copy, PartialEq)]
+pub enum PathKind {
+    /// `\\?\C:\x`: passed on without any change.
+    Verbatim,
+    /// `\\.\C:\x` or `//?/C:/x`: normalized, but not relative to any directory.
+    LocalDevice,
+    /// Only `\\.` or `\\?`.
+    RootLocalDevice,
+    /// `\\server\share\x`
+    Unc,
+    /// `C:\x`
+    DriveAbsolute,
+    /// `C:x`: relative to the current directory of drive `C:`.
+    DriveRelative,
+    /// `\x`: relative to the root of the current directory.
+    Rooted,
+    /// `x`: relative to the current directory.
+    Relative,
+}
+
+impl PathKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PathKind::Verbatim => "verbatim",
+            PathKind::LocalDevice => "local-device",
+            PathKind::RootLocalDevice => "root-local-device",
+            PathKind::Unc => "unc",
+            PathKind::DriveAbsolute => "drive-absolute",
+            PathKind::DriveRelative => "drive-relative",
+            PathKind::Rooted => "rooted",
+            PathKind::Relative => "relative",
+        }
+    }
+}
+
+/// A change, that `GetFullPathNameW` makes, or a problem of the full path.
+pub enum Note {
+    SlashesReplaced,
+    /// The path is appended to this directory.
+    Joined(Vec<u16>),
+    SeparatorsCollapsed,
+    DotSegmentsRemoved,
+    /// `..` in the root is ignored.
+    ParentOfRoot,
+    /// Trailing dots and spaces are removed from the last segment, and a single trailing dot from the others.
+    TrailingDotsAndSpaces,
+    /// The last segment is a reserved device name, so the path is the device.
+    DeviceName(Vec<u16>),
+    /// The full path needs long path support or a `\\?\` prefix.
+    MaxPath(usize),
+    TooLong(usize),
+}
+
+impl Note {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Note::SlashesReplaced => "slashes-replaced",
+            Note::Joined(_) => "joined",
+            Note::SeparatorsCollapsed => "separators-collapsed",
+            Note::DotSegmentsRemoved => "dot-segments-removed",
+            Note::ParentOfRoot => "parent-of-root",
+            Note::TrailingDotsAndSpaces => "trailing-dots-and-spaces",
+            Note::DeviceName(_) => "device-name",
+            Note::MaxPath(_) => "max-path",
+            Note::TooLong(_) => "too-long",
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Note::SlashesReplaced => "slashes are replaced with backslashes".to_owned(),
+            Note::Joined(dir) => format!("the path is relative to »{}«", String::from_utf16_lossy(dir)),
+            Note::SeparatorsCollapsed => "consecutive separators are collapsed".to_owned(),
+            Note::DotSegmentsRemoved => "the segments `.` and `..` are removed".to_owned(),
+            Note::ParentOfRoot => "`..` in the root is ignored".to_owned(),
+            Note::TrailingDotsAndSpaces => "trailing dots and spaces are removed".to_owned(),
+            Note::DeviceName(name) => format!("»{}« is a reserved device name, the path is the device", String::from_utf16_lossy(name)),
+            Note::MaxPath(len) => format!("the full path has {} code units, which does not fit into MAX_PATH ({}) \
+                                           without long path support or a \\\\?\\ prefix", len, MAX_PATH),
+            Note::TooLong(len) => format!("the full path has {} code units, more than {}", len, MAX_LONG_PATH),
+        }
+    }
+
+    /// Whether the note is a problem, rather than a change.
+    pub fn is_problem(&self) -> bool {
+        matches!(self, Note::DeviceName(_) | Note::MaxPath(_) | Note::TooLong(_))
+    }
+}
+
+/// The current directory and the current directories of other drives, like in the environment variables `=D:`.
+pub struct CurrentDirs {
+    pub cwd: Vec<u16>,
+    pub drives: Vec<Vec<u16>>,
+}
+
+impl CurrentDirs {
+    /// The current directory of a drive. Drives without one are in their root.
+    fn of_drive(&self, letter: u16) -> Vec<u16> {
+        let same_drive = |dir: &Vec<u16>| dir.len() >= 2 && dir[1] == COLON && upper(dir[0]) == upper(letter);
+        if same_drive(&self.cwd) {
+            return self.cwd.clone();
+        }
+        match self.drives.iter().find(|dir| same_drive(dir)) {
+            Some(dir) => dir.clone(),
+            None => vec![letter, COLON, BACKSLASH],
+        }
+    }
+}
+
+pub struct FullPath {
+    pub path: Vec<u16>,
+    pub kind: PathKind,
+    pub full_path: Vec<u16>,
+    pub notes: Vec<Note>,
+}
+
+fn is_separator(c: u16) -> bool {
+    c == BACKSLASH || c == SLASH
+}
+
+fn upper(c: u16) -> u16 {
+    if (b'a' as u16..=b'z' as u16).contains(&c) { c - 0x20 } else { c }
+}
+
+fn is_drive_letter(c: u16) -> bool {
+    (b'A' as u16..=b'Z' as u16).contains(&upper(c))
+}
+
+pub fn path_kind(path: &[u16]) -> PathKind {
+    let sep = |i: usize| path.get(i).is_some_and(|&c| is_separator(c));
+    if sep(0) && sep(1) {
+        let device_char = path.get(2).is_some_and(|&c| c == DOT || c == QUESTION_MARK);
+        if device_char && sep(3) {
+            if path[..4] == [BACKSLASH, BACKSLASH, QUESTION_MARK, BACKSLASH] {
+                PathKind::Verbatim
+            } else {
+                PathKind::LocalDevice
+            }
+        } else if device_char && path.len() == 3 {
+            PathKind::RootLocalDevice
+        } else {
+            PathKind::Unc
+        }
+    } else if sep(0) {
+        PathKind::Rooted
+    } else if path.len() >= 2 && path[1] == COLON && is_drive_letter(path[0]) {
+        if sep(2) { PathKind::DriveAbsolute } else { PathKind::DriveRelative }
+    } else {
+        PathKind::Relative
+    }
+}
+
+/// The length of the root, that `..` cannot remove: `C:\`, `\\server\share\` or `\\.\device\`.
+fn root_len(path: &[u16], kind: PathKind) -> usize {
+    let after_segments = |start: usize, count: usize| -> usize {
+        let mut end = start;
+        for _ in 0..count {
+            end = match path[end..].iter().position(|&c| c == BACKSLASH) {
+                Some(p) => end + p + 1,
+                None => return path.len(),
+            };
+        }
+        end
+    };
+    match kind {
+        PathKind::Unc => after_segments(2, 2),
+        PathKind::LocalDevice => after_segments(4, 1),
+        PathKind::DriveAbsolute => 3,
+        PathKind::DriveRelative => 2,
+        PathKind::Rooted => 1,
+        _ => 0,
+    }
+}
+
+/// Returns the reserved device name, if the last segment is one, ignoring an extension and trailing spaces.
+fn device_name(path: &[u16]) -> Option<Vec<u16>> {
+    let start = path.iter().rposition(|&c| c == BACKSLASH).map_or(0, |p| p + 1);
+    let segment = &path[start..];
+    let end = segment.iter().position(|&c| c == DOT || c == COLON).unwrap_or(segment.len());
+    let mut name = &segment[..end];
+    while name.last() == Some(&SPACE) {
+        name = &name[..name.len() - 1];
+    }
+    let upper_name : Vec<u16> = name.iter().map(|&c| upper(c)).collect();
+    let matches = |candidate: &str| upper_name.iter().copied().eq(candidate.encode_utf16());
+    let reserved = DEVICE_NAMES.iter().any(|n| matches(n))
+        || (upper_name.len() == 4
+            && NUMBERED_DEVICE_NAMES.iter().any(|n| upper_name[..3].iter().copied().eq(n.encode_utf16()))
+            && ((b'1' as u16..=b'9' as u16).contains(&upper_name[3]) || SUPERSCRIPT_DIGITS.contains(&upper_name[3])));
+    reserved.then_some(upper_name)
+}
+
+/// Removes `.` and `..`, collapses separators and removes trailing dots and spaces after the root.
+fn normalize_segments(path: &[u16], root: usize, notes: &mut Vec<Note>) -> Vec<u16> {
+    let ends_with_separator = path.len() > root && path.last() == Some(&BACKSLASH);
+    let segments : Vec<&[u16]> = path[root..].split(|&c| c == BACKSLASH).collect();
+    let mut kept : Vec<Vec<u16>> = vec![];
+    let (mut collapsed, mut dot_segments, mut parent_of_root, mut trimmed) = (false, false, false, false);
+    let last = segments.len() - 1;
+    for (index, &segment) in segments.iter().enumerate() {
+        match segment {
+            [] => collapsed |= index != last,
+            [DOT] => dot_segments = true,
+            [DOT, DOT] => {
+                dot_segments = true;
+                parent_of_root |= kept.pop().is_none();
+            },
+            _ => {
+                let mut segment = segment.to_vec();
+                if index == last {
+                    while segment.last().is_some_and(|&c| c == DOT || c == SPACE) {
+                        segment.pop();
+                        trimmed = true;
+                    }
+                } else if segment.ends_with(&[DOT]) && !segment.ends_with(&[DOT, DOT]) {
+                    segment.pop();
+                    trimmed = true;
+                }
+                if !segment.is_empty() {
+                    kept.push(segment);
+                }
+            },
+        }
+    }
+    if collapsed { notes.push(Note::SeparatorsCollapsed); }
+    if dot_segments { notes.push(Note::DotSegmentsRemoved); }
+    if parent_of_root { notes.push(Note::ParentOfRoot); }
+    if trimmed { notes.push(Note::TrailingDotsAndSpaces); }
+
+    let mut result = path[..root].to_vec();
+    result.extend(kept.join(&BACKSLASH));
+    if ends_with_separator && result.last() != Some(&BACKSLASH) {
+        result.push(BACKSLASH);
+    }
+    result
+}
+
+/// Emulates `GetFullPathNameW`. The path is made absolute with the current directories,
+/// but in contrast to Windows, the file system is never accessed.
+pub fn get_full_path_name(path: &[u16], dirs: &CurrentDirs) -> FullPath {
+    let kind = path_kind(path);
+    let mut notes : Vec<Note> = vec![];
+    if kind == PathKind::Verbatim || kind == PathKind::RootLocalDevice {
+        return FullPath { path: path.to_vec(), kind, full_path: path.to_vec(), notes };
+    }
+
+    let mut joined : Vec<u16> = path.iter().map(|&c| if c == SLASH { BACKSLASH } else { c }).collect();
+    if joined != path {
+        notes.push(Note::SlashesReplaced);
+    }
+    let base : Option<Vec<u16>> = match kind {
+        PathKind::Relative => Some(dirs.cwd.clone()),
+        PathKind::DriveRelative => {
+            joined.drain(..2);
+            Some(dirs.of_drive(path[0]))
+        },
+        PathKind::Rooted => {
+            let cwd_kind = path_kind(&dirs.cwd);
+            Some(dirs.cwd[..root_len(&dirs.cwd, cwd_kind)].to_vec())
+        },
+        _ => None,
+    };
+    let base_joined = base.is_some();
+    if let Some(base) = base {
+        let mut full : Vec<u16> = base.iter().map(|&c| if c == SLASH { BACKSLASH } else { c }).collect();
+        notes.push(Note::Joined(full.clone()));
+        match (full.last() == Some(&BACKSLASH), joined.first() == Some(&BACKSLASH)) {
+            (true, true) => { full.pop(); },
+            (false, false) if !joined.is_empty() => full.push(BACKSLASH),
+            _ => {},
+        }
+        full.extend_from_slice(&joined);
+        joined = full;
+    }
+
+    // `//?/` becomes `\\?\`, but is still normalized like `\\.\`.
+    let full_kind = if base_joined { path_kind(&joined) } else { kind };
+    if matches!(kind, PathKind::Relative | PathKind::DriveRelative | PathKind::Rooted | PathKind::DriveAbsolute) {
+        if let Some(name) = device_name(&joined) {
+            let mut device : Vec<u16> = "\\\\.\\".encode_utf16().collect();
+            device.extend_from_slice(&name);
+            notes.push(Note::DeviceName(name));
+            return FullPath { path: path.to_vec(), kind, full_path: device, notes };
+        }
+    }
+    let root = root_len(&joined, full_kind);
+    let full_path = normalize_segments(&joined, root, &mut notes);
+
+    if full_path.len() > MAX_LONG_PATH {
+        notes.push(Note::TooLong(full_path.len()));
+    } else if full_path.len() >= MAX_PATH {
+        notes.push(Note::MaxPath(full_path.len()));
+    }
+    FullPath { path: path.to_vec(), kind, full_path, notes }
+}
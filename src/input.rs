@@ -1,19 +1,65 @@
 use std::fs::File;
 use std::{
+    collections::HashMap,
     ffi::OsStr,
     ffi::OsString,
     io,
+    os::windows::ffi::OsStrExt,
+    os::windows::ffi::OsStringExt,
 };
 
 use serde::{
     Deserialize,
 };
 
+use crate::envblock::EnvEdit;
+use crate::template::TemplateValues;
+use crate::options::{
+    ArgsOpt,
+    CmdlineOpt,
+    CmdlineOrArgsOpt,
+    EnvOpt,
+    TemplateOpt,
+};
+
 #[derive(Deserialize, Debug)]
 pub(super) struct JsonUserInput {
     args: Option<Vec<String>>,
     cmdline: Option<String>,
     program: Option<String>,
+    template: Option<String>,
+    values: Option<HashMap<String, JsonTemplateValue>>,
+    /// Environment variables to set, or to remove, if the value is null.
+    env: Option<HashMap<String, Option<String>>>,
+}
+
+/// The value of a placeholder: a string, or an array of strings for a variadic placeholder.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub(super) enum JsonTemplateValue {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// The files and directories, that the emulated search for the program sees.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub(super) struct VirtualFs {
+    #[serde(default)]
+    pub(super) files: Vec<String>,
+    pub(super) app_dir: Option<String>,
+    pub(super) cwd: Option<String>,
+    pub(super) system_dir: Option<String>,
+    pub(super) system16_dir: Option<String>,
+    pub(super) windows_dir: Option<String>,
+    /// The value of the environment variable `PATH`. If it is missing, `PATH` is taken from `env`.
+    pub(super) path: Option<String>,
+    /// Environment variables, for example `PATHEXT`. The names are compared without regard to case.
+    #[serde(default)]
+    pub(super) env: HashMap<String, String>,
+    /// The registry key `App Paths`: the name of a program, like `app.exe`, and its path.
+    #[serde(default)]
+    pub(super) app_paths: HashMap<String, String>,
 }
 
 pub(super) enum StdInOrBufReader{
@@ -48,20 +94,17 @@ pub(super) fn read_user_input_from_file(file : &OsStr) -> Result<JsonUserInput,
     Ok(user_input)
 }
 
-fn get_cmdline_from_args<'a, I, S>(args : I) -> String
-where
-    S : AsRef<str> + 'a ,
-    I : std::iter::Iterator<Item = S>
-{
-    let result : String = String::new();
-    for arg in args {
-        let s : &str = arg.as_ref();
-        println!("DEBUG: {}", s);
-    }
-    return result;
+pub(super) fn read_virtual_fs_from_file(file : &OsStr) -> Result<VirtualFs, String> {
+    let mut reader = if is_filename_stdin(file) {
+        StdInOrBufReader::StdIn(io::stdin().lock())
+    } else {
+        let file = File::open(file).map_err(|error| error.to_string())?;
+        StdInOrBufReader::BufReader(std::io::BufReader::new(file))
+    };
+    let mut de = serde_json::Deserializer::from_reader(reader.into_writer());
+    VirtualFs::deserialize(&mut de).map_err(|error| error.to_string())
 }
 
-
 pub(super) fn get_cmdline_from_json(json_user_input : &JsonUserInput) -> Result<OsString,String> {
     if json_user_input.cmdline.is_some() && json_user_input.args.is_some() {
         return Err("Do not provide \"args\" and \"cmdline\" in JSON".to_owned());
@@ -70,8 +113,7 @@ pub(super) fn get_cmdline_from_json(json_user_input : &JsonUserInput) -> Result<
     if let Some(cmdline) = &json_user_input.cmdline {
         Ok(OsString::from(cmdline))
     }else if let Some(args) = &json_user_input.args {
-        let cmdline : String = get_cmdline_from_args(args.iter());
-        Ok(OsStr::new("").to_owned())
+        Ok(OsString::from_wide(&crate::commandline::cmdline_from_args(args)?))
     }
     else{
         Err("".to_owned())
@@ -81,3 +123,121 @@ pub(super) fn get_program_from_json(json_user_input : &JsonUserInput) -> Result<
 
     return Err("TEMP TODO".to_owned());
 }
+
+pub(super) fn get_args_from_json(json_user_input : &JsonUserInput) -> Result<Vec<OsString>,String> {
+    match &json_user_input.args {
+        Some(args) => Ok(args.iter().map(OsString::from).collect()),
+        None => Err("JSON does not contain \"args\"".to_owned()),
+    }
+}
+
+/// Reads one argument per line. A line ending with CR LF is allowed.
+pub(super) fn get_args_from_file(file : &OsStr) -> Result<Vec<OsString>,String> {
+    let mut reader = if is_filename_stdin(file) {
+        StdInOrBufReader::StdIn(io::stdin().lock())
+    } else {
+        let file = File::open(file).map_err(|error| error.to_string())?;
+        StdInOrBufReader::BufReader(std::io::BufReader::new(file))
+    };
+    let mut text = String::new();
+    reader.into_writer().read_to_string(&mut text).map_err(|error| error.to_string())?;
+    Ok(text.lines().map(OsString::from).collect())
+}
+
+pub(super) fn get_args(args_opt : ArgsOpt) -> Result<Vec<OsString>,String> {
+    match args_opt {
+        ArgsOpt::Rest(args) => Ok(args),
+        ArgsOpt::FromJSONFile(file_name) => get_args_from_json(&read_user_input_from_file(&file_name)?),
+        ArgsOpt::FromFile(file_name) => get_args_from_file(&file_name),
+    }
+}
+
+pub(super) fn get_cmdline(cmdline_opt : CmdlineOpt) -> Result<Option<OsString>,String> {
+    match cmdline_opt {
+        CmdlineOpt::Str(os_str) => Ok(Some(os_str)),
+        CmdlineOpt::Null => Ok(None),
+        CmdlineOpt::FromJSONFile(file_name) => Ok(Some(get_cmdline_from_json(&read_user_input_from_file(&file_name)?)?)),
+    }
+}
+
+/// Gets the command line, or puts it together from the args.
+pub(super) fn get_cmdline_or_args(opt : CmdlineOrArgsOpt) -> Result<Vec<u16>,String> {
+    match opt {
+        CmdlineOrArgsOpt::Cmdline(cmdline_opt) => match get_cmdline(cmdline_opt)? {
+            Some(cmdline) => Ok(cmdline.encode_wide().collect()),
+            None => Err("cmd line is NULL".to_owned()),
+        },
+        CmdlineOrArgsOpt::Args(args_opt) => crate::commandline::cmdline_from_args(&get_args(args_opt)?),
+    }
+}
+
+/// Gets the template and the values of its placeholders. The values from the command line come after those from JSON.
+pub(super) fn get_template(template_opt : TemplateOpt, cli_values : Vec<(String, OsString)>)
+    -> Result<(Vec<u16>, TemplateValues),String>
+{
+    let mut values : TemplateValues = HashMap::new();
+    let template : OsString = match template_opt {
+        TemplateOpt::Str(template) => template,
+        TemplateOpt::FromJSONFile(file_name) => {
+            let json_user_input = read_user_input_from_file(&file_name)?;
+            for (name, value) in json_user_input.values.unwrap_or_default() {
+                let value_list = match value {
+                    JsonTemplateValue::One(one) => vec![one],
+                    JsonTemplateValue::Many(many) => many,
+                };
+                values.insert(name, value_list.iter().map(|v| v.encode_utf16().collect()).collect());
+            }
+            match json_user_input.template {
+                Some(template) => OsString::from(template),
+                None => return Err("JSON does not contain \"template\"".to_owned()),
+            }
+        },
+    };
+    for (name, value) in cli_values {
+        values.entry(name).or_default().push(value.encode_wide().collect());
+    }
+    Ok((template.encode_wide().collect(), values))
+}
+
+/// Turns the environment options into edits. The variables of a JSON file are applied in the order of their names.
+pub(super) fn get_env_edits(env_opts : Vec<EnvOpt>) -> Result<Vec<EnvEdit>,String> {
+    let mut edits : Vec<EnvEdit> = vec![];
+    for env_opt in env_opts {
+        match env_opt {
+            EnvOpt::Set(assignment) => {
+                let (name, value) = crate::envblock::split_assignment(&assignment.encode_wide().collect::<Vec<u16>>())?;
+                edits.push(EnvEdit::Set(name, value));
+            },
+            EnvOpt::Unset(name) => edits.push(EnvEdit::Unset(name.encode_wide().collect())),
+            EnvOpt::Clear => edits.push(EnvEdit::Clear),
+            EnvOpt::FromJSONFile(file_name) => {
+                let env = match read_user_input_from_file(&file_name)?.env {
+                    Some(env) => env,
+                    None => return Err("JSON does not contain \"env\"".to_owned()),
+                };
+                let mut vars : Vec<(String, Option<String>)> = env.into_iter().collect();
+                vars.sort();
+                for (name, value) in vars {
+                    edits.push(match value {
+                        Some(value) => EnvEdit::Set(name.encode_utf16().collect(), value.encode_utf16().collect()),
+                        None => EnvEdit::Unset(name.encode_utf16().collect()),
+                    });
+                }
+            },
+        }
+    }
+    Ok(edits)
+}
+
+pub(super) fn read_bytes_from_file(file : &OsStr) -> Result<Vec<u8>, String> {
+    let mut reader = if is_filename_stdin(file) {
+        StdInOrBufReader::StdIn(io::stdin().lock())
+    } else {
+        let file = File::open(file).map_err(|error| error.to_string())?;
+        let buf_reader = std::io::BufReader::new(file);
+        StdInOrBufReader::BufReader(buf_reader)
+    };
+    let mut bytes : Vec<u8> = vec![];
+    reader.into_writer().read_to_end(&mut bytes).map_err(|error| error.to_string())?;
+    Ok(bytes)
+}
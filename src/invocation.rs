@@ -0,0 +1,654 @@
+use std::{
+    ffi::OsString,
+    os::windows::ffi::OsStringExt,
+};
+
+use crate::commandline::{
+    append_arg,
+    escape_arg_zero,
+    parse_lp_cmd_line,
+};
+
+const BACKSLASH: u16 = b'\\' as u16;
+const QUOTE: u16 = b'"' as u16;
+const TAB: u16 = b'\t' as u16;
+const SPACE: u16 = b' ' as u16;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(super) enum Shell {
+    GitBash,
+    GitBashNoPathConv,
+    Cmd,
+    PowerShell51,
+    PowerShell7,
+}
+
+impl Shell {
+    pub(super) const ALL: [Shell; 5] = [
+        Shell::GitBash,
+        Shell::GitBashNoPathConv,
+        Shell::Cmd,
+        Shell::PowerShell51,
+        Shell::PowerShell7,
+    ];
+
+    pub(super) fn name(self) -> &'static str {
+        match self {
+            Shell::GitBash => "git-bash",
+            Shell::GitBashNoPathConv => "git-bash with MSYS_NO_PATHCONV=1",
+            Shell::Cmd => "cmd.exe",
+            Shell::PowerShell51 => "Windows PowerShell 5.1",
+            Shell::PowerShell7 => "PowerShell 7",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(super) enum Outcome {
+    /// The shell passes exactly the command line to CreateProcessW.
+    Exact,
+    /// The command line differs, but it is split into the same arguments.
+    SameArgs,
+    /// The shell cannot produce this command line.
+    Impossible,
+}
+
+impl Outcome {
+    pub(super) fn as_str(self) -> &'static str {
+        match self {
+            Outcome::Exact => "exact",
+            Outcome::SameArgs => "same arguments",
+            Outcome::Impossible => "not possible",
+        }
+    }
+}
+
+pub(super) struct Invocation {
+    pub(super) shell: Shell,
+    /// What to type into the shell to start the program directly.
+    pub(super) typed: Option<String>,
+    /// The command line, that the shell passes to CreateProcessW, if `typed` is entered.
+    pub(super) produced: Option<Vec<u16>>,
+    pub(super) outcome: Outcome,
+    /// What to type into the shell to reproduce the launch with create-process-rust.
+    pub(super) create_process_rust: String,
+    pub(super) notes: Vec<String>,
+}
+
+fn is_blank(w: u16) -> bool {
+    w == SPACE || w == TAB
+}
+
+fn is_whitespace(w: u16) -> bool {
+    char::from_u32(u32::from(w)).is_some_and(char::is_whitespace)
+}
+
+fn split_args(cmdline: &[u16], handle_first_special: bool) -> Vec<Vec<u16>> {
+    parse_lp_cmd_line(cmdline, handle_first_special).iter().map(|arg| arg.to_utf16()).collect()
+}
+
+fn outcome(produced: &[u16], target: &[u16]) -> Outcome {
+    if produced == target {
+        Outcome::Exact
+    } else if split_args(produced, true) == split_args(target, true) {
+        Outcome::SameArgs
+    } else {
+        Outcome::Impossible
+    }
+}
+
+pub(super) fn encode_utf16le_base64(units: &[u16]) -> String {
+    use base64::{engine::general_purpose::STANDARD as base64_STANDARD, Engine as _};
+    let bytes : Vec<u8> = units.iter().flat_map(|u| u.to_le_bytes()).collect();
+    base64_STANDARD.encode(bytes)
+}
+
+fn to_string(units: &[u16]) -> Option<String> {
+    String::from_utf16(units).ok()
+}
+
+/// Quotes an argument like `append_arg`.
+fn crt_quote(arg: &[u16]) -> Vec<u16> {
+    let mut quoted : Vec<u16> = vec![];
+    append_arg(&mut quoted, OsString::from_wide(arg), false, false).ok();
+    quoted
+}
+
+
+// ---------------------------------------------------------------------------
+// git-bash / MSYS2
+
+/// How the MSYS2 runtime quotes an argument, when it creates the command line from argv.
+fn msys_quote(arg: &[u16], cmdline: &mut Vec<u16>) {
+    let needs_quotes = arg.is_empty()
+        || arg.iter().any(|&w| is_blank(w) || w == QUOTE || w == u16::from(b'\n') || w == u16::from(b'\r'));
+    if !needs_quotes {
+        cmdline.extend_from_slice(arg);
+        return;
+    }
+    cmdline.push(QUOTE);
+    let mut backslashes : usize = 0;
+    for &w in arg {
+        match w {
+            BACKSLASH => backslashes += 1,
+            QUOTE => {
+                cmdline.extend(std::iter::repeat_n(BACKSLASH, backslashes * 2 + 1));
+                cmdline.push(QUOTE);
+                backslashes = 0;
+            },
+            _ => {
+                cmdline.extend(std::iter::repeat_n(BACKSLASH, backslashes));
+                cmdline.push(w);
+                backslashes = 0;
+            },
+        }
+    }
+    cmdline.extend(std::iter::repeat_n(BACKSLASH, backslashes * 2));
+    cmdline.push(QUOTE);
+}
+
+fn msys_cmdline(args: &[Vec<u16>]) -> Vec<u16> {
+    let mut cmdline : Vec<u16> = vec![];
+    for (i, arg) in args.iter().enumerate() {
+        if i != 0 {
+            cmdline.push(SPACE);
+        }
+        msys_quote(arg, &mut cmdline);
+    }
+    cmdline
+}
+
+/// How an argument must be typed without MSYS_NO_PATHCONV, so that the path conversion of MSYS2 leaves it alone.
+/// This follows the documented rules of MSYS2 roughly.
+fn avoid_path_conversion(arg: &str) -> Result<std::borrow::Cow<'_, str>, String> {
+    if arg.contains(';') {
+        // a Windows path list, that is not converted
+        return Ok(arg.into());
+    }
+    if arg.starts_with("//") {
+        return Err(format!("MSYS2 would remove a leading slash from »{}«", arg));
+    }
+    if let Some(rest) = arg.strip_prefix('/') {
+        // »//x« is an escaped Windows switch, that becomes »/x«, if there is no other slash.
+        return if rest.contains('/') || rest.contains('\\') {
+            Err(format!("MSYS2 would convert »{}« to a Windows path", arg))
+        } else {
+            Ok(format!("/{}", arg).into())
+        };
+    }
+    if let Some((_, value)) = arg.split_once('=') {
+        if value.starts_with('/') {
+            return Err(format!("MSYS2 would convert the value of »{}« to a Windows path", arg));
+        }
+    }
+    Ok(arg.into())
+}
+
+pub(super) fn bash_word(arg: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "_-.,:/@%+=".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_safe) {
+        arg.to_owned()
+    } else if arg.chars().any(|c| c.is_control()) {
+        let mut word = String::from("$'");
+        for c in arg.chars() {
+            match c {
+                '\'' => word.push_str("\\'"),
+                '\\' => word.push_str("\\\\"),
+                '\n' => word.push_str("\\n"),
+                '\r' => word.push_str("\\r"),
+                '\t' => word.push_str("\\t"),
+                c if c.is_control() => word.push_str(&format!("\\u{:04x}", u32::from(c))),
+                c => word.push(c),
+            }
+        }
+        word.push('\'');
+        word
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Types an argument for bash. Without MSYS_NO_PATHCONV the path conversion of MSYS2 must be avoided.
+fn bash_arg(arg: &[u16], path_conversion: bool) -> Result<String, String> {
+    let arg = match to_string(arg) {
+        Some(arg) if !arg.contains('\0') => arg,
+        _ => return Err("it is not valid UTF-16 and cannot be passed through bash".to_owned()),
+    };
+    if path_conversion {
+        avoid_path_conversion(&arg)
+            .map(|arg| bash_word(&arg))
+            .map_err(|msg| format!("{}, use MSYS_NO_PATHCONV=1", msg))
+    } else {
+        Ok(bash_word(&arg))
+    }
+}
+
+fn bash_invocation(
+    shell: Shell,
+    target: &[u16],
+    args: &[Vec<u16>],
+    program: Option<&[u16]>,
+    create_process_rust: &str,
+) -> Invocation {
+    let path_conversion = shell == Shell::GitBash;
+    let prefix = if path_conversion { "" } else { "MSYS_NO_PATHCONV=1 " };
+    let mut notes : Vec<String> = vec![];
+
+    // Argument zero is the program, that is not converted.
+    let words : Result<Vec<String>, String> = args.iter().enumerate()
+        .map(|(i, arg)| bash_arg(arg, path_conversion && i != 0).map_err(|msg| format!("Argument {}: {}.", i, msg)))
+        .collect();
+    let (typed, produced, outcome) = match words {
+        Ok(words) => {
+            let produced = msys_cmdline(args);
+            let outcome = outcome(&produced, target);
+            (Some(format!("{}{}", prefix, words.join(" "))), Some(produced), outcome)
+        },
+        Err(msg) => {
+            notes.push(msg);
+            (None, None, Outcome::Impossible)
+        },
+    };
+    if outcome != Outcome::Impossible {
+        notes.push("The MSYS2 runtime replaces argument zero with the full Windows path of the program.".to_owned());
+    }
+
+    // create-process-rust
+    let program_option = match program {
+        None => "--program-is-null".to_owned(),
+        Some(program) => match bash_arg(program, path_conversion) {
+            Ok(word) => format!("--program {}", word),
+            Err(_) => format!("--program-utf16le-base64 {}", encode_utf16le_base64(program)),
+        },
+    };
+    let start = format!("{}{} {}", prefix, bash_word(create_process_rust), program_option);
+    let mut candidates : Vec<String> = vec![];
+    if let Ok(word) = bash_arg(target, path_conversion) {
+        candidates.push(format!("{} --cmd-line-in-arg {}", start, word));
+    }
+    // The MSYS2 runtime quotes the rest, so it only works, if the command line is quoted like that.
+    if msys_cmdline(args) == target {
+        let words : Result<Vec<String>, String> = args.iter().map(|arg| bash_arg(arg, path_conversion)).collect();
+        if let Ok(words) = words {
+            candidates.push(format!("{} --cmd-line-is-rest {}", start, words.join(" ")));
+        }
+    }
+    let create_process_rust = shortest_or_base64(candidates, || {
+        let encoded = encode_utf16le_base64(target);
+        let encoded = if path_conversion {
+            avoid_path_conversion(&encoded).map(|e| e.into_owned()).unwrap_or(encoded)
+        } else {
+            encoded
+        };
+        format!("{} --cmd-line-utf16le-base64 {}", start, encoded)
+    });
+
+    Invocation { shell, typed, produced, outcome, create_process_rust, notes }
+}
+
+fn shortest_or_base64<F: FnOnce() -> String>(candidates: Vec<String>, base64: F) -> String {
+    candidates.into_iter()
+        .min_by_key(|c| c.chars().count())
+        .unwrap_or_else(base64)
+}
+
+
+// ---------------------------------------------------------------------------
+// cmd.exe
+
+/// Escapes text for the interactive prompt of cmd.exe.
+/// A quote toggles the quote state of cmd.exe. Outside of quotes special characters are escaped with a caret.
+/// Inside of quotes nothing can be escaped, so a percent sign there might start an environment variable.
+fn cmd_escape(text: &[u16], in_quotes: &mut bool, notes: &mut Vec<String>) -> Option<String> {
+    let text = match to_string(text) {
+        Some(text) => text,
+        None => {
+            notes.push("The command line is not valid UTF-16 and cannot be typed into cmd.exe.".to_owned());
+            return None;
+        },
+    };
+    if text.contains(['\0', '\r', '\n']) {
+        notes.push("The command line contains a line break, which cannot be typed into cmd.exe.".to_owned());
+        return None;
+    }
+    let mut escaped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                *in_quotes = !*in_quotes;
+                escaped.push(c);
+            },
+            '&' | '|' | '<' | '>' | '^' | '(' | ')' | '%' if !*in_quotes => {
+                escaped.push('^');
+                escaped.push(c);
+            },
+            '%' => {
+                let rest : String = chars.clone().collect();
+                if rest.find('%').is_some_and(|end| end > 0) {
+                    notes.push("A percent sign inside of quotes might be expanded as an environment variable by cmd.exe.".to_owned());
+                }
+                escaped.push(c);
+            },
+            c => escaped.push(c),
+        }
+    }
+    Some(escaped)
+}
+
+fn cmd_invocation(
+    target: &[u16],
+    program: Option<&[u16]>,
+    create_process_rust: &str,
+) -> Invocation {
+    let mut notes : Vec<String> = vec![];
+    let parsed = parse_lp_cmd_line(target, true);
+
+    // cmd.exe passes argument zero as typed, then a space, and then the rest of the line as typed,
+    // including the whitespace in front of it.
+    let (arg_zero, blanks, rest) : (&[u16], &[u16], &[u16]) = match parsed.first() {
+        None => (&[], &[], &[]),
+        Some(arg) => {
+            let rest_start = parsed.get(1).map_or(target.len(), |next| next.range.start);
+            let rest_start = rest_start.max(arg.range.end);
+            (&target[..arg.range.end], &target[arg.range.end..rest_start], &target[rest_start..])
+        },
+    };
+    // The program and the rest must stay separated, even if cmd.exe adds a space.
+    let blanks_to_type : &[u16] = match blanks.first() {
+        Some(&SPACE) if blanks.len() > 1 => &blanks[1..],
+        _ => blanks,
+    };
+    let mut in_quotes = false;
+    let mut to_type : Vec<u16> = arg_zero.to_vec();
+    to_type.extend_from_slice(blanks_to_type);
+    to_type.extend_from_slice(rest);
+    let typed = cmd_escape(&to_type, &mut in_quotes, &mut notes);
+    let produced = typed.as_ref().map(|_| {
+        let mut produced : Vec<u16> = arg_zero.to_vec();
+        if !blanks_to_type.is_empty() || !rest.is_empty() {
+            produced.push(SPACE);
+            produced.extend_from_slice(blanks_to_type);
+            produced.extend_from_slice(rest);
+        }
+        produced
+    });
+    let outcome = produced.as_deref().map_or(Outcome::Impossible, |p| outcome(p, target));
+    if outcome == Outcome::SameArgs {
+        notes.push("cmd.exe inserts a space after argument zero.".to_owned());
+    }
+
+    // create-process-rust
+    let mut cpr_notes : Vec<String> = vec![];
+    let mut in_quotes = false;
+    let cpr_word : Vec<u16> = escape_arg_zero(&create_process_rust.encode_utf16().collect::<Vec<u16>>(), false)
+        .map(|escaped| escaped.escaped.into_owned())
+        .unwrap_or_default();
+    let start = format!("{} {}",
+        cmd_escape(&cpr_word, &mut in_quotes, &mut cpr_notes).unwrap_or_default(),
+        match program {
+            None => "--program-is-null".to_owned(),
+            Some(program) => {
+                match cmd_escape(&crt_quote(program), &mut in_quotes, &mut cpr_notes) {
+                    Some(escaped) => format!("--program {}", escaped),
+                    None => format!("--program-utf16le-base64 {}", encode_utf16le_base64(program)),
+                }
+            },
+        });
+    let mut candidates : Vec<String> = vec![];
+    let mut rest_notes : Vec<String> = vec![];
+    let mut in_quotes_rest = in_quotes;
+    if let Some(escaped) = cmd_escape(target, &mut in_quotes_rest, &mut rest_notes) {
+        if rest_notes.is_empty() {
+            candidates.push(format!("{} --cmd-line-is-rest {}", start, escaped));
+        }
+    }
+    let create_process_rust = shortest_or_base64(candidates, || {
+        format!("{} --cmd-line-utf16le-base64 {}", start, encode_utf16le_base64(target))
+    });
+
+    Invocation { shell: Shell::Cmd, typed, produced, outcome, create_process_rust, notes }
+}
+
+
+// ---------------------------------------------------------------------------
+// PowerShell
+
+/// How .NET quotes an argument of `ProcessStartInfo.ArgumentList`. PowerShell 7.3 and later uses this.
+fn dotnet_quote(arg: &[u16], cmdline: &mut Vec<u16>) {
+    if arg.is_empty() {
+        cmdline.extend([QUOTE, QUOTE]);
+        return;
+    }
+    if !arg.iter().any(|&w| is_whitespace(w) || w == QUOTE) {
+        cmdline.extend_from_slice(arg);
+        return;
+    }
+    cmdline.push(QUOTE);
+    let mut backslashes : usize = 0;
+    for &w in arg {
+        match w {
+            BACKSLASH => backslashes += 1,
+            QUOTE => {
+                cmdline.extend(std::iter::repeat_n(BACKSLASH, backslashes * 2 + 1));
+                cmdline.push(QUOTE);
+                backslashes = 0;
+            },
+            _ => {
+                cmdline.extend(std::iter::repeat_n(BACKSLASH, backslashes));
+                cmdline.push(w);
+                backslashes = 0;
+            },
+        }
+    }
+    cmdline.extend(std::iter::repeat_n(BACKSLASH, backslashes * 2));
+    cmdline.push(QUOTE);
+}
+
+/// How Windows PowerShell 5.1 quotes an argument: it only puts quotes around it,
+/// if it contains whitespace outside of quotes. Nothing is escaped. Empty arguments are dropped.
+fn legacy_quote(arg: &[u16], cmdline: &mut Vec<u16>) {
+    let mut quote_count : usize = 0;
+    let mut needs_quotes = false;
+    for &w in arg {
+        if w == QUOTE {
+            quote_count += 1;
+        } else if is_whitespace(w) && quote_count.is_multiple_of(2) {
+            needs_quotes = true;
+        }
+    }
+    if needs_quotes {
+        cmdline.push(QUOTE);
+        cmdline.extend_from_slice(arg);
+        cmdline.push(QUOTE);
+    } else {
+        cmdline.extend_from_slice(arg);
+    }
+}
+
+/// PowerShell passes the resolved path of the program in quotes, followed by the arguments.
+fn powershell_cmdline(args: &[Vec<u16>], legacy: bool) -> Vec<u16> {
+    let mut cmdline : Vec<u16> = vec![QUOTE];
+    cmdline.extend_from_slice(args.first().map_or(&[][..], |a| &a[..]));
+    cmdline.push(QUOTE);
+    for arg in args.iter().skip(1) {
+        if legacy && arg.is_empty() {
+            continue;
+        }
+        cmdline.push(SPACE);
+        if legacy {
+            legacy_quote(arg, &mut cmdline);
+        } else {
+            dotnet_quote(arg, &mut cmdline);
+        }
+    }
+    cmdline
+}
+
+fn powershell_word(arg: &[u16]) -> String {
+    const SINGLE_QUOTES: [char; 5] = ['\'', '\u{2018}', '\u{2019}', '\u{201a}', '\u{201b}'];
+    const DOUBLE_QUOTES: [char; 4] = ['"', '\u{201c}', '\u{201d}', '\u{201e}'];
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "_.,:/\\".contains(c);
+    match to_string(arg) {
+        // »-name:value« and »--%« have a special meaning for PowerShell
+        Some(arg) if !arg.is_empty() && arg.chars().all(|c| is_safe(c) || c == '-')
+            && !(arg.starts_with('-') && arg.contains(':')) && arg != "--%" => arg,
+        Some(arg) if !arg.chars().any(|c| c.is_control()) => {
+            let mut word = String::from("'");
+            for c in arg.chars() {
+                if SINGLE_QUOTES.contains(&c) {
+                    word.push(c);
+                }
+                word.push(c);
+            }
+            word.push('\'');
+            word
+        },
+        _ => {
+            // a double quoted string, that can express control characters and lone surrogates
+            let mut word = String::from("\"");
+            for c in char::decode_utf16(arg.iter().copied()) {
+                match c {
+                    Ok(c) if c == '`' || c == '$' || DOUBLE_QUOTES.contains(&c) => {
+                        word.push('`');
+                        word.push(c);
+                    },
+                    Ok(c) if !c.is_control() => word.push(c),
+                    Ok(c) => word.push_str(&format!("$([char]0x{:04X})", u32::from(c))),
+                    Err(e) => word.push_str(&format!("$([char]0x{:04X})", e.unpaired_surrogate())),
+                }
+            }
+            word.push('"');
+            word
+        },
+    }
+}
+
+fn powershell_program(arg_zero: &[u16]) -> String {
+    let word = powershell_word(arg_zero);
+    if word.starts_with(['\'', '"']) {
+        format!("& {}", word)
+    } else {
+        word
+    }
+}
+
+/// The stop-parsing token `--%` passes the rest of the line verbatim, but it ends at a pipe or a line break,
+/// and environment variables like `%NAME%` are expanded.
+fn stop_parsing_possible(rest: &[u16]) -> bool {
+    match to_string(rest) {
+        None => false,
+        Some(rest) => {
+            let has_variable = rest.split('%').skip(1).collect::<Vec<&str>>().len() >= 2;
+            !rest.contains(['|', '\0', '\r', '\n']) && !has_variable
+        },
+    }
+}
+
+fn powershell_invocation(
+    shell: Shell,
+    target: &[u16],
+    args: &[Vec<u16>],
+    program: Option<&[u16]>,
+    create_process_rust: &str,
+) -> Invocation {
+    let legacy = shell == Shell::PowerShell51;
+    let mut notes : Vec<String> = vec![];
+    let arg_zero : &[u16] = args.first().map_or(&[][..], |a| &a[..]);
+
+    let mut typed : Option<String> = None;
+    let mut produced : Option<Vec<u16>> = None;
+    let mut best = Outcome::Impossible;
+
+    if !(legacy && args.iter().skip(1).any(|a| a.is_empty())) {
+        let words : Vec<String> = args.iter().skip(1).map(|a| powershell_word(a)).collect();
+        let cmdline = powershell_cmdline(args, legacy);
+        best = outcome(&cmdline, target);
+        typed = Some(std::iter::once(powershell_program(arg_zero)).chain(words).collect::<Vec<String>>().join(" "));
+        produced = Some(cmdline);
+    } else {
+        notes.push("Windows PowerShell 5.1 drops empty arguments.".to_owned());
+    }
+
+    if best != Outcome::Exact {
+        let parsed = parse_lp_cmd_line(target, true);
+        let rest : &[u16] = parsed.get(1).map_or(&[][..], |arg| &target[arg.range.start..]);
+        if stop_parsing_possible(rest) {
+            let mut cmdline : Vec<u16> = vec![QUOTE];
+            cmdline.extend_from_slice(arg_zero);
+            cmdline.push(QUOTE);
+            if !rest.is_empty() {
+                cmdline.push(SPACE);
+                cmdline.extend_from_slice(rest);
+            }
+            let stop_parsing_outcome = outcome(&cmdline, target);
+            if best == Outcome::Impossible || stop_parsing_outcome == Outcome::Exact {
+                best = stop_parsing_outcome;
+                typed = Some(format!("{} --% {}", powershell_program(arg_zero), String::from_utf16_lossy(rest)));
+                produced = Some(cmdline);
+            }
+        }
+    }
+    if best != Outcome::Impossible {
+        notes.push("PowerShell replaces argument zero with the full path of the program in quotes.".to_owned());
+    }
+    if !legacy {
+        let name = String::from_utf16_lossy(arg_zero).to_lowercase();
+        if [".bat", ".cmd", ".js", ".vbs", ".wsf", "cmd", "cmd.exe", "cscript.exe", "wscript.exe", "msiexec.exe"].iter().any(|s| name.ends_with(s)) {
+            notes.push("For this program PowerShell 7 uses the rules of Windows PowerShell 5.1, \
+                       unless $PSNativeCommandArgumentPassing is 'Standard'.".to_owned());
+        }
+    }
+
+    // create-process-rust
+    let cpr : Vec<u16> = create_process_rust.encode_utf16().collect();
+    let start = format!("{} {}", powershell_program(&cpr), match program {
+        None => "--program-is-null".to_owned(),
+        Some(program) => format!("--program {}", powershell_word(program)),
+    });
+    let mut candidates : Vec<String> = vec![];
+    let mut in_arg : Vec<u16> = vec![];
+    if legacy {
+        legacy_quote(target, &mut in_arg);
+    } else {
+        dotnet_quote(target, &mut in_arg);
+    }
+    if split_args(&in_arg, false) == vec![target.to_vec()] {
+        candidates.push(format!("{} --cmd-line-in-arg {}", start, powershell_word(target)));
+    }
+    if stop_parsing_possible(target) {
+        let program_option = match program {
+            None => "--program-is-null".to_owned(),
+            Some(program) => format!("--program {}", String::from_utf16_lossy(&crt_quote(program))),
+        };
+        candidates.push(format!("{} --% {} --cmd-line-is-rest {}", powershell_program(&cpr), program_option, String::from_utf16_lossy(target)));
+    }
+    let create_process_rust = shortest_or_base64(candidates, || {
+        format!("{} --cmd-line-utf16le-base64 {}", start, encode_utf16le_base64(target))
+    });
+
+    Invocation { shell, typed, produced, outcome: best, create_process_rust, notes }
+}
+
+
+/// For each shell: what to type to start a program with the command line `target`,
+/// and how to reproduce the launch with create-process-rust.
+/// If `program` is `None`, create-process-rust is invoked with `--program-is-null`.
+pub(super) fn generate_invocations(
+    target: &[u16],
+    program: Option<&[u16]>,
+    create_process_rust: &str,
+) -> Result<Vec<Invocation>, String> {
+    if target.contains(&0u16) {
+        return Err("The command line contains a NUL character".to_owned());
+    }
+    let args : Vec<Vec<u16>> = split_args(target, true);
+    if args.is_empty() {
+        return Err("The command line is empty".to_owned());
+    }
+    Ok(Shell::ALL.iter().map(|&shell| match shell {
+        Shell::GitBash | Shell::GitBashNoPathConv => bash_invocation(shell, target, &args, program, create_process_rust),
+        Shell::Cmd => cmd_invocation(target, program, create_process_rust),
+        Shell::PowerShell51 | Shell::PowerShell7 => powershell_invocation(shell, target, &args, program, create_process_rust),
+    }).collect())
+}
@@ -0,0 +1,5 @@
+//! The modules, that do not call the API of Windows. They are in a library, so that their tests can run on any platform.
+
+pub mod envblock;
+pub mod fullpath;
+pub mod pathconv;
@@ -0,0 +1,203 @@
+use std::ops::Range;
+
+use crate::commandline::{
+    escape_arg_zero,
+    parse_lp_cmd_line,
+    Arg,
+};
+
+const BACKSLASH: u16 = b'\\' as u16;
+const QUOTE: u16 = b'"' as u16;
+const TAB: u16 = b'\t' as u16;
+const SPACE: u16 = b' ' as u16;
+
+/// The maximum length of the command line including the terminating NUL character.
+const MAX_CMDLINE_LEN: usize = 32767;
+/// cmd.exe cannot handle longer command lines.
+const MAX_CMD_CMDLINE_LEN: usize = 8191;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) enum Level {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Level {
+    /// The names are the levels of SARIF.
+    pub(super) fn as_str(&self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warning => "warning",
+            Level::Note => "note",
+        }
+    }
+}
+
+pub(super) struct Rule {
+    pub(super) id: &'static str,
+    pub(super) name: &'static str,
+    pub(super) level: Level,
+    pub(super) description: &'static str,
+}
+
+pub(super) const RULES: [Rule; 9] = [
+    Rule { id: "CPR001", name: "unterminated-quote", level: Level::Warning,
+           description: "The command line ends inside of quotes, because the closing quote is missing." },
+    Rule { id: "CPR002", name: "arg-zero-trailing-backslash", level: Level::Warning,
+           description: "Argument zero is quoted and ends with a backslash, which looks like an escaped quote to other parsers." },
+    Rule { id: "CPR003", name: "cmd-crt-split-mismatch", level: Level::Warning,
+           description: "The program is cmd.exe or a batch file, and an argument is split differently by cmd.exe than by the Microsoft C-Runtime." },
+    Rule { id: "CPR004", name: "lone-surrogate", level: Level::Error,
+           description: "The command line contains a surrogate code unit, that is not part of a pair, so it is not Unicode." },
+    Rule { id: "CPR005", name: "nul-character", level: Level::Error,
+           description: "The command line contains a NUL character, which ends it early." },
+    Rule { id: "CPR006", name: "cmdline-too-long", level: Level::Error,
+           description: "The command line is longer than 32767 characters including the terminating NUL character." },
+    Rule { id: "CPR007", name: "cmdline-too-long-for-cmd", level: Level::Warning,
+           description: "The command line is longer than 8191 characters, which is too long for cmd.exe." },
+    Rule { id: "CPR008", name: "leading-whitespace", level: Level::Warning,
+           description: "The command line starts with whitespace, so argument zero is empty." },
+    Rule { id: "CPR009", name: "program-arg-zero-mismatch", level: Level::Note,
+           description: "Argument zero does not name the program, that is started." },
+];
+
+pub(super) struct Finding {
+    pub(super) rule: &'static Rule,
+    pub(super) level: Level,
+    pub(super) message: String,
+    /// The range of UTF-16 code units in the command line.
+    pub(super) range: Option<Range<usize>>,
+}
+
+fn rule(name: &str) -> &'static Rule {
+    RULES.iter().find(|rule| rule.name == name).expect("unknown lint rule")
+}
+
+fn finding(name: &str, message: String, range: Option<Range<usize>>) -> Finding {
+    let rule = rule(name);
+    Finding { rule, level: rule.level, message, range }
+}
+
+/// The file name of a path in lower case.
+fn file_name_lowercase(path: &[u16]) -> String {
+    let name = path.rsplit(|&w| w == BACKSLASH || w == b'/' as u16).next().unwrap_or(path);
+    String::from_utf16_lossy(name).to_lowercase()
+}
+
+/// True, if the program is cmd.exe or a batch file, which is run by cmd.exe.
+fn is_cmd(program: &[u16]) -> bool {
+    let name = file_name_lowercase(program);
+    name == "cmd" || name == "cmd.exe" || name.ends_with(".bat") || name.ends_with(".cmd")
+}
+
+/// Splits the command line like cmd.exe splits the parameters of a batch file:
+/// Quotes toggle quoting, backslashes are not special, and space, tab, comma, semicolon and equals sign are delimiters.
+pub(super) fn cmd_tokens(cmdline: &[u16]) -> Vec<Range<usize>> {
+    let is_delimiter = |w: u16| [SPACE, TAB, b',' as u16, b';' as u16, b'=' as u16].contains(&w);
+    let mut tokens : Vec<Range<usize>> = vec![];
+    let mut start : Option<usize> = None;
+    let mut in_quotes = false;
+    for (i, &w) in cmdline.iter().enumerate() {
+        if w == QUOTE {
+            in_quotes = !in_quotes;
+        }
+        match (start, !in_quotes && is_delimiter(w)) {
+            (None, false) => start = Some(i),
+            (Some(s), true) => {
+                tokens.push(s..i);
+                start = None;
+            },
+            _ => {},
+        }
+    }
+    if let Some(s) = start {
+        tokens.push(s..cmdline.len());
+    }
+    tokens
+}
+
+fn lint_cmdline(program: Option<&[u16]>, cmdline: &[u16], findings: &mut Vec<Finding>) {
+    let args : Vec<Arg<'_>> = parse_lp_cmd_line(cmdline, true);
+
+    if let Some(arg) = args.iter().find(|arg| arg.info.unterminated) {
+        findings.push(finding("unterminated-quote",
+                              "The closing quote of the last argument is missing.".to_owned(), Some(arg.range.clone())));
+    }
+
+    if let Some(arg_zero) = args.first() {
+        if arg_zero.raw.contains(&QUOTE) && arg_zero.raw.ends_with(&[BACKSLASH, QUOTE]) {
+            if let Ok(escaped) = escape_arg_zero(&arg_zero.to_utf16(), true) {
+                if let Some(warning) = escaped.warning {
+                    findings.push(finding("arg-zero-trailing-backslash", warning.to_owned(), Some(arg_zero.range.clone())));
+                }
+            }
+        }
+    }
+
+    let targets_cmd = program.is_some_and(is_cmd) || args.first().is_some_and(|arg| is_cmd(&arg.to_utf16()));
+    // Other programs don't care, how cmd.exe would split `--key=value`.
+    if targets_cmd {
+        let tokens = cmd_tokens(cmdline);
+        for arg in args.iter().filter(|arg| !arg.raw.is_empty() && !tokens.contains(&arg.range)) {
+            findings.push(finding("cmd-crt-split-mismatch",
+                                  format!("cmd.exe does not see argument {} »{}« as one parameter.",
+                                          arg.number, arg.arg.to_string_lossy()),
+                                  Some(arg.range.clone())));
+        }
+    }
+
+    let mut units = cmdline.iter().enumerate().peekable();
+    while let Some((i, &w)) = units.next() {
+        match w {
+            0xd800..=0xdbff if units.peek().is_some_and(|(_, &next)| (0xdc00..=0xdfff).contains(&next)) => {
+                units.next();
+            },
+            0xd800..=0xdfff => findings.push(finding("lone-surrogate",
+                                                     format!("Lone surrogate 0x{:04x}.", w), Some(i..i + 1))),
+            0 => findings.push(finding("nul-character",
+                                       "NUL character.".to_owned(), Some(i..i + 1))),
+            _ => {},
+        }
+    }
+
+    if cmdline.len() + 1 > MAX_CMDLINE_LEN {
+        findings.push(finding("cmdline-too-long",
+                              format!("The command line has {} characters.", cmdline.len()), Some(MAX_CMDLINE_LEN - 1..cmdline.len())));
+    } else if cmdline.len() + 1 > MAX_CMD_CMDLINE_LEN {
+        let mut f = finding("cmdline-too-long-for-cmd",
+                            format!("The command line has {} characters.", cmdline.len()), Some(MAX_CMD_CMDLINE_LEN - 1..cmdline.len()));
+        if !targets_cmd {
+            f.level = Level::Note;
+        }
+        findings.push(f);
+    }
+
+    if cmdline.first().is_some_and(|&w| w == SPACE || w == TAB) {
+        findings.push(finding("leading-whitespace", "Argument zero is empty.".to_owned(), Some(0..1)));
+    }
+
+    if let (Some(program), Some(arg_zero)) = (program, args.first()) {
+        let arg_zero_vec = arg_zero.to_utf16();
+        let file_name = |path: &[u16]| -> String {
+            file_name_lowercase(path).trim_end_matches(".exe").to_owned()
+        };
+        if file_name(program) != file_name(&arg_zero_vec) {
+            findings.push(finding("program-arg-zero-mismatch",
+                                  format!("Argument zero is »{}«, but the program is »{}«.",
+                                          String::from_utf16_lossy(&arg_zero_vec), String::from_utf16_lossy(program)),
+                                  Some(arg_zero.range.clone())));
+        }
+    }
+}
+
+/// Runs all rules over the program and the command line, that are passed to CreateProcessW.
+pub(super) fn lint(program: Option<&[u16]>, cmdline: Option<&[u16]>) -> Vec<Finding> {
+    let mut findings : Vec<Finding> = vec![];
+    if let Some(cmdline) = cmdline {
+        lint_cmdline(program, cmdline, &mut findings);
+    }
+    findings.sort_by(|a, b| a.level.cmp(&b.level)
+                     .then_with(|| a.range.as_ref().map(|r| r.start).cmp(&b.range.as_ref().map(|r| r.start))));
+    findings
+}
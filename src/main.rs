@@ -15,11 +15,35 @@
 //===----------------------------------------------------------------------===//
 
 
+// The program calls the API of Windows. On other platforms only the library is built, so that its tests can run there.
+#![cfg(windows)]
+
 mod input;
 mod output;
 mod commandline;
 mod process;
 mod options;
+mod variants;
+mod invocation;
+mod export;
+mod template;
+mod sentinel;
+mod edit;
+mod lint;
+mod matrix;
+mod resolve;
+mod shellcmd;
+mod shelllink;
+mod audit;
+mod forensic;
+mod batbadbut;
+mod batches;
+mod codepage;
+mod confusables;
+mod codepage_tables;
+mod diff;
+mod exesearch;
+mod worstfit;
 
 use std::{
     borrow::Cow,
@@ -36,6 +60,12 @@ use std::{
 
 use itertools::Itertools;
 
+use create_process_rust::{
+    envblock,
+    fullpath,
+    pathconv,
+};
+
 use crate::commandline::*;
 use crate::options::*;
 use crate::input::*;
@@ -54,7 +84,7 @@ fn main() -> Result<(), String>{
         Err(msg) => {
             eprintln!("{}\n",msg);
             output::print_args(cmdline, &parsed_args_list,
-                       &options::PrintOptions { json: false, silent: false, print_args: true },
+                       &options::PrintOptions { json: false, silent: false, print_args: true, confusables: false },
                        "", true, &mut std::io::stderr())
                 .map_err(|error| error.to_string())?;
 
@@ -72,6 +102,366 @@ fn main() -> Result<(), String>{
         options::MainChoice::ExecOpts(opts) => {
             exec(opts, options.print_opts, cmdline, parsed_args_list)
         },
+        options::MainChoice::GenerateVariants(opts) => {
+            generate_variants(opts, &options.print_opts)
+        },
+        options::MainChoice::GenerateInvocations(opts) => {
+            generate_invocations(opts, &options.print_opts, &arg0_or_default)
+        },
+        options::MainChoice::BatBadBut(opts) => {
+            analyze_batch_file_call(opts, &options.print_opts)
+        },
+        options::MainChoice::NarrowView(opts) => {
+            narrow_view(opts, &options.print_opts)
+        },
+        options::MainChoice::WorstFit(opts) => {
+            check_best_fit(opts, &options.print_opts)
+        },
+        options::MainChoice::SplitArgs(opts) => {
+            split_args(opts, &options.print_opts)
+        },
+        options::MainChoice::Explain(opts) => {
+            explain(opts, &options.print_opts)
+        },
+        options::MainChoice::Diff(opts) => {
+            diff_cmdlines(opts, &options.print_opts)
+        },
+        options::MainChoice::CompareParsers(opts) => {
+            compare_parsers(opts, &options.print_opts)
+        },
+        options::MainChoice::ResolveCommand(opts) => {
+            resolve_command(opts, &options.print_opts)
+        },
+        options::MainChoice::FullPathName(opts) => {
+            full_path_name(opts, &options.print_opts)
+        },
+        options::MainChoice::ConvertPath(opts) => {
+            convert_path(opts, &options.print_opts)
+        },
+        options::MainChoice::ExpandShellCommand(opts) => {
+            expand_shell_command(opts, &options.print_opts)
+        },
+        options::MainChoice::AuditPaths(opts) => {
+            audit_paths(opts, &options.print_opts)
+        },
+        options::MainChoice::ReadShortcut(opts) => {
+            read_shortcut(opts, &options.print_opts)
+        },
+        options::MainChoice::SplitEvents(opts) => {
+            split_events(opts)
+        },
+        options::MainChoice::FillTemplate(opts) => {
+            fill_template(opts, &options.print_opts)
+        },
+        options::MainChoice::EditCmdline(opts) => {
+            edit_cmdline(opts, &options.print_opts)
+        },
+    }
+}
+
+
+fn generate_variants(opts: options::VariantsOptions, print_opts: &options::PrintOptions) -> Result<(), String> {
+    let args : Vec<Vec<u16>> = get_args(opts.args)?
+        .iter()
+        .map(|arg| arg.encode_wide().collect())
+        .collect();
+    let variants : Vec<Vec<u16>> = match opts.seed {
+        None => variants::enumerate_variants(&args, opts.count)?,
+        Some(seed) => variants::sample_variants(&args, opts.count, seed)?,
+    };
+    if variants.len() < opts.count {
+        eprintln!("Note: only {} of {} variants were found.", variants.len(), opts.count);
+    }
+    output::print_variants(&args, &variants, print_opts, &mut std::io::stdout())
+        .map_err(|error| error.to_string())
+}
+
+
+fn generate_invocations(
+    opts: options::InvocationsOptions,
+    print_opts: &options::PrintOptions,
+    arg0: &str,
+) -> Result<(), String> {
+    let target : Vec<u16> = get_cmdline_or_args(opts.target)?;
+    let program : Option<Vec<u16>> = opts.program.map(|p| p.encode_wide().collect());
+    let invocations = invocation::generate_invocations(&target, program.as_deref(), arg0)?;
+    output::print_invocations(&target, &invocations, print_opts, &mut std::io::stdout())
+        .map_err(|error| error.to_string())
+}
+
+
+/// The value of `%ComSpec%`, that `CreateProcessW` uses to run batch files.
+fn comspec() -> Vec<u16> {
+    match std::env::var_os("ComSpec") {
+        Some(comspec) => comspec.encode_wide().collect(),
+        None => batbadbut::DEFAULT_COMSPEC.encode_utf16().collect(),
+    }
+}
+
+
+fn analyze_batch_file_call(opts: options::BatchOptions, print_opts: &options::PrintOptions) -> Result<(), String> {
+    let cmdline : Vec<u16> = get_cmdline_or_args(opts.target)?;
+    let program : Option<Vec<u16>> = opts.program.map(|p| p.encode_wide().collect());
+    let report = batbadbut::analyze_cmdline(program.as_deref(), &cmdline, &comspec())?;
+    output::print_batch_report(&report, print_opts, &mut std::io::stdout())
+        .map_err(|error| error.to_string())?;
+    if !report.safe {
+        return Err("The batch file does not get the intended arguments.".to_owned());
+    }
+    Ok(())
+}
+
+
+fn narrow_view(opts: options::NarrowViewOptions, print_opts: &options::PrintOptions) -> Result<(), String> {
+    let options::NarrowViewOptions{ cmdline, code_page, best_fit, default_char } = opts;
+    let cmdline : Vec<u16> = match get_cmdline(cmdline)? {
+        Some(cmdline) => cmdline.encode_wide().collect(),
+        None => return Err("cmd line is NULL".to_owned()),
+    };
+    let narrow = codepage::wide_to_multibyte(code_page, &cmdline, best_fit, default_char)?;
+    output::print_narrow_view(&cmdline, &narrow, code_page, best_fit, default_char, print_opts, &mut std::io::stdout())
+}
+
+
+fn check_best_fit(opts: options::WorstFitOptions, print_opts: &options::PrintOptions) -> Result<(), String> {
+    let cmdline : Vec<u16> = match get_cmdline(opts.cmdline)? {
+        Some(cmdline) => cmdline.encode_wide().collect(),
+        None => return Err("cmd line is NULL".to_owned()),
+    };
+    let report = worstfit::analyze_cmdline(opts.code_page, &cmdline)?;
+    output::print_worstfit_report(&report, print_opts, &mut std::io::stdout())
+        .map_err(|error| error.to_string())?;
+    if !report.differences.is_empty() {
+        return Err(format!("The arguments change, if the command line is converted to code page {}.", report.code_page));
+    }
+    Ok(())
+}
+
+
+fn split_args(opts: options::SplitArgsOptions, print_opts: &options::PrintOptions) -> Result<(), String> {
+    let prefix : Vec<u16> = match get_cmdline(opts.prefix)? {
+        Some(prefix) => prefix.encode_wide().collect(),
+        None => return Err("prefix cmd line is NULL".to_owned()),
+    };
+    let args = get_args(opts.args)?;
+    let batches = batches::pack_args(&prefix, &args, opts.limit)?;
+    output::print_batches(&prefix, &batches, opts.limit, print_opts, &mut std::io::stdout())
+        .map_err(|error| error.to_string())?;
+    if opts.run {
+        for (n, batch) in batches.iter().enumerate() {
+            let exit_code = crate::process::create_process(opts.program.as_deref(), Some(OsString::from_wide(&batch.cmdline)), None)?;
+            if !print_opts.json {
+                eprintln!("Command line {} of {}: the exit code is {}", n + 1, batches.len(), exit_code);
+            }
+            if exit_code != 0 {
+                std::process::exit(exit_code as i32);
+            }
+        }
+    }
+    Ok(())
+}
+
+
+fn explain(opts: options::ExplainOptions, print_opts: &options::PrintOptions) -> Result<(), String> {
+    let cmdline : Vec<u16> = match get_cmdline(opts.cmdline)? {
+        Some(cmdline) => cmdline.encode_wide().collect(),
+        None => return Err("cmd line is NULL".to_owned()),
+    };
+    let (args, steps) = trace_lp_cmd_line(&cmdline, true);
+    output::print_trace(&cmdline, &args, &steps, print_opts, &mut std::io::stdout())
+        .map_err(|error| error.to_string())
+}
+
+
+fn diff_cmdlines(opts: options::DiffOptions, print_opts: &options::PrintOptions) -> Result<(), String> {
+    let get = |cmdline_opt: CmdlineOpt| -> Result<Vec<u16>, String> {
+        match get_cmdline(cmdline_opt)? {
+            Some(cmdline) => Ok(cmdline.encode_wide().collect()),
+            None => Err("cmd line is NULL".to_owned()),
+        }
+    };
+    let first = get(opts.first)?;
+    let second = get(opts.second)?;
+    let cmdline_diff = diff::diff_cmdlines(&first, &second);
+    output::print_diff(&first, &second, &cmdline_diff, print_opts, &mut std::io::stdout())
+        .map_err(|error| error.to_string())?;
+    if !cmdline_diff.is_equal() {
+        return Err("The command lines have different arguments.".to_owned());
+    }
+    Ok(())
+}
+
+
+fn compare_parsers(opts: options::CompareParsersOptions, print_opts: &options::PrintOptions) -> Result<(), String> {
+    let cmdline : Vec<u16> = match get_cmdline(opts.cmdline)? {
+        Some(cmdline) => cmdline.encode_wide().collect(),
+        None => return Err("cmd line is NULL".to_owned()),
+    };
+    let matrix = matrix::compare_parsers(&cmdline, &opts.rest_after, opts.code_page)?;
+    output::print_matrix(&cmdline, &matrix, print_opts, &mut std::io::stdout())
+        .map_err(|error| error.to_string())
+}
+
+
+fn resolve_command(opts: options::ResolveCommandOptions, print_opts: &options::PrintOptions) -> Result<(), String> {
+    let virtual_fs = read_virtual_fs_from_file(&opts.virtual_fs)?;
+    let command : Vec<u16> = opts.command.encode_wide().collect();
+    let resolution = resolve::resolve_command(&command, &virtual_fs);
+    output::print_command_resolution(&resolution, print_opts, &mut std::io::stdout())
+        .map_err(|error| error.to_string())?;
+    if !resolution.agree {
+        return Err("The resolvers disagree.".to_owned());
+    }
+    Ok(())
+}
+
+
+/// The current directories of this program, unless they are given.
+fn current_dirs(cwd: Option<OsString>, drive_cwds: Vec<OsString>) -> Result<fullpath::CurrentDirs, String> {
+    let cwd : OsString = match cwd {
+        Some(cwd) => cwd,
+        None => std::env::current_dir().map_err(|error| error.to_string())?.into_os_string(),
+    };
+    let mut drives : Vec<Vec<u16>> = drive_cwds.iter().map(|dir| dir.encode_wide().collect()).collect();
+    for letter in 'A'..='Z' {
+        if let Some(dir) = std::env::var_os(format!("={}:", letter)) {
+            drives.push(dir.encode_wide().collect());
+        }
+    }
+    Ok(fullpath::CurrentDirs { cwd: cwd.encode_wide().collect(), drives })
+}
+
+
+fn full_path_name(opts: options::FullPathOptions, print_opts: &options::PrintOptions) -> Result<(), String> {
+    let dirs = current_dirs(opts.cwd, opts.drive_cwds)?;
+    let path : Vec<u16> = opts.path.encode_wide().collect();
+    let full_path = fullpath::get_full_path_name(&path, &dirs);
+    output::print_full_path(&full_path, print_opts, &mut std::io::stdout())
+        .map_err(|error| error.to_string())
+}
+
+
+fn mount_table(mounts: &[(OsString, OsString)]) -> Vec<pathconv::Mount> {
+    mounts.iter()
+        .map(|(posix, windows)| pathconv::Mount { posix: posix.encode_wide().collect(), windows: windows.encode_wide().collect() })
+        .collect()
+}
+
+
+fn convert_path(opts: options::ConvertPathOptions, print_opts: &options::PrintOptions) -> Result<(), String> {
+    let path : Vec<u16> = opts.path.encode_wide().collect();
+    let conversion = pathconv::convert_path(&path, opts.to, &mount_table(&opts.mounts))?;
+    output::print_path_conversion(&conversion, print_opts, &mut std::io::stdout())
+        .map_err(|error| error.to_string())
+}
+
+
+/// Splits `NAME=VALUE` options into variables.
+fn env_vars(assignments: &[OsString]) -> Result<envblock::EnvVars, String> {
+    assignments.iter()
+        .map(|assignment| envblock::split_assignment(&assignment.encode_wide().collect::<Vec<u16>>()))
+        .collect()
+}
+
+
+fn expand_shell_command(opts: options::ShellCommandOptions, print_opts: &options::PrintOptions) -> Result<(), String> {
+    let mut env = env_vars(&opts.env)?;
+    if opts.inherit_env {
+        env.extend(std::env::vars_os().map(|(name, value)| (name.encode_wide().collect(), value.encode_wide().collect())));
+    }
+    let template : Vec<u16> = opts.template.encode_wide().collect();
+    let file : Vec<u16> = opts.file.encode_wide().collect();
+    let args : Vec<Vec<u16>> = opts.args.iter().map(|arg| arg.encode_wide().collect()).collect();
+    let shell_command = shellcmd::expand_shell_command(&template, &file, &args, &env)?;
+    output::print_shell_command(&shell_command, print_opts, &mut std::io::stdout())
+        .map_err(|error| error.to_string())
+}
+
+
+fn audit_paths(opts: options::AuditOptions, print_opts: &options::PrintOptions) -> Result<(), String> {
+    let env = env_vars(&opts.env)?;
+    let mut entries : Vec<audit::AuditEntry> = vec![];
+    for file in &opts.files {
+        let bytes = read_bytes_from_file(file)?;
+        entries.extend(audit::audit_file(&bytes, &file.to_string_lossy(), &env)?);
+    }
+    let vulnerable = entries.iter().filter(|entry| entry.vulnerable()).count();
+    if !opts.all {
+        entries.retain(|entry| entry.vulnerable());
+    }
+    output::print_audit(&entries, print_opts, &mut std::io::stdout())
+        .map_err(|error| error.to_string())?;
+    if vulnerable != 0 {
+        return Err(format!("{} of the command lines have an unquoted program path with spaces.", vulnerable));
+    }
+    Ok(())
+}
+
+
+fn read_shortcut(opts: options::ShortcutOptions, print_opts: &options::PrintOptions) -> Result<(), String> {
+    let env = env_vars(&opts.env)?;
+    let bytes = read_bytes_from_file(&opts.file)?;
+    let shell_link = shelllink::read_shell_link(&bytes, opts.code_page, &env)?;
+    if let Some(warning) = shell_link.warning {
+        eprintln!("Warning: {}", warning);
+    }
+    output::print_shell_link(&shell_link, print_opts, &mut std::io::stdout())
+        .map_err(|error| error.to_string())?;
+    // The JSON object already contains the arguments.
+    if print_opts.json {
+        return Ok(());
+    }
+    let parsed_args_list = parse_lp_cmd_line(&shell_link.cmdline, true);
+    output::print_args(&shell_link.cmdline, &parsed_args_list, print_opts, "", true, &mut std::io::stdout())
+        .map_err(|error| error.to_string())
+}
+
+
+fn split_events(opts: options::EventsOptions) -> Result<(), String> {
+    let mut stdout = std::io::stdout();
+    for file in &opts.files {
+        let bytes = read_bytes_from_file(file)?;
+        let events = forensic::read_process_events(&bytes)
+            .map_err(|error| format!("{}: {}", file.to_string_lossy(), error))?;
+        for event in events.iter().filter(|event| !opts.only_anomalies || !event.anomalies.is_empty()) {
+            output::print_process_event(event, &file.to_string_lossy(), &mut stdout)
+                .map_err(|error| error.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+
+fn fill_template(opts: options::TemplateOptions, print_opts: &options::PrintOptions) -> Result<(), String> {
+    let (template_text, values) = get_template(opts.template, opts.values)?;
+    let template = template::parse_template(&template_text)?;
+    let filled = template::fill_template(&template, &values, opts.quoting)?;
+    output::print_filled_template(&filled, print_opts, &mut std::io::stdout())
+        .map_err(|error| error.to_string())?;
+    if !filled.verified {
+        return Err("The filled template is not split into the intended arguments.".to_owned());
+    }
+    Ok(())
+}
+
+
+fn edit_cmdline(opts: options::EditOptions, print_opts: &options::PrintOptions) -> Result<(), String> {
+    let cmdline : Vec<u16> = match get_cmdline(opts.cmdline)? {
+        Some(cmdline) => cmdline.encode_wide().collect(),
+        None => return Err("cmd line is NULL".to_owned()),
+    };
+    let mut warnings : Vec<String> = vec![];
+    let new_cmdline = edit::apply_edits(&cmdline, &opts.edits, &mut warnings)?;
+    for warning in &warnings {
+        eprintln!("Warning: {}", warning);
+    }
+    if print_opts.silent && !print_opts.json {
+        writeln!(std::io::stdout(), "{}", OsString::from_wide(&new_cmdline).to_string_lossy())
+            .map_err(|error| error.to_string())
+    } else {
+        let new_parsed_args_list = commandline::parse_lp_cmd_line(&new_cmdline, true);
+        output::print_args(&new_cmdline, &new_parsed_args_list, print_opts, "", true, &mut std::io::stdout())
+            .map_err(|error| error.to_string())
     }
 }
 
@@ -120,6 +510,27 @@ fn exec(
                    \"--program-from-cmd-line\" or \"--program-is-null\".".to_owned());
     }
 
+    if exec_options.virtual_fs.is_some()
+        && !(exec_options.dry_run && matches!(exec_options.program, ProgramOpt::Null)) {
+        return Err("Error: \"--virtual-fs\" can only be specified with \"--program-is-null\" and \"--dry-run\".".to_owned());
+    }
+
+    if exec_options.convert_program && matches!(exec_options.program, ProgramOpt::Null) {
+        return Err("Error: \"--convert-program\" can not be combined with \"--program-is-null\".".to_owned());
+    }
+
+    if !exec_options.mounts.is_empty() && !exec_options.convert_program {
+        return Err("Error: \"--mount\" can only be specified with \"--convert-program\".".to_owned());
+    }
+
+    if exec_options.export.is_some() && exec_options.lint.is_some() {
+        return Err("Error: \"--export\" can not be combined with \"--lint\".".to_owned());
+    }
+
+    if !exec_options.env.is_empty() && (exec_options.export.is_some() || exec_options.lint.is_some()) {
+        return Err("Error: the \"--env*\" options can not be combined with \"--export\" or \"--lint\".".to_owned());
+    }
+
     let cmdline_comes_from_stdin =
         if let CmdlineOpt::FromJSONFile(file_name) = &exec_options.cmdline{
             is_filename_stdin(file_name)
@@ -188,6 +599,15 @@ fn exec(
         },
     };
 
+    let program : Option<Cow<'_,OsStr>> = match program {
+        Some(cow_prog) if exec_options.convert_program => {
+            let prog_vec : Vec<u16> = cow_prog.encode_wide().collect();
+            let conversion = pathconv::convert_path(&prog_vec, pathconv::PathStyle::Windows, &mount_table(&exec_options.mounts))?;
+            Some(Cow::from(OsString::from_wide(&conversion.converted)))
+        },
+        other => other,
+    };
+
     match &program {
         Some(cow_prog) => 
             if exec_options.prepend_program {
@@ -210,6 +630,27 @@ fn exec(
     };
 
 
+    if let Some(format) = exec_options.lint {
+        let program_vec : Option<Vec<u16>> = program.as_ref().map(|p| p.encode_wide().collect());
+        let cmdline_vec : Option<Vec<u16>> = new_cmdline.as_ref().map(|c| c.encode_wide().collect());
+        let findings = lint::lint(program_vec.as_deref(), cmdline_vec.as_deref());
+        output::print_lint_findings(cmdline_vec.as_deref(), &findings, format, &mut std::io::stdout())
+            .map_err(|error| error.to_string())?;
+        if findings.iter().any(|f| f.level == lint::Level::Error) {
+            return Err("The lint found errors.".to_owned());
+        }
+        return Ok(());
+    }
+
+    if let Some(language) = exec_options.export {
+        let program_vec : Option<Vec<u16>> = program.as_ref().map(|p| p.encode_wide().collect());
+        let cmdline_vec : Option<Vec<u16>> = new_cmdline.as_ref().map(|c| c.encode_wide().collect());
+        let launch = export::Launch { program: program_vec.as_deref(), cmdline: cmdline_vec.as_deref() };
+        let (snippet, notes) = export::export_snippet(language, &launch);
+        return output::print_snippet(&snippet, &notes, &print_opts, &mut std::io::stdout())
+            .map_err(|error| error.to_string());
+    }
+
     let mut writer_wrapper: StdOutOrStdErr =
         if print_opts.print_args || exec_options.split_and_print_inner_cmdline { StdOutOrStdErr::StdErr(io::stderr())}
         else { StdOutOrStdErr::StdOut(io::stdout()) };
@@ -220,12 +661,70 @@ fn exec(
     // `T` in this case is `OsString` and `T::Target` should be `OsStr` or `&OsStr`.
     writeln!(&mut writer_wrapper.into_writer(), "The program      (1st argument to CreateProcessW) is:   {}", quote_or_null((&program).as_deref()))
         .map_err(|x| format!("Write failed with {}", x.to_string()))?;
+    if let (true, Some(cow_prog)) = (exec_options.dry_run, &program) {
+        let program_vec : Vec<u16> = cow_prog.encode_wide().collect();
+        let full_path = fullpath::get_full_path_name(&program_vec, &current_dirs(None, vec![])?);
+        writeln!(&mut writer_wrapper.into_writer(), "The full path of the program (GetFullPathNameW) is:     »{}«",
+                 String::from_utf16_lossy(&full_path.full_path))
+            .map_err(|x| format!("Write failed with {}", x))?;
+        for note in full_path.notes.iter().filter(|note| note.is_problem()) {
+            writeln!(&mut writer_wrapper.into_writer(), "Warning: {}", note.describe())
+                .map_err(|x| format!("Write failed with {}", x))?;
+        }
+    }
     writeln!(
              &mut writer_wrapper.into_writer(),
              "The command line (2nd argument to CreateProcessW) is:   {}",
              quote_or_null(new_cmdline.as_deref())
         ).map_err(|x| format!("Write failed with {}", x.to_string()))?;
 
+    let search : Option<exesearch::ExeSearch> = match &exec_options.virtual_fs {
+        Some(file_name) => {
+            let virtual_fs = read_virtual_fs_from_file(file_name)?;
+            let cmdline_vec : Vec<u16> = match &new_cmdline {
+                None => return Err("Error: cannot search for the program, because cmd line is NULL.".to_owned()),
+                Some(cmdline) => cmdline.encode_wide().collect(),
+            };
+            let search = exesearch::search_program(&cmdline_vec, &virtual_fs);
+            output::print_exe_search(&cmdline_vec, &search, &print_opts, writer_wrapper.into_writer())
+                .map_err(|x| format!("Write failed with {}", x))?;
+            Some(search)
+        },
+        None => None,
+    };
+
+    if exec_options.dry_run {
+        // If the cmd line is NULL, CreateProcessW uses the program as cmd line.
+        let program_vec : Option<Vec<u16>> = program.as_ref().map(|p| p.encode_wide().collect());
+        let image : Option<Vec<u16>> = match (&program_vec, &search) {
+            (Some(program_vec), _) => Some(program_vec.clone()),
+            (None, Some(search)) => search.found.map(|found| search.candidates[found].path.clone()),
+            (None, None) => None,
+        };
+        let cmdline_vec : Option<Vec<u16>> = new_cmdline.as_ref().map(|c| c.encode_wide().collect()).or(program_vec);
+        if let Some(cmdline_vec) = cmdline_vec {
+            if let Some(wrapping) = batbadbut::predict_cmd_wrapping(image.as_deref(), &cmdline_vec, &comspec()) {
+                output::print_cmd_wrapping(&wrapping, &print_opts, writer_wrapper.into_writer())
+                    .map_err(|x| format!("Write failed with {}", x))?;
+            }
+        }
+    }
+
+    // Without `--env*` options, NULL is passed and the new process inherits the environment.
+    let env_edits = get_env_edits(exec_options.env)?;
+    let env_block : Option<envblock::EnvBlock> =
+        if env_edits.is_empty() { None }
+        else {
+            let initial : envblock::EnvVars = std::env::vars_os()
+                .map(|(name, value)| (name.encode_wide().collect(), value.encode_wide().collect()))
+                .collect();
+            Some(envblock::build_env_block(initial, &env_edits)?)
+        };
+    if let (true, Some(env_block)) = (exec_options.dry_run, &env_block) {
+        output::print_env_block(env_block, &print_opts, writer_wrapper.into_writer())
+            .map_err(|x| format!("Write failed with {}", x))?;
+    }
+
     if exec_options.split_and_print_inner_cmdline {
         print_inner_cmdline(&new_cmdline,&print_opts)?;
     }
@@ -235,7 +734,7 @@ fn exec(
 
     let exit_code : u32 =
         if ! exec_options.dry_run {
-            crate::process::create_process(program.as_deref(), new_cmdline.as_deref())?
+            crate::process::create_process(program.as_deref(), new_cmdline.as_deref(), env_block.as_ref().map(|env| env.block.as_slice()))?
         } else {
             writeln!(&mut writer_wrapper.into_writer(), "\nSkipping execution because of --dry-run.")
                 .map_err(|x| format!("Write failed with {}", x.to_string()))?;
@@ -0,0 +1,71 @@
+use crate::codepage::{
+    multibyte_to_wide,
+    parse_narrow_cmd_line,
+    wide_to_multibyte,
+};
+use crate::commandline::{
+    get_rest,
+    parse_lp_cmd_line,
+};
+use crate::lint::cmd_tokens;
+
+/// One way to split the command line.
+pub(super) struct Interpretation {
+    pub(super) name: String,
+    pub(super) args: Vec<Vec<u16>>,
+}
+
+pub(super) struct Matrix {
+    pub(super) interpretations: Vec<Interpretation>,
+    /// For each argument index, whether all interpretations have the same argument there.
+    pub(super) agree: Vec<bool>,
+}
+
+impl Matrix {
+    pub(super) fn cell(&self, row: usize, column: usize) -> Option<&Vec<u16>> {
+        self.interpretations[column].args.get(row)
+    }
+}
+
+/// Splits the command line with every parser of this tool:
+/// - the Microsoft C-Runtime with and without the special rules for argument zero,
+/// - the C-Runtime up to argument N, and the rest of the command line as it is, like `cmd.exe /c` takes it,
+/// - the C-Runtime of a program, that gets the command line in the ANSI code page with best fit,
+/// - the parameters of a batch file, like cmd.exe splits them.
+pub(super) fn compare_parsers(cmdline: &[u16], rest_after: &[usize], code_page: u32) -> Result<Matrix, String> {
+    let to_vecs = |args: &[crate::commandline::Arg<'_>]| -> Vec<Vec<u16>> { args.iter().map(|a| a.to_utf16()).collect() };
+    let crt = parse_lp_cmd_line(cmdline, true);
+
+    let mut interpretations = vec![
+        Interpretation { name: "CRT".to_owned(), args: to_vecs(&crt) },
+        Interpretation { name: "CRT, arg 0 not special".to_owned(), args: to_vecs(&parse_lp_cmd_line(cmdline, false)) },
+    ];
+    for &n in rest_after {
+        let mut args = to_vecs(&crt[..crt.len().min(n + 1)]);
+        if let Some(arg) = crt.get(n) {
+            let rest = get_rest(cmdline, arg);
+            if !rest.is_empty() {
+                args.push(rest.to_vec());
+            }
+        }
+        interpretations.push(Interpretation { name: format!("rest after arg {}", n), args });
+    }
+    let narrow = wide_to_multibyte(code_page, cmdline, true, b'?')?;
+    interpretations.push(Interpretation {
+        name: format!("CRT, code page {}", code_page),
+        args: parse_narrow_cmd_line(code_page, &narrow.bytes).iter()
+            .map(|arg| multibyte_to_wide(code_page, arg))
+            .collect::<Result<Vec<Vec<u16>>, String>>()?,
+    });
+    interpretations.push(Interpretation {
+        name: "batch file parameters".to_owned(),
+        args: cmd_tokens(cmdline).into_iter().map(|range| cmdline[range].to_vec()).collect(),
+    });
+
+    let rows = interpretations.iter().map(|i| i.args.len()).max().unwrap_or(0);
+    let agree = (0..rows).map(|row| {
+        let first = interpretations[0].args.get(row);
+        interpretations.iter().all(|i| i.args.get(row) == first)
+    }).collect();
+    Ok(Matrix { interpretations, agree })
+}
@@ -8,6 +8,7 @@ use std::{
 use base64::{engine::general_purpose::STANDARD as base64_STANDARD, Engine as _};
 
 use crate::commandline;
+use crate::pathconv::PathStyle;
 
 #[derive(Debug)]
 pub(super) enum ProgramOpt{
@@ -24,6 +25,64 @@ pub(super) enum CmdlineOpt{
     FromJSONFile(OsString), // filename
 }
 
+#[derive(Debug)]
+pub(super) enum ArgsOpt{
+    Rest(Vec<OsString>),
+    FromJSONFile(OsString), // filename
+    FromFile(OsString), // filename, one argument per line
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(super) enum ExportLanguage{
+    Rust,
+    C,
+    Python,
+    PowerShell,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(super) enum LintFormat{
+    Text,
+    Json,
+    Sarif,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum QuotingStyle{
+    Crt,
+    CrtAlways,
+    Raw,
+}
+
+#[derive(Debug)]
+pub(super) enum TemplateOpt{
+    Str(OsString),
+    FromJSONFile(OsString), // filename
+}
+
+#[derive(Debug)]
+pub(super) enum CmdlineEdit{
+    Insert(usize, OsString),
+    Delete(usize),
+    Replace(usize, OsString),
+    InsertBefore(OsString, OsString), // matching argument, new argument
+    InsertAfter(OsString, OsString), // matching argument, new argument
+}
+
+#[derive(Debug)]
+pub(super) enum EnvOpt{
+    Set(OsString), // NAME=VALUE
+    Unset(OsString),
+    Clear,
+    FromJSONFile(OsString), // filename
+}
+
+#[derive(Debug)]
+pub(super) enum CmdlineOrArgsOpt{
+    Cmdline(CmdlineOpt),
+    Args(ArgsOpt),
+}
+
 #[derive(Debug)]
 pub(super) struct ExecOptions{
     pub(super) program : ProgramOpt,
@@ -32,12 +91,142 @@ pub(super) struct ExecOptions{
     pub(super) strip_program : bool,
     pub(super) dry_run : bool,
     pub(super) split_and_print_inner_cmdline: bool,
+    pub(super) export : Option<ExportLanguage>,
+    pub(super) lint : Option<LintFormat>,
+    pub(super) virtual_fs : Option<OsString>,
+    pub(super) convert_program : bool,
+    pub(super) mounts : Vec<(OsString, OsString)>,
+    pub(super) env : Vec<EnvOpt>,
+}
+
+#[derive(Debug)]
+pub(super) struct VariantsOptions{
+    pub(super) args : ArgsOpt,
+    pub(super) count : usize,
+    pub(super) seed : Option<u64>,
+}
+
+#[derive(Debug)]
+pub(super) struct InvocationsOptions{
+    pub(super) target : CmdlineOrArgsOpt,
+    pub(super) program : Option<OsString>,
+}
+
+#[derive(Debug)]
+pub(super) struct BatchOptions{
+    pub(super) target : CmdlineOrArgsOpt,
+    pub(super) program : Option<OsString>,
+}
+
+#[derive(Debug)]
+pub(super) struct NarrowViewOptions{
+    pub(super) cmdline : CmdlineOpt,
+    pub(super) code_page : u32,
+    pub(super) best_fit : bool,
+    pub(super) default_char : u8,
+}
+
+#[derive(Debug)]
+pub(super) struct WorstFitOptions{
+    pub(super) cmdline : CmdlineOpt,
+    pub(super) code_page : u32,
+}
+
+#[derive(Debug)]
+pub(super) struct SplitArgsOptions{
+    pub(super) prefix : CmdlineOpt,
+    pub(super) args : ArgsOpt,
+    pub(super) limit : usize,
+    pub(super) program : Option<OsString>,
+    pub(super) run : bool,
+}
+
+#[derive(Debug)]
+pub(super) struct ExplainOptions{
+    pub(super) cmdline : CmdlineOpt,
+}
+
+#[derive(Debug)]
+pub(super) struct DiffOptions{
+    pub(super) first : CmdlineOpt,
+    pub(super) second : CmdlineOpt,
+}
+
+#[derive(Debug)]
+pub(super) struct CompareParsersOptions{
+    pub(super) cmdline : CmdlineOpt,
+    pub(super) rest_after : Vec<usize>,
+    pub(super) code_page : u32,
+}
+
+#[derive(Debug)]
+pub(super) struct ResolveCommandOptions{
+    pub(super) command : OsString,
+    pub(super) virtual_fs : OsString,
+}
+
+#[derive(Debug)]
+pub(super) struct FullPathOptions{
+    pub(super) path : OsString,
+    pub(super) cwd : Option<OsString>,
+    pub(super) drive_cwds : Vec<OsString>,
+}
+
+#[derive(Debug)]
+pub(super) struct ConvertPathOptions{
+    pub(super) path : OsString,
+    pub(super) to : PathStyle,
+    /// POSIX path and Windows directory
+    pub(super) mounts : Vec<(OsString, OsString)>,
+}
+
+#[derive(Debug)]
+pub(super) struct ShellCommandOptions{
+    pub(super) template : OsString,
+    pub(super) file : OsString,
+    pub(super) args : Vec<OsString>,
+    pub(super) env : Vec<OsString>, // NAME=VALUE
+    pub(super) inherit_env : bool,
+}
+
+#[derive(Debug)]
+pub(super) struct AuditOptions{
+    pub(super) files : Vec<OsString>,
+    pub(super) env : Vec<OsString>, // NAME=VALUE
+    pub(super) all : bool,
+}
+
+#[derive(Debug)]
+pub(super) struct ShortcutOptions{
+    pub(super) file : OsString,
+    pub(super) code_page : u32,
+    pub(super) env : Vec<OsString>, // NAME=VALUE
+}
+
+#[derive(Debug)]
+pub(super) struct EventsOptions{
+    pub(super) files : Vec<OsString>,
+    pub(super) only_anomalies : bool,
+}
+
+#[derive(Debug)]
+pub(super) struct TemplateOptions{
+    pub(super) template : TemplateOpt,
+    pub(super) values : Vec<(String, OsString)>,
+    pub(super) quoting : QuotingStyle,
+}
+
+#[derive(Debug)]
+pub(super) struct EditOptions{
+    pub(super) cmdline : CmdlineOpt,
+    pub(super) edits : Vec<CmdlineEdit>,
 }
 
 pub(super) struct PrintOptions{
     pub(super) json : bool,
     pub(super) silent : bool,
     pub(super) print_args : bool,
+    pub(super) confusables : bool,
 }
 
 #[derive(Debug)]
@@ -45,6 +234,24 @@ pub(super) enum MainChoice{
     Help,
     PrintArgs,
     ExecOpts(ExecOptions),
+    GenerateVariants(VariantsOptions),
+    GenerateInvocations(InvocationsOptions),
+    BatBadBut(BatchOptions),
+    NarrowView(NarrowViewOptions),
+    WorstFit(WorstFitOptions),
+    SplitArgs(SplitArgsOptions),
+    Explain(ExplainOptions),
+    Diff(DiffOptions),
+    CompareParsers(CompareParsersOptions),
+    ResolveCommand(ResolveCommandOptions),
+    FullPathName(FullPathOptions),
+    ConvertPath(ConvertPathOptions),
+    ExpandShellCommand(ShellCommandOptions),
+    AuditPaths(AuditOptions),
+    ReadShortcut(ShortcutOptions),
+    SplitEvents(EventsOptions),
+    FillTemplate(TemplateOptions),
+    EditCmdline(EditOptions),
 }
 
 pub(super) struct MainOptions{
@@ -71,6 +278,11 @@ USAGE:
     [--print-args]
     [--dry-run]
     [--split-and-print-inner-cmdline]
+    [--export {{ rust | c | python | powershell }}]
+    [--lint {{ text | json | sarif }}]
+    [--virtual-fs <file>]
+    [--convert-program [--mount <posix-path> <windows-dir>]...]
+    [--env <name>=<value> | --env-unset <name> | --env-clear | --env-from-json <file>]...
     {{
       {{ {{ --program <program> | --program-utf16le-base64 <encoded-program> }} [--prepend-program] }} |
       {{ --program-from-cmd-line [--strip-program] }} |
@@ -80,7 +292,190 @@ USAGE:
       --cmd-line-is-null |
       --cmd-line-in-arg <cmdline> |
       --cmd-line-utf16le-base64 <encoded-cmd-line> |
-      --cmd-line-from-json-array <file> |
+      --cmd-line-from-json <file> |
+      --cmd-line-is-rest <arg>...
+    }}
+
+  \"{0}\"
+    [<PRINT_OPTION>...]
+    --generate-variants
+    [--count <n>]
+    [--seed <n>]
+    {{ --args-from-json <file> | --args-are-rest <arg>... }}
+
+  \"{0}\"
+    [<PRINT_OPTION>...]
+    --generate-invocations
+    [ --program <program> | --program-utf16le-base64 <encoded-program> ]
+    {{
+      --cmd-line-in-arg <cmdline> |
+      --cmd-line-utf16le-base64 <encoded-cmd-line> |
+      --cmd-line-from-json <file> |
+      --cmd-line-is-rest <arg>... |
+      --args-from-json <file> |
+      --args-are-rest <arg>...
+    }}
+
+  \"{0}\"
+    [<PRINT_OPTION>...]
+    --batbadbut
+    [ --program <program> | --program-utf16le-base64 <encoded-program> ]
+    {{
+      --cmd-line-in-arg <cmdline> |
+      --cmd-line-utf16le-base64 <encoded-cmd-line> |
+      --cmd-line-from-json <file> |
+      --cmd-line-is-rest <arg>... |
+      --args-from-json <file> |
+      --args-are-rest <arg>...
+    }}
+
+  \"{0}\"
+    [<PRINT_OPTION>...]
+    --narrow-view
+    [--code-page {{ 437 | 850 | 932 | 936 | 1250 | 1252 | 65001 }}]
+    [--no-best-fit]
+    [--default-char <char>]
+    {{
+      --cmd-line-in-arg <cmdline> |
+      --cmd-line-utf16le-base64 <encoded-cmd-line> |
+      --cmd-line-from-json <file> |
+      --cmd-line-is-rest <arg>...
+    }}
+
+  \"{0}\"
+    [<PRINT_OPTION>...]
+    --worstfit
+    [--code-page {{ 437 | 850 | 932 | 936 | 1250 | 1252 | 65001 }}]
+    {{
+      --cmd-line-in-arg <cmdline> |
+      --cmd-line-utf16le-base64 <encoded-cmd-line> |
+      --cmd-line-from-json <file> |
+      --cmd-line-is-rest <arg>...
+    }}
+
+  \"{0}\"
+    [<PRINT_OPTION>...]
+    --split-args
+    [--limit <n>]
+    [ --program <program> | --program-utf16le-base64 <encoded-program> ]
+    [--run]
+    {{
+      --cmd-line-in-arg <prefix> |
+      --cmd-line-utf16le-base64 <encoded-prefix> |
+      --cmd-line-from-json <file>
+    }}
+    {{
+      --args-from-json <file> |
+      --args-from-file <file> |
+      --args-are-rest <arg>...
+    }}
+
+  \"{0}\"
+    [<PRINT_OPTION>...]
+    --explain
+    {{
+      --cmd-line-in-arg <cmdline> |
+      --cmd-line-utf16le-base64 <encoded-cmd-line> |
+      --cmd-line-from-json <file> |
+      --cmd-line-is-rest <arg>...
+    }}
+
+  \"{0}\"
+    [<PRINT_OPTION>...]
+    --diff
+    {{
+      --cmd-line-in-arg <first-cmdline> |
+      --cmd-line-utf16le-base64 <encoded-first-cmd-line> |
+      --cmd-line-from-json <first-file>
+    }}
+    {{
+      --cmd-line-in-arg <second-cmdline> |
+      --cmd-line-utf16le-base64 <encoded-second-cmd-line> |
+      --cmd-line-from-json <second-file> |
+      --cmd-line-is-rest <arg>...
+    }}
+
+  \"{0}\"
+    [<PRINT_OPTION>...]
+    --compare-parsers
+    [--rest-after <n>]...
+    [--code-page {{ 437 | 850 | 932 | 936 | 1250 | 1252 | 65001 }}]
+    {{
+      --cmd-line-in-arg <cmdline> |
+      --cmd-line-utf16le-base64 <encoded-cmd-line> |
+      --cmd-line-from-json <file> |
+      --cmd-line-is-rest <arg>...
+    }}
+
+  \"{0}\"
+    [<PRINT_OPTION>...]
+    --resolve-command
+    --virtual-fs <file>
+    <command>
+
+  \"{0}\"
+    [<PRINT_OPTION>...]
+    --full-path-name
+    [--cwd <dir>]
+    [--drive-cwd <dir>]...
+    <path>
+
+  \"{0}\"
+    [<PRINT_OPTION>...]
+    --convert-path
+    [--to {{ windows | mixed | msys | cygwin | wsl }}]
+    [--mount <posix-path> <windows-dir>]...
+    <path>
+
+  \"{0}\"
+    [<PRINT_OPTION>...]
+    --expand-shell-command
+    [--env <name>=<value>]...
+    [--inherit-env]
+    <template> <file> [<arg>...]
+
+  \"{0}\"
+    [<PRINT_OPTION>...]
+    --audit-paths
+    [--env <name>=<value>]...
+    [--all]
+    <file>...
+
+  \"{0}\"
+    [<PRINT_OPTION>...]
+    --read-shortcut
+    [--code-page {{ 437 | 850 | 932 | 936 | 1250 | 1252 | 65001 }}]
+    [--env <name>=<value>]...
+    <file>
+
+  \"{0}\"
+    [<PRINT_OPTION>...]
+    --split-events
+    [--only-anomalies]
+    <file>...
+
+  \"{0}\"
+    [<PRINT_OPTION>...]
+    --fill-template
+    [--quoting {{ crt | crt-always | raw }}]
+    {{ --template <template> | --template-from-json <file> }}
+    [--value <name> <value>]...
+    [--values-are-rest <name> <arg>...]
+
+  \"{0}\"
+    [<PRINT_OPTION>...]
+    --edit-cmd-line
+    {{
+      --insert <n> <arg> |
+      --delete <n> |
+      --replace <n> <arg> |
+      --insert-before <matching-arg> <arg> |
+      --insert-after <matching-arg> <arg>
+    }}...
+    {{
+      --cmd-line-in-arg <cmdline> |
+      --cmd-line-utf16le-base64 <encoded-cmd-line> |
+      --cmd-line-from-json <file> |
       --cmd-line-is-rest <arg>...
     }}
 
@@ -102,10 +497,51 @@ OPTIONS:
 
   --dry-run
     Don’t actually execute the program.
+    If the program (or arg 0, if the program is NULL) is a batch file, print the command line,
+    that `CreateProcessW` passes to `%ComSpec%` instead, and how cmd.exe splits it into commands and parameters.
 
   --split-and-print-inner-cmdline
     Split the command line assembled from the value of a `--cmd-line-*` option and other options into arguments and print those arguments.
 
+  --export {{ rust | c | python | powershell }}
+    Don’t execute the program, but print code in the given language, that calls `CreateProcessW` with the same program and command line.
+    Differences, that cannot be avoided in that language, are printed as warnings to stderr.
+
+  --lint {{ text | json | sarif }}
+    Don’t execute the program, but check the program and the command line for problems and print the findings.
+    Each finding has a rule ID, a level and a range of UTF-16 code units in the command line.
+    This program fails, if there is a finding with the level `error`.
+
+  --virtual-fs <file>
+    Requires `--program-is-null` and `--dry-run`.
+    Emulate how `CreateProcessW` searches for the program in the first argument of the command line,
+    using the files and directories in the given JSON file, and print every candidate that is tried.
+    If arg 0 is not quoted, each part of the command line that ends before a space or tab is tried,
+    and a warning is printed, if a file created at an earlier candidate would be launched instead.
+    The JSON file has the fields \"files\" (an array of paths), \"app-dir\", \"cwd\", \"system-dir\",
+    \"system16-dir\", \"windows-dir\" and \"path\" (separated by semicolons).
+
+  --convert-program
+    Convert the program from mixed (`C:/x`), MSYS (`/c/x`), Cygwin (`/cygdrive/c/x`) or WSL (`/mnt/c/x`) form
+    to a Windows path, before it is passed to `CreateProcessW`. See `--convert-path`.
+
+  --env <name>=<value>
+    Set an environment variable in the environment block of the new process.
+    Without any of the `--env*` options, the new process inherits the environment of this program.
+
+  --env-unset <name>
+    Remove an environment variable from the environment block. Names are compared without regard to case.
+
+  --env-clear
+    Remove all environment variables, that are set before this option, from the environment block.
+
+  --env-from-json <file>
+    Set the environment variables of the object \"env\" in a JSON file. A variable with the value null is removed.
+
+  The `--env*` options are applied in their order to the environment of this program.
+  The block is sorted like Windows sorts it: the current directories of drives (`=C:`) first, then by the upper case names.
+  `--dry-run` prints the block. They can not be combined with `--export` or `--lint`.
+
   --print-args-only
     Print all arguments to this program and do nothing else.
 
@@ -144,6 +580,202 @@ OPTIONS:
   --cmd-line-is-rest <arg>...
     Use the rest of the command line as new command line.
 
+  --generate-variants
+    Print distinct command lines, that are all split into the same arguments by the algorithm of the Microsoft C-Runtime.
+    The arguments are given by an `--args-*` option. The first of them is argument zero.
+    Every variant is checked with the parser of this program, before it is printed.
+
+  --count <n>
+    The maximum number of variants to print. The default is 100.
+
+  --seed <n>
+    Randomly sample the variants with this seed, instead of enumerating them.
+
+  --generate-invocations
+    Print what to type into git-bash, cmd.exe and PowerShell to start a program with the given command line.
+    For each shell also print the shortest invocation of this program, that reproduces the launch.
+    Without `--program`, that invocation uses `--program-is-null`.
+
+  --batbadbut
+    Check if a batch file (.bat or .cmd) gets the intended arguments, when it is started with the given command line.
+    CreateProcessW runs batch files with `%ComSpec%` (cmd.exe), which reads the command line differently than the Microsoft C-Runtime.
+    Every argument, that escapes into the syntax of cmd.exe, is reported together with the injected commands.
+    If the arguments are given, the first one is argument zero and is quoted like a program name, the others are quoted
+    for the Microsoft C-Runtime. Without `--program`, argument zero must be the batch file. This program fails, if the command line is unsafe.
+
+  --narrow-view
+    Show what a program, that uses `char` instead of `wchar_t`, gets from the command line.
+    The command line is converted like `WideCharToMultiByte` does. The result is what `GetCommandLineA` returns.
+    It is split like the Microsoft C-Runtime splits it for `main(int argc, char *argv[])`.
+
+  --code-page {{ 437 | 850 | 932 | 936 | 1250 | 1252 | 65001 }}
+    The ANSI code page of the process. The default is 1252.
+
+  --no-best-fit
+    Don’t replace characters, that the code page lacks, by similar characters, like `WC_NO_BEST_FIT_CHARS`.
+    Best fit uses the tables of Microsoft, if they were generated with `scripts/gen-codepage-tables.py`.
+    Otherwise it is approximate: the same small set of similar characters is used for every code page.
+
+  --default-char <char>
+    The ASCII character for characters, that the code page lacks. The default is `?`.
+
+  --worstfit
+    Check, whether the arguments change, if the command line is converted to the code page with best fit.
+    Both command lines are split, and every argument, that is merged, split, becomes an option or changes, is reported
+    with the characters, that caused it. Fails, if an argument changes.
+
+  --args-from-json <file>
+    Read the arguments from the array \"args\" in a JSON file. Write a dash/hyphen (-) for stdin.
+
+  --args-are-rest <arg>...
+    Use the rest of the arguments.
+
+  --args-from-file <file>
+    Read the arguments from a UTF-8 text file, one argument per line. Write a dash/hyphen (-) for stdin.
+
+  --explain
+    Show each step of the parser, while it splits the command line: the code units, whether they are in quotes
+    before and after, what is done with them and where an argument ends.
+
+  --diff
+    Compare the arguments of two command lines, for example the intended command line and the one, that a child process got.
+    Added, removed and changed arguments are marked, and in changed arguments the differing characters are put in ⟦⟧.
+    Fails, if the arguments differ.
+
+  --compare-parsers
+    Show a table of the arguments, that each parser of this tool gets from the command line.
+    Rows, where the parsers disagree, are marked with ≠, and cells, that differ from the first column, with *.
+
+  --rest-after <n>
+    Add a column, where the rest of the command line after argument <n> is one argument. The default is 0.
+
+  --split-args
+    Put the arguments after the prefix command line into as few command lines as possible, like xargs.
+    Each argument is quoted. Fails, if an argument does not fit into a command line with the prefix.
+
+  --limit <n>
+    The maximum length of each command line in UTF-16 code units including the terminating NUL character.
+    The default is 32767, the limit of CreateProcessW. Use 8191 for cmd.exe.
+
+  --run
+    Run the command lines one after another, until one has a non-zero exit code.
+
+  --resolve-command
+    Find the file, that <command> starts in cmd.exe, with `start` (ShellExecuteExW), with `Get-Command -CommandType Application`
+    in PowerShell and with `CreateProcessW`, in the virtual filesystem given with `--virtual-fs`.
+    cmd.exe has internal commands and tries each extension of `PATHEXT` in the current directory and in `PATH`,
+    `start` also uses the registry key `App Paths`, PowerShell doesn't search the current directory,
+    and `CreateProcessW` only appends `.exe`. Besides the fields described for `--virtual-fs` for `--dry-run`,
+    the JSON file may have the objects \"env\" (for example with `PATHEXT`) and \"app-paths\" (from `app.exe` to its path).
+    Fails, if the resolvers disagree.
+
+  --full-path-name
+    Make <path> absolute and normalize it like `GetFullPathNameW`, without accessing the file system.
+    Print the kind of the path (verbatim `\\\\?\\`, local device `\\\\.\\`, UNC, drive-absolute, drive-relative, rooted, relative),
+    each change, and warnings about reserved device names like `NUL` or `CON.txt` and about paths longer than MAX_PATH.
+    `--dry-run` also prints the full path of the program.
+
+  --cwd <dir>
+    The current directory. The default is the current directory of this program.
+
+  --drive-cwd <dir>
+    The current directory of another drive, for example `D:\\data`. The default is taken from the environment variables `=D:`.
+
+  --convert-path
+    Convert <path> like `cygpath` to the form given with `--to`. The default is `windows` (`C:\\x`).
+    The other forms are `mixed` (`C:/x`), `msys` (`/c/x`), `cygwin` (`/cygdrive/c/x`) and `wsl` (`/mnt/c/x`).
+    The form of <path> is recognized. UNC paths (`\\\\server\\share` or `//server/share`) and verbatim paths
+    (`\\\\?\\C:\\x`) are supported. Relative paths stay relative. Rooted paths (`\\x`) and drive-relative paths (`C:x`)
+    depend on the current drive or directory, so they are kept in the forms `windows` and `mixed`, and fail for the others.
+
+  --to {{ windows | mixed | msys | cygwin | wsl }}
+    The form of the converted path.
+
+  --mount <posix-path> <windows-dir>
+    Add an entry to the mount table, for example `--mount /usr C:\\msys64\\usr`. The longest matching entry wins.
+
+  --expand-shell-command
+    Expand the template of a file association (`HKCR\\<class>\\shell\\<verb>\\command`) like `ShellExecuteExW` does,
+    for example `\"%SystemRoot%\\system32\\NOTEPAD.EXE\" %1`. `%1`, `%0` and `%L` become <file>, `%2` to `%9` the arguments,
+    `%*` all arguments and `%~n` the arguments from the n-th on. Then `%VAR%` is expanded like for `REG_EXPAND_SZ`,
+    with the variables given with `--env`. Print the program, the command line and its arguments,
+    and warn, if `%1` or a program with spaces is not in quotes.
+
+  --inherit-env
+    Also expand the environment variables of this program. Variables given with `--env` take precedence.
+
+  --audit-paths
+    Find services and scheduled tasks, whose program path contains spaces, but is not in quotes.
+    Each <file> is a `.reg` export (UTF-16LE with byte order mark, or `REGEDIT4`) or the XML of a scheduled task.
+    The command lines are the `ImagePath` of the keys below `...\\Services`, except for drivers,
+    and `<Command>` and `<Arguments>` of the `<Exec>` actions of tasks. `REG_EXPAND_SZ` values and tasks are
+    expanded with the variables given with `--env`, and defaults like `SystemRoot=C:\\Windows`.
+    Each command line is split, and the names, that `CreateProcessW` tries before the program, are printed.
+    Fails, if an entry is vulnerable.
+
+  --all
+    Print all entries, not only the vulnerable ones.
+
+  --read-shortcut
+    Read a shortcut (`.lnk`, MS-SHLLINK) and print the target, the arguments, the working directory and the target
+    with environment variables. The target is escaped like argument zero, the arguments are appended as they are,
+    and the resulting command line is split. Strings, that are not in UTF-16, are decoded with `--code-page`.
+    The environment variables in the target are expanded with the variables given with `--env`.
+
+  --split-events
+    Split the command lines of process creation events, Sysmon event 1 and Security event 4688.
+    Each <file> is an export of events as XML (`wevtutil qe ... /f:xml`, UTF-16LE or UTF-8) or JSON lines.
+    `CommandLine` is split with the rules of the CRT, and argument zero is compared with `Image` or `NewProcessName`.
+    Flags anomalies: argument zero, that does not name the image, unterminated or escaped quotes,
+    quotes inside of words like `c\"m\"d`, confusable characters and Base64 arguments like `-EncodedCommand`.
+    Prints NDJSON, one object per event with the record ID of the event, also without `--json`.
+
+  --only-anomalies
+    Print only the events with anomalies.
+
+  --fill-template
+    Fill a template like `tool.exe --in {{input}} -- {{rest...}}` and print the command line.
+    Literal text is kept as it is. `{{{{` and `}}}}` are a literal `{{` and `}}`.
+    The value of a placeholder is quoted, so that it becomes exactly one argument. A variadic placeholder `{{name...}}` becomes one argument per value.
+    A placeholder can select its own quoting, for example `{{input:crt-always}}`.
+    The command line is split again, to check that each placeholder became the intended arguments. If not, this program fails.
+
+  --quoting {{ crt | crt-always | raw }}
+    How values are quoted: only if needed, always, or not at all. The default is `crt`.
+
+  --template <template>
+    Specify the template in one argument.
+
+  --template-from-json <file>
+    Read the string \"template\" and the object \"values\" from a JSON file. Write a dash/hyphen (-) for stdin.
+    A value is a string, or an array of strings for a variadic placeholder.
+
+  --value <name> <value>
+    Set the value of the placeholder <name>. Repeat it to give several values to a variadic placeholder.
+
+  --values-are-rest <name> <arg>...
+    Use the rest of the arguments as values of the variadic placeholder <name>.
+
+  --edit-cmd-line
+    Edit the command line given by a `--cmd-line-*` option and print it. The edits are done in the given order.
+    Arguments, that are not touched, keep their raw text and the whitespace around them. New arguments are quoted if needed.
+    The edited command line is split again, to check that only the intended arguments changed. If not, this program fails.
+
+  --insert <n> <arg>
+    Insert an argument, so that it becomes argument <n>.
+
+  --delete <n>
+    Delete argument <n>.
+
+  --replace <n> <arg>
+    Replace argument <n>.
+
+  --insert-before <matching-arg> <arg>
+    Insert an argument before the first argument, that is equal to <matching-arg>.
+
+  --insert-after <matching-arg> <arg>
+    Insert an argument after the first argument, that is equal to <matching-arg>.
+
 
 PRINT_OPTIONS:
 
@@ -153,6 +785,10 @@ PRINT_OPTIONS:
   --silent
     Don't be verbose
 
+  --confusables
+    When printing arguments, mark characters, that look like whitespace, quotes or dashes, but are none,
+    and invisible and bidirectional control characters.
+
 
 ", arg0, env!("GIT_HASH"), dirty_text)
 }
@@ -192,27 +828,707 @@ fn decode_utf16le_base64(input: &OsStr) -> Result<OsString,String> {
 
 }
 
-pub(super) fn get_options(cmd_line : &[u16], args: &Vec<crate::commandline::Arg>) -> Result<MainOptions,String> {
-    let mut args_iter = args.iter();
-    let mut print_opts = PrintOptions{
-        print_args: false,
-        json: false,
-        silent: false,
-    };
+fn next_value<'a, 'b>(args_iter: &mut std::slice::Iter<'a, commandline::Arg<'b>>, arg: &commandline::Arg<'_>)
+    -> Result<&'a commandline::Arg<'b>, String>
+{
+    args_iter.next().ok_or_else(|| format!("missing argument for option:\n  {}", arg))
+}
 
-    // skip first/zerothed argument
-    if let None = args_iter.next() {
+fn parse_number<T: std::str::FromStr>(arg: &commandline::Arg<'_>, value: &commandline::Arg<'_>) -> Result<T, String> {
+    value.arg.to_str()
+        .and_then(|s| s.parse::<T>().ok())
+        .ok_or_else(|| format!("bad argument for the following option: not a number\n {}\nbad argument:\n {}", arg, value))
+}
+
+/// An option, that selects the mode, like `--generate-variants`, may only be preceded by print options.
+fn require_leading_print_opts(name: &OsStr, arg: &commandline::Arg<'_>, only_print_opts_thus_far: bool) -> Result<(), String> {
+    if only_print_opts_thus_far {
+        Ok(())
+    } else {
+        Err(format!("bad option, \"{}\" may only be preceded by print options:\n  {}", name.to_string_lossy(), arg))
+    }
+}
+
+fn parse_print_opt(x: &OsStr, print_opts: &mut PrintOptions) -> bool {
+    if x == OsStr::new("--json") {
+        print_opts.json = true;
+    } else if x == OsStr::new("--silent") {
+        print_opts.silent = true;
+    } else if x == OsStr::new("--print-args") {
         print_opts.print_args = true;
-        return Ok( MainOptions{ print_opts, main_choice: MainChoice::PrintArgs, });
+    } else if x == OsStr::new("--confusables") {
+        print_opts.confusables = true;
+    } else {
+        return false;
     }
+    true
+}
 
-    let opt_program : &OsStr = OsStr::new("--program");
+fn parse_args_opt(
+    x: &OsStr,
+    arg: &commandline::Arg<'_>,
+    args_iter: &mut std::slice::Iter<'_, commandline::Arg<'_>>,
+    args_opt: &mut Option<ArgsOpt>,
+) -> Result<bool, String> {
+    let new_args_opt = if x == OsStr::new("--args-from-json") {
+        ArgsOpt::FromJSONFile(next_value(args_iter, arg)?.arg.clone())
+    } else if x == OsStr::new("--args-from-file") {
+        ArgsOpt::FromFile(next_value(args_iter, arg)?.arg.clone())
+    } else if x == OsStr::new("--args-are-rest") {
+        // all args get consumed
+        ArgsOpt::Rest(args_iter.by_ref().map(|a| a.arg.clone()).collect())
+    } else {
+        return Ok(false);
+    };
+    if args_opt.is_some() {
+        return Err(format!("bad option, args are already initilaized:\n  {}", arg));
+    }
+    *args_opt = Some(new_args_opt);
+    Ok(true)
+}
+
+fn parse_cmdline_opt<'a>(
+    x: &OsStr,
+    arg: &commandline::Arg<'a>,
+    cmd_line: &'a [u16],
+    args_iter: &mut std::slice::Iter<'_, commandline::Arg<'a>>,
+    cmdline_opt: &mut Option<CmdlineOpt>,
+) -> Result<bool, String> {
+    let new_cmdline_opt = if x == OsStr::new("--cmd-line-in-arg") {
+        CmdlineOpt::Str(next_value(args_iter, arg)?.arg.clone())
+    } else if x == OsStr::new("--cmd-line-utf16le-base64") {
+        let next_arg = next_value(args_iter, arg)?;
+        match decode_utf16le_base64(&next_arg.arg) {
+            Ok(p) => CmdlineOpt::Str(p),
+            Err(err_str) => return Err(format!("bad argument for the following option: {}\n {}\nbad argument:\n {}", &err_str, &arg, &next_arg)),
+        }
+    } else if x == OsStr::new("--cmd-line-from-json") {
+        CmdlineOpt::FromJSONFile(next_value(args_iter, arg)?.arg.clone())
+    } else if x == OsStr::new("--cmd-line-is-rest") {
+        // all args get consumed
+        args_iter.by_ref().for_each(drop);
+        CmdlineOpt::Str(OsString::from_wide(commandline::get_rest(cmd_line, arg)))
+    } else {
+        return Ok(false);
+    };
+    if cmdline_opt.is_some() {
+        return Err(format!("bad option, cmd line is already initilaized:\n  {}", &arg));
+    }
+    *cmdline_opt = Some(new_cmdline_opt);
+    Ok(true)
+}
+
+/// Parses `--program` and `--program-utf16le-base64`.
+fn parse_program_opt(
+    x: &OsStr,
+    arg: &commandline::Arg<'_>,
+    args_iter: &mut std::slice::Iter<'_, commandline::Arg<'_>>,
+    program: &mut Option<OsString>,
+) -> Result<bool, String> {
+    if x != OsStr::new("--program") && x != OsStr::new("--program-utf16le-base64") {
+        return Ok(false);
+    }
+    if program.is_some() {
+        return Err(format!("bad option, program is already initilaized:\n  {}", &arg));
+    }
+    let next_arg = next_value(args_iter, arg)?;
+    *program = Some(if x == OsStr::new("--program") {
+        next_arg.arg.clone()
+    } else {
+        decode_utf16le_base64(&next_arg.arg)
+            .map_err(|err_str| format!("bad argument for the following option: {}\n {}\nbad argument:\n {}", &err_str, &arg, &next_arg))?
+    });
+    Ok(true)
+}
+
+/// Parses the options of a mode, that takes a command line or arguments, and optionally a program.
+fn get_target_options<'a>(
+    cmd_line: &'a [u16],
+    args_iter: &mut std::slice::Iter<'_, commandline::Arg<'a>>,
+    print_opts: &mut PrintOptions,
+) -> Result<(CmdlineOrArgsOpt, Option<OsString>), String> {
+    let mut cmdline_opt : Option<CmdlineOpt> = None;
+    let mut args : Option<ArgsOpt> = None;
+    let mut program : Option<OsString> = None;
+
+    while let Some(arg) = args_iter.next() {
+        match arg.arg.as_os_str() {
+            x if parse_print_opt(x, print_opts) => {},
+            x if parse_cmdline_opt(x, arg, cmd_line, args_iter, &mut cmdline_opt)? => {},
+            x if parse_args_opt(x, arg, args_iter, &mut args)? => {},
+            x if parse_program_opt(x, arg, args_iter, &mut program)? => {},
+            _other => {
+                return Err(format!("unknown option:\n  {}", &arg));
+            }
+        }
+    }
+    let target = match (cmdline_opt, args) {
+        (Some(cmdline), None) => CmdlineOrArgsOpt::Cmdline(cmdline),
+        (None, Some(args)) => CmdlineOrArgsOpt::Args(args),
+        (None, None) => return Err("cmd line was not specied".to_owned()),
+        (Some(_), Some(_)) => return Err("Do not specify a cmd line and args".to_owned()),
+    };
+    Ok((target, program))
+}
+
+fn get_variants_options(
+    args_iter: &mut std::slice::Iter<'_, commandline::Arg<'_>>,
+    print_opts: &mut PrintOptions,
+) -> Result<VariantsOptions, String> {
+    let mut args : Option<ArgsOpt> = None;
+    let mut count : usize = 100;
+    let mut seed : Option<u64> = None;
+
+    while let Some(arg) = args_iter.next() {
+        match arg.arg.as_os_str() {
+            x if parse_print_opt(x, print_opts) => {},
+            x if parse_args_opt(x, arg, args_iter, &mut args)? => {},
+            x if x == OsStr::new("--count") => {
+                count = parse_number(arg, next_value(args_iter, arg)?)?;
+            },
+            x if x == OsStr::new("--seed") => {
+                seed = Some(parse_number(arg, next_value(args_iter, arg)?)?);
+            },
+            _other => {
+                return Err(format!("unknown option:\n  {}", &arg));
+            }
+        }
+    }
+    match args {
+        Some(args) => Ok(VariantsOptions{ args, count, seed }),
+        None => Err("args were not specified".to_owned()),
+    }
+}
+
+fn get_narrow_view_options<'a>(
+    cmd_line: &'a [u16],
+    args_iter: &mut std::slice::Iter<'_, commandline::Arg<'a>>,
+    print_opts: &mut PrintOptions,
+) -> Result<NarrowViewOptions, String> {
+    let mut cmdline_opt : Option<CmdlineOpt> = None;
+    let mut code_page : u32 = 1252;
+    let mut best_fit = true;
+    let mut default_char : u8 = b'?';
+
+    while let Some(arg) = args_iter.next() {
+        match arg.arg.as_os_str() {
+            x if parse_print_opt(x, print_opts) => {},
+            x if parse_cmdline_opt(x, arg, cmd_line, args_iter, &mut cmdline_opt)? => {},
+            x if x == OsStr::new("--code-page") => {
+                code_page = parse_number(arg, next_value(args_iter, arg)?)?;
+            },
+            x if x == OsStr::new("--no-best-fit") => {
+                best_fit = false;
+            },
+            x if x == OsStr::new("--default-char") => {
+                let next_arg = next_value(args_iter, arg)?;
+                default_char = match next_arg.arg.to_str().map(str::as_bytes) {
+                    Some(&[c]) if c.is_ascii() => c,
+                    _ => return Err(format!("bad argument for the following option: not one ASCII character\n {}\nbad argument:\n {}", &arg, &next_arg)),
+                };
+            },
+            _other => {
+                return Err(format!("unknown option:\n  {}", &arg));
+            }
+        }
+    }
+    match cmdline_opt {
+        Some(cmdline) => Ok(NarrowViewOptions{ cmdline, code_page, best_fit, default_char }),
+        None => Err("cmd line was not specied".to_owned()),
+    }
+}
+
+fn get_worstfit_options<'a>(
+    cmd_line: &'a [u16],
+    args_iter: &mut std::slice::Iter<'_, commandline::Arg<'a>>,
+    print_opts: &mut PrintOptions,
+) -> Result<WorstFitOptions, String> {
+    let mut cmdline_opt : Option<CmdlineOpt> = None;
+    let mut code_page : u32 = 1252;
+
+    while let Some(arg) = args_iter.next() {
+        match arg.arg.as_os_str() {
+            x if parse_print_opt(x, print_opts) => {},
+            x if parse_cmdline_opt(x, arg, cmd_line, args_iter, &mut cmdline_opt)? => {},
+            x if x == OsStr::new("--code-page") => {
+                code_page = parse_number(arg, next_value(args_iter, arg)?)?;
+            },
+            _other => {
+                return Err(format!("unknown option:\n  {}", &arg));
+            }
+        }
+    }
+    match cmdline_opt {
+        Some(cmdline) => Ok(WorstFitOptions{ cmdline, code_page }),
+        None => Err("cmd line was not specied".to_owned()),
+    }
+}
+
+fn get_split_args_options<'a>(
+    cmd_line: &'a [u16],
+    args_iter: &mut std::slice::Iter<'_, commandline::Arg<'a>>,
+    print_opts: &mut PrintOptions,
+) -> Result<SplitArgsOptions, String> {
+    let mut prefix : Option<CmdlineOpt> = None;
+    let mut args : Option<ArgsOpt> = None;
+    let mut limit : usize = crate::batches::MAX_CMDLINE_LEN;
+    let mut program : Option<OsString> = None;
+    let mut run = false;
+
+    while let Some(arg) = args_iter.next() {
+        match arg.arg.as_os_str() {
+            x if parse_print_opt(x, print_opts) => {},
+            x if parse_cmdline_opt(x, arg, cmd_line, args_iter, &mut prefix)? => {},
+            x if parse_args_opt(x, arg, args_iter, &mut args)? => {},
+            x if x == OsStr::new("--limit") => {
+                let next_arg = next_value(args_iter, arg)?;
+                limit = parse_number(arg, next_arg)?;
+                if limit == 0 || limit > crate::batches::MAX_CMDLINE_LEN {
+                    return Err(format!("bad argument for the following option: not between 1 and {}\n {}\nbad argument:\n {}",
+                                       crate::batches::MAX_CMDLINE_LEN, &arg, &next_arg));
+                }
+            },
+            x if parse_program_opt(x, arg, args_iter, &mut program)? => {},
+            x if x == OsStr::new("--run") => {
+                run = true;
+            },
+            _other => {
+                return Err(format!("unknown option:\n  {}", &arg));
+            }
+        }
+    }
+    match (prefix, args) {
+        (Some(prefix), Some(args)) => Ok(SplitArgsOptions{ prefix, args, limit, program, run }),
+        (None, _) => Err("prefix cmd line was not specied".to_owned()),
+        (_, None) => Err("args were not specied".to_owned()),
+    }
+}
+
+fn get_explain_options<'a>(
+    cmd_line: &'a [u16],
+    args_iter: &mut std::slice::Iter<'_, commandline::Arg<'a>>,
+    print_opts: &mut PrintOptions,
+) -> Result<ExplainOptions, String> {
+    let mut cmdline_opt : Option<CmdlineOpt> = None;
+
+    while let Some(arg) = args_iter.next() {
+        match arg.arg.as_os_str() {
+            x if parse_print_opt(x, print_opts) => {},
+            x if parse_cmdline_opt(x, arg, cmd_line, args_iter, &mut cmdline_opt)? => {},
+            _other => {
+                return Err(format!("unknown option:\n  {}", &arg));
+            }
+        }
+    }
+    match cmdline_opt {
+        Some(cmdline) => Ok(ExplainOptions{ cmdline }),
+        None => Err("cmd line was not specied".to_owned()),
+    }
+}
+
+fn get_diff_options<'a>(
+    cmd_line: &'a [u16],
+    args_iter: &mut std::slice::Iter<'_, commandline::Arg<'a>>,
+    print_opts: &mut PrintOptions,
+) -> Result<DiffOptions, String> {
+    let mut first : Option<CmdlineOpt> = None;
+    let mut second : Option<CmdlineOpt> = None;
+
+    while let Some(arg) = args_iter.next() {
+        // The first cmd line option gives the first command line, the next one the second.
+        let cmdline_opt = if first.is_none() { &mut first } else { &mut second };
+        match arg.arg.as_os_str() {
+            x if parse_print_opt(x, print_opts) => {},
+            x if parse_cmdline_opt(x, arg, cmd_line, args_iter, cmdline_opt)? => {},
+            _other => {
+                return Err(format!("unknown option:\n  {}", &arg));
+            }
+        }
+    }
+    match (first, second) {
+        (Some(first), Some(second)) => Ok(DiffOptions{ first, second }),
+        _ => Err("two cmd lines must be specified".to_owned()),
+    }
+}
+
+fn get_compare_parsers_options<'a>(
+    cmd_line: &'a [u16],
+    args_iter: &mut std::slice::Iter<'_, commandline::Arg<'a>>,
+    print_opts: &mut PrintOptions,
+) -> Result<CompareParsersOptions, String> {
+    let mut cmdline_opt : Option<CmdlineOpt> = None;
+    let mut rest_after : Vec<usize> = vec![];
+    let mut code_page : u32 = 1252;
+
+    while let Some(arg) = args_iter.next() {
+        match arg.arg.as_os_str() {
+            x if parse_print_opt(x, print_opts) => {},
+            x if parse_cmdline_opt(x, arg, cmd_line, args_iter, &mut cmdline_opt)? => {},
+            x if x == OsStr::new("--rest-after") => {
+                rest_after.push(parse_number(arg, next_value(args_iter, arg)?)?);
+            },
+            x if x == OsStr::new("--code-page") => {
+                code_page = parse_number(arg, next_value(args_iter, arg)?)?;
+            },
+            _other => {
+                return Err(format!("unknown option:\n  {}", &arg));
+            }
+        }
+    }
+    if rest_after.is_empty() {
+        rest_after.push(0);
+    }
+    match cmdline_opt {
+        Some(cmdline) => Ok(CompareParsersOptions{ cmdline, rest_after, code_page }),
+        None => Err("cmd line was not specied".to_owned()),
+    }
+}
+
+fn get_resolve_command_options(
+    args_iter: &mut std::slice::Iter<'_, commandline::Arg<'_>>,
+    print_opts: &mut PrintOptions,
+) -> Result<ResolveCommandOptions, String> {
+    let mut command : Option<OsString> = None;
+    let mut virtual_fs : Option<OsString> = None;
+
+    while let Some(arg) = args_iter.next() {
+        match arg.arg.as_os_str() {
+            x if parse_print_opt(x, print_opts) => {},
+            x if x == OsStr::new("--virtual-fs") => {
+                virtual_fs = Some(next_value(args_iter, arg)?.arg.clone());
+            },
+            _ if command.is_none() => {
+                command = Some(arg.arg.clone());
+            },
+            _other => {
+                return Err(format!("unknown option:\n  {}", &arg));
+            }
+        }
+    }
+    match (command, virtual_fs) {
+        (Some(command), Some(virtual_fs)) => Ok(ResolveCommandOptions{ command, virtual_fs }),
+        (None, _) => Err("command was not specied".to_owned()),
+        (_, None) => Err("\"--virtual-fs\" was not specied".to_owned()),
+    }
+}
+
+fn get_full_path_options(
+    args_iter: &mut std::slice::Iter<'_, commandline::Arg<'_>>,
+    print_opts: &mut PrintOptions,
+) -> Result<FullPathOptions, String> {
+    let mut path : Option<OsString> = None;
+    let mut cwd : Option<OsString> = None;
+    let mut drive_cwds : Vec<OsString> = vec![];
+
+    while let Some(arg) = args_iter.next() {
+        match arg.arg.as_os_str() {
+            x if parse_print_opt(x, print_opts) => {},
+            x if x == OsStr::new("--cwd") => {
+                cwd = Some(next_value(args_iter, arg)?.arg.clone());
+            },
+            x if x == OsStr::new("--drive-cwd") => {
+                drive_cwds.push(next_value(args_iter, arg)?.arg.clone());
+            },
+            _ if path.is_none() => {
+                path = Some(arg.arg.clone());
+            },
+            _other => {
+                return Err(format!("unknown option:\n  {}", &arg));
+            }
+        }
+    }
+    match path {
+        Some(path) => Ok(FullPathOptions{ path, cwd, drive_cwds }),
+        None => Err("path was not specied".to_owned()),
+    }
+}
+
+/// Parses `--mount <posix-path> <windows-dir>`.
+fn parse_mount_opt<'a>(
+    x: &OsStr,
+    arg: &commandline::Arg<'_>,
+    args_iter: &mut std::slice::Iter<'_, commandline::Arg<'a>>,
+    mounts: &mut Vec<(OsString, OsString)>,
+) -> Result<bool, String> {
+    if x != OsStr::new("--mount") {
+        return Ok(false);
+    }
+    let posix = next_value(args_iter, arg)?.arg.clone();
+    let windows = next_value(args_iter, arg)?.arg.clone();
+    mounts.push((posix, windows));
+    Ok(true)
+}
+
+fn get_convert_path_options(
+    args_iter: &mut std::slice::Iter<'_, commandline::Arg<'_>>,
+    print_opts: &mut PrintOptions,
+) -> Result<ConvertPathOptions, String> {
+    let mut path : Option<OsString> = None;
+    let mut to : PathStyle = PathStyle::Windows;
+    let mut mounts : Vec<(OsString, OsString)> = vec![];
+
+    while let Some(arg) = args_iter.next() {
+        match arg.arg.as_os_str() {
+            x if parse_print_opt(x, print_opts) => {},
+            x if parse_mount_opt(x, arg, args_iter, &mut mounts)? => {},
+            x if x == OsStr::new("--to") => {
+                let next_arg = next_value(args_iter, arg)?;
+                to = match next_arg.arg.to_str().and_then(PathStyle::from_name) {
+                    Some(style) => style,
+                    None => return Err(format!("bad argument for the following option: unknown path style\n {}\nbad argument:\n {}", &arg, &next_arg)),
+                };
+            },
+            _ if path.is_none() => {
+                path = Some(arg.arg.clone());
+            },
+            _other => {
+                return Err(format!("unknown option:\n  {}", &arg));
+            }
+        }
+    }
+    match path {
+        Some(path) => Ok(ConvertPathOptions{ path, to, mounts }),
+        None => Err("path was not specied".to_owned()),
+    }
+}
+
+/// The arguments after <file> are taken as they are.
+fn get_shell_command_options(
+    args_iter: &mut std::slice::Iter<'_, commandline::Arg<'_>>,
+    print_opts: &mut PrintOptions,
+) -> Result<ShellCommandOptions, String> {
+    let mut template : Option<OsString> = None;
+    let mut file : Option<OsString> = None;
+    let mut env : Vec<OsString> = vec![];
+    let mut inherit_env = false;
+
+    while let Some(arg) = args_iter.next() {
+        match arg.arg.as_os_str() {
+            x if parse_print_opt(x, print_opts) => {},
+            x if x == OsStr::new("--env") => {
+                env.push(next_value(args_iter, arg)?.arg.clone());
+            },
+            x if x == OsStr::new("--inherit-env") => {
+                inherit_env = true;
+            },
+            _ if template.is_none() => {
+                template = Some(arg.arg.clone());
+            },
+            _ => {
+                file = Some(arg.arg.clone());
+                break;
+            },
+        }
+    }
+    let args : Vec<OsString> = args_iter.map(|arg| arg.arg.clone()).collect();
+    match (template, file) {
+        (Some(template), Some(file)) => Ok(ShellCommandOptions{ template, file, args, env, inherit_env }),
+        (None, _) => Err("template was not specied".to_owned()),
+        (_, None) => Err("file was not specied".to_owned()),
+    }
+}
+
+fn get_audit_options(
+    args_iter: &mut std::slice::Iter<'_, commandline::Arg<'_>>,
+    print_opts: &mut PrintOptions,
+) -> Result<AuditOptions, String> {
+    let mut files : Vec<OsString> = vec![];
+    let mut env : Vec<OsString> = vec![];
+    let mut all = false;
+
+    while let Some(arg) = args_iter.next() {
+        match arg.arg.as_os_str() {
+            x if parse_print_opt(x, print_opts) => {},
+            x if x == OsStr::new("--env") => {
+                env.push(next_value(args_iter, arg)?.arg.clone());
+            },
+            x if x == OsStr::new("--all") => {
+                all = true;
+            },
+            x if x.to_string_lossy().starts_with("--") => {
+                return Err(format!("unknown option:\n  {}", &arg));
+            },
+            _ => {
+                files.push(arg.arg.clone());
+            },
+        }
+    }
+    if files.is_empty() {
+        return Err("no file was specied".to_owned());
+    }
+    Ok(AuditOptions{ files, env, all })
+}
+
+fn get_shortcut_options(
+    args_iter: &mut std::slice::Iter<'_, commandline::Arg<'_>>,
+    print_opts: &mut PrintOptions,
+) -> Result<ShortcutOptions, String> {
+    let mut file : Option<OsString> = None;
+    let mut code_page : u32 = 1252;
+    let mut env : Vec<OsString> = vec![];
+
+    while let Some(arg) = args_iter.next() {
+        match arg.arg.as_os_str() {
+            x if parse_print_opt(x, print_opts) => {},
+            x if x == OsStr::new("--code-page") => {
+                code_page = parse_number(arg, next_value(args_iter, arg)?)?;
+            },
+            x if x == OsStr::new("--env") => {
+                env.push(next_value(args_iter, arg)?.arg.clone());
+            },
+            _ if file.is_none() => {
+                file = Some(arg.arg.clone());
+            },
+            _other => {
+                return Err(format!("unknown option:\n  {}", &arg));
+            }
+        }
+    }
+    match file {
+        Some(file) => Ok(ShortcutOptions{ file, code_page, env }),
+        None => Err("file was not specied".to_owned()),
+    }
+}
+
+fn get_events_options(
+    args_iter: &mut std::slice::Iter<'_, commandline::Arg<'_>>,
+    print_opts: &mut PrintOptions,
+) -> Result<EventsOptions, String> {
+    let mut files : Vec<OsString> = vec![];
+    let mut only_anomalies = false;
+
+    for arg in args_iter {
+        match arg.arg.as_os_str() {
+            x if parse_print_opt(x, print_opts) => {},
+            x if x == OsStr::new("--only-anomalies") => {
+                only_anomalies = true;
+            },
+            x if x.to_string_lossy().starts_with("--") => {
+                return Err(format!("unknown option:\n  {}", &arg));
+            },
+            _ => {
+                files.push(arg.arg.clone());
+            },
+        }
+    }
+    if files.is_empty() {
+        return Err("no file was specied".to_owned());
+    }
+    Ok(EventsOptions{ files, only_anomalies })
+}
+
+fn get_template_options(
+    args_iter: &mut std::slice::Iter<'_, commandline::Arg<'_>>,
+    print_opts: &mut PrintOptions,
+) -> Result<TemplateOptions, String> {
+    let mut template : Option<TemplateOpt> = None;
+    let mut values : Vec<(String, OsString)> = vec![];
+    let mut quoting : QuotingStyle = QuotingStyle::Crt;
+
+    let placeholder_name = |arg: &commandline::Arg<'_>, name_arg: &commandline::Arg<'_>| -> Result<String, String> {
+        name_arg.arg.to_str()
+            .map(str::to_owned)
+            .ok_or_else(|| format!("bad argument for the following option: cannot convert to UTF-8\n {}\nbad argument:\n {}", arg, name_arg))
+    };
+
+    while let Some(arg) = args_iter.next() {
+        match arg.arg.as_os_str() {
+            x if parse_print_opt(x, print_opts) => {},
+            x if x == OsStr::new("--template") || x == OsStr::new("--template-from-json") => {
+                if template.is_some() {
+                    return Err(format!("bad option, template is already initilaized:\n  {}", &arg));
+                }
+                let next_arg = next_value(args_iter, arg)?.arg.clone();
+                template = Some(if x == OsStr::new("--template") { TemplateOpt::Str(next_arg) } else { TemplateOpt::FromJSONFile(next_arg) });
+            },
+            x if x == OsStr::new("--quoting") => {
+                let next_arg = next_value(args_iter, arg)?;
+                quoting = next_arg.arg.to_str()
+                    .and_then(crate::template::parse_quoting_style)
+                    .ok_or_else(|| format!("bad argument for the following option: unknown quoting style\n {}\nbad argument:\n {}", &arg, &next_arg))?;
+            },
+            x if x == OsStr::new("--value") => {
+                let name = placeholder_name(arg, next_value(args_iter, arg)?)?;
+                values.push((name, next_value(args_iter, arg)?.arg.clone()));
+            },
+            x if x == OsStr::new("--values-are-rest") => {
+                let name = placeholder_name(arg, next_value(args_iter, arg)?)?;
+                // all args get consumed
+                values.extend(args_iter.by_ref().map(|a| (name.clone(), a.arg.clone())));
+            },
+            _other => {
+                return Err(format!("unknown option:\n  {}", &arg));
+            }
+        }
+    }
+    match template {
+        Some(template) => Ok(TemplateOptions{ template, values, quoting }),
+        None => Err("template was not specified".to_owned()),
+    }
+}
+
+fn get_edit_options<'a>(
+    cmd_line: &'a [u16],
+    args_iter: &mut std::slice::Iter<'_, commandline::Arg<'a>>,
+    print_opts: &mut PrintOptions,
+) -> Result<EditOptions, String> {
+    let mut cmdline_opt : Option<CmdlineOpt> = None;
+    let mut edits : Vec<CmdlineEdit> = vec![];
+
+    while let Some(arg) = args_iter.next() {
+        match arg.arg.as_os_str() {
+            x if parse_print_opt(x, print_opts) => {},
+            x if parse_cmdline_opt(x, arg, cmd_line, args_iter, &mut cmdline_opt)? => {},
+            x if x == OsStr::new("--insert") => {
+                let index = parse_number(arg, next_value(args_iter, arg)?)?;
+                edits.push(CmdlineEdit::Insert(index, next_value(args_iter, arg)?.arg.clone()));
+            },
+            x if x == OsStr::new("--delete") => {
+                edits.push(CmdlineEdit::Delete(parse_number(arg, next_value(args_iter, arg)?)?));
+            },
+            x if x == OsStr::new("--replace") => {
+                let index = parse_number(arg, next_value(args_iter, arg)?)?;
+                edits.push(CmdlineEdit::Replace(index, next_value(args_iter, arg)?.arg.clone()));
+            },
+            x if x == OsStr::new("--insert-before") => {
+                let matching = next_value(args_iter, arg)?.arg.clone();
+                edits.push(CmdlineEdit::InsertBefore(matching, next_value(args_iter, arg)?.arg.clone()));
+            },
+            x if x == OsStr::new("--insert-after") => {
+                let matching = next_value(args_iter, arg)?.arg.clone();
+                edits.push(CmdlineEdit::InsertAfter(matching, next_value(args_iter, arg)?.arg.clone()));
+            },
+            _other => {
+                return Err(format!("unknown option:\n  {}", &arg));
+            }
+        }
+    }
+    match cmdline_opt {
+        Some(cmdline) => Ok(EditOptions{ cmdline, edits }),
+        None => Err("cmd line was not specied".to_owned()),
+    }
+}
+
+pub(super) fn get_options(cmd_line : &[u16], args: &Vec<crate::commandline::Arg>) -> Result<MainOptions,String> {
+    let mut args_iter = args.iter();
+    let mut print_opts = PrintOptions{
+        print_args: false,
+        json: false,
+        silent: false,
+        confusables: false,
+    };
+
+    // skip first/zerothed argument
+    if let None = args_iter.next() {
+        print_opts.print_args = true;
+        return Ok( MainOptions{ print_opts, main_choice: MainChoice::PrintArgs, });
+    }
+
+    let opt_program : &OsStr = OsStr::new("--program");
     let opt_program_utf16le_base64 : &OsStr = OsStr::new("--program-utf16le-base64");
     let opt_program_from_cmd_line : &OsStr = OsStr::new("--program-from-cmd-line");
     let opt_program_is_null : &OsStr = OsStr::new("--program-is-null");
     let opt_cmd_line_in_arg : &OsStr = OsStr::new("--cmd-line-in-arg");
-    let opt_cmd_line_utf16le_base64 : &OsStr = OsStr::new("--cmd-line-utf16le-base64");
-    let opt_cmd_line_is_rest : &OsStr = OsStr::new("--cmd-line-is-rest");
     let opt_cmd_line_is_null : &OsStr = OsStr::new("--cmd-line-is-null");
     let opt_prepend_program : &OsStr = OsStr::new("--prepend-program");
     let opt_strip_program : &OsStr = OsStr::new("--strip-program");
@@ -228,9 +1544,36 @@ pub(super) fn get_options(cmd_line : &[u16], args: &Vec<crate::commandline::Arg>
     ];
     let opt_print_args : &OsStr = OsStr::new("--print-args");
     let opt_print_args_only : &OsStr = OsStr::new("--print-args-only");
+    let opt_generate_variants : &OsStr = OsStr::new("--generate-variants");
+    let opt_generate_invocations : &OsStr = OsStr::new("--generate-invocations");
+    let opt_batbadbut : &OsStr = OsStr::new("--batbadbut");
+    let opt_narrow_view : &OsStr = OsStr::new("--narrow-view");
+    let opt_worstfit : &OsStr = OsStr::new("--worstfit");
+    let opt_split_args : &OsStr = OsStr::new("--split-args");
+    let opt_explain : &OsStr = OsStr::new("--explain");
+    let opt_diff : &OsStr = OsStr::new("--diff");
+    let opt_compare_parsers : &OsStr = OsStr::new("--compare-parsers");
+    let opt_resolve_command : &OsStr = OsStr::new("--resolve-command");
+    let opt_full_path_name : &OsStr = OsStr::new("--full-path-name");
+    let opt_convert_path : &OsStr = OsStr::new("--convert-path");
+    let opt_expand_shell_command : &OsStr = OsStr::new("--expand-shell-command");
+    let opt_audit_paths : &OsStr = OsStr::new("--audit-paths");
+    let opt_read_shortcut : &OsStr = OsStr::new("--read-shortcut");
+    let opt_split_events : &OsStr = OsStr::new("--split-events");
+    let opt_fill_template : &OsStr = OsStr::new("--fill-template");
+    let opt_edit_cmd_line : &OsStr = OsStr::new("--edit-cmd-line");
     let opt_dry_run : &OsStr = OsStr::new("--dry-run");
+    let opt_export : &OsStr = OsStr::new("--export");
+    let opt_lint : &OsStr = OsStr::new("--lint");
+    let opt_virtual_fs : &OsStr = OsStr::new("--virtual-fs");
+    let opt_convert_program : &OsStr = OsStr::new("--convert-program");
+    let opt_env : &OsStr = OsStr::new("--env");
+    let opt_env_unset : &OsStr = OsStr::new("--env-unset");
+    let opt_env_clear : &OsStr = OsStr::new("--env-clear");
+    let opt_env_from_json : &OsStr = OsStr::new("--env-from-json");
     let opt_json : &OsStr = OsStr::new("--json");
     let opt_silent : &OsStr = OsStr::new("--silent");
+    let opt_confusables : &OsStr = OsStr::new("--confusables");
 
     let mut program : Option<ProgramOpt> = None;
     let mut cmdline_opt : Option<CmdlineOpt> = None;
@@ -238,6 +1581,12 @@ pub(super) fn get_options(cmd_line : &[u16], args: &Vec<crate::commandline::Arg>
     let mut strip_program : bool = false;
     let mut dry_run : bool = false;
     let mut split_and_print_inner_cmdline = false;
+    let mut export : Option<ExportLanguage> = None;
+    let mut lint : Option<LintFormat> = None;
+    let mut virtual_fs : Option<OsString> = None;
+    let mut convert_program = false;
+    let mut mounts : Vec<(OsString, OsString)> = vec![];
+    let mut env : Vec<EnvOpt> = vec![];
 
     let mut only_print_opts_thus_far = true;
     while let Some(arg) = args_iter.next() {
@@ -254,6 +1603,44 @@ pub(super) fn get_options(cmd_line : &[u16], args: &Vec<crate::commandline::Arg>
             x if x == opt_split_and_print_inner_cmdline => {
                 split_and_print_inner_cmdline = true;
             },
+            x if x == opt_export => {
+                let next_arg = next_value(&mut args_iter, arg)?;
+                export = Some(match next_arg.arg.to_str() {
+                    Some("rust") => ExportLanguage::Rust,
+                    Some("c") => ExportLanguage::C,
+                    Some("python") => ExportLanguage::Python,
+                    Some("powershell") => ExportLanguage::PowerShell,
+                    _ => return Err(format!("bad argument for the following option: unknown language\n {}\nbad argument:\n {}", &arg, &next_arg)),
+                });
+            },
+            x if x == opt_lint => {
+                let next_arg = next_value(&mut args_iter, arg)?;
+                lint = Some(match next_arg.arg.to_str() {
+                    Some("text") => LintFormat::Text,
+                    Some("json") => LintFormat::Json,
+                    Some("sarif") => LintFormat::Sarif,
+                    _ => return Err(format!("bad argument for the following option: unknown format\n {}\nbad argument:\n {}", &arg, &next_arg)),
+                });
+            },
+            x if x == opt_virtual_fs => {
+                virtual_fs = Some(next_value(&mut args_iter, arg)?.arg.clone());
+            },
+            x if x == opt_convert_program => {
+                convert_program = true;
+            },
+            x if parse_mount_opt(x, arg, &mut args_iter, &mut mounts)? => {},
+            x if x == opt_env => {
+                env.push(EnvOpt::Set(next_value(&mut args_iter, arg)?.arg.clone()));
+            },
+            x if x == opt_env_unset => {
+                env.push(EnvOpt::Unset(next_value(&mut args_iter, arg)?.arg.clone()));
+            },
+            x if x == opt_env_clear => {
+                env.push(EnvOpt::Clear);
+            },
+            x if x == opt_env_from_json => {
+                env.push(EnvOpt::FromJSONFile(next_value(&mut args_iter, arg)?.arg.clone()));
+            },
             x if x == opt_print_args => {
                 print_opts.print_args = true;
                 continue; // skip setting only_print_opts_thus_far to false
@@ -266,6 +1653,10 @@ pub(super) fn get_options(cmd_line : &[u16], args: &Vec<crate::commandline::Arg>
                 print_opts.silent = true;
                 continue; // skip setting only_print_opts_thus_far to false
             },
+            x if x == opt_confusables => {
+                print_opts.confusables = true;
+                continue; // skip setting only_print_opts_thus_far to false
+            },
             x if x == opt_print_args_only => {
                 return if only_print_opts_thus_far {
                     print_opts.print_args = true;
@@ -276,6 +1667,97 @@ pub(super) fn get_options(cmd_line : &[u16], args: &Vec<crate::commandline::Arg>
                                 &arg))
                 };
             }
+            x if x == opt_generate_variants => {
+                require_leading_print_opts(opt_generate_variants, arg, only_print_opts_thus_far)?;
+                let variants_opts = get_variants_options(&mut args_iter, &mut print_opts)?;
+                return Ok( MainOptions{ print_opts, main_choice: MainChoice::GenerateVariants(variants_opts), });
+            },
+            x if x == opt_generate_invocations => {
+                require_leading_print_opts(opt_generate_invocations, arg, only_print_opts_thus_far)?;
+                let (target, program) = get_target_options(cmd_line, &mut args_iter, &mut print_opts)?;
+                let invocations_opts = InvocationsOptions{ target, program };
+                return Ok( MainOptions{ print_opts, main_choice: MainChoice::GenerateInvocations(invocations_opts), });
+            },
+            x if x == opt_batbadbut => {
+                require_leading_print_opts(opt_batbadbut, arg, only_print_opts_thus_far)?;
+                let (target, program) = get_target_options(cmd_line, &mut args_iter, &mut print_opts)?;
+                return Ok( MainOptions{ print_opts, main_choice: MainChoice::BatBadBut(BatchOptions{ target, program }), });
+            },
+            x if x == opt_narrow_view => {
+                require_leading_print_opts(opt_narrow_view, arg, only_print_opts_thus_far)?;
+                let narrow_view_opts = get_narrow_view_options(cmd_line, &mut args_iter, &mut print_opts)?;
+                return Ok( MainOptions{ print_opts, main_choice: MainChoice::NarrowView(narrow_view_opts), });
+            },
+            x if x == opt_worstfit => {
+                require_leading_print_opts(opt_worstfit, arg, only_print_opts_thus_far)?;
+                let worstfit_opts = get_worstfit_options(cmd_line, &mut args_iter, &mut print_opts)?;
+                return Ok( MainOptions{ print_opts, main_choice: MainChoice::WorstFit(worstfit_opts), });
+            },
+            x if x == opt_split_args => {
+                require_leading_print_opts(opt_split_args, arg, only_print_opts_thus_far)?;
+                let split_args_opts = get_split_args_options(cmd_line, &mut args_iter, &mut print_opts)?;
+                return Ok( MainOptions{ print_opts, main_choice: MainChoice::SplitArgs(split_args_opts), });
+            },
+            x if x == opt_explain => {
+                require_leading_print_opts(opt_explain, arg, only_print_opts_thus_far)?;
+                let explain_opts = get_explain_options(cmd_line, &mut args_iter, &mut print_opts)?;
+                return Ok( MainOptions{ print_opts, main_choice: MainChoice::Explain(explain_opts), });
+            },
+            x if x == opt_diff => {
+                require_leading_print_opts(opt_diff, arg, only_print_opts_thus_far)?;
+                let diff_opts = get_diff_options(cmd_line, &mut args_iter, &mut print_opts)?;
+                return Ok( MainOptions{ print_opts, main_choice: MainChoice::Diff(diff_opts), });
+            },
+            x if x == opt_compare_parsers => {
+                require_leading_print_opts(opt_compare_parsers, arg, only_print_opts_thus_far)?;
+                let compare_opts = get_compare_parsers_options(cmd_line, &mut args_iter, &mut print_opts)?;
+                return Ok( MainOptions{ print_opts, main_choice: MainChoice::CompareParsers(compare_opts), });
+            },
+            x if x == opt_resolve_command => {
+                require_leading_print_opts(opt_resolve_command, arg, only_print_opts_thus_far)?;
+                let resolve_opts = get_resolve_command_options(&mut args_iter, &mut print_opts)?;
+                return Ok( MainOptions{ print_opts, main_choice: MainChoice::ResolveCommand(resolve_opts), });
+            },
+            x if x == opt_full_path_name => {
+                require_leading_print_opts(opt_full_path_name, arg, only_print_opts_thus_far)?;
+                let full_path_opts = get_full_path_options(&mut args_iter, &mut print_opts)?;
+                return Ok( MainOptions{ print_opts, main_choice: MainChoice::FullPathName(full_path_opts), });
+            },
+            x if x == opt_convert_path => {
+                require_leading_print_opts(opt_convert_path, arg, only_print_opts_thus_far)?;
+                let convert_opts = get_convert_path_options(&mut args_iter, &mut print_opts)?;
+                return Ok( MainOptions{ print_opts, main_choice: MainChoice::ConvertPath(convert_opts), });
+            },
+            x if x == opt_expand_shell_command => {
+                require_leading_print_opts(opt_expand_shell_command, arg, only_print_opts_thus_far)?;
+                let shell_command_opts = get_shell_command_options(&mut args_iter, &mut print_opts)?;
+                return Ok( MainOptions{ print_opts, main_choice: MainChoice::ExpandShellCommand(shell_command_opts), });
+            },
+            x if x == opt_audit_paths => {
+                require_leading_print_opts(opt_audit_paths, arg, only_print_opts_thus_far)?;
+                let audit_opts = get_audit_options(&mut args_iter, &mut print_opts)?;
+                return Ok( MainOptions{ print_opts, main_choice: MainChoice::AuditPaths(audit_opts), });
+            },
+            x if x == opt_read_shortcut => {
+                require_leading_print_opts(opt_read_shortcut, arg, only_print_opts_thus_far)?;
+                let shortcut_opts = get_shortcut_options(&mut args_iter, &mut print_opts)?;
+                return Ok( MainOptions{ print_opts, main_choice: MainChoice::ReadShortcut(shortcut_opts), });
+            },
+            x if x == opt_split_events => {
+                require_leading_print_opts(opt_split_events, arg, only_print_opts_thus_far)?;
+                let events_opts = get_events_options(&mut args_iter, &mut print_opts)?;
+                return Ok( MainOptions{ print_opts, main_choice: MainChoice::SplitEvents(events_opts), });
+            },
+            x if x == opt_fill_template => {
+                require_leading_print_opts(opt_fill_template, arg, only_print_opts_thus_far)?;
+                let template_opts = get_template_options(&mut args_iter, &mut print_opts)?;
+                return Ok( MainOptions{ print_opts, main_choice: MainChoice::FillTemplate(template_opts), });
+            },
+            x if x == opt_edit_cmd_line => {
+                require_leading_print_opts(opt_edit_cmd_line, arg, only_print_opts_thus_far)?;
+                let edit_opts = get_edit_options(cmd_line, &mut args_iter, &mut print_opts)?;
+                return Ok( MainOptions{ print_opts, main_choice: MainChoice::EditCmdline(edit_opts), });
+            },
             x if opts_help.contains(&x) => {
                 return Ok( MainOptions{ print_opts, main_choice: MainChoice::Help, });
             },
@@ -314,36 +1796,7 @@ pub(super) fn get_options(cmd_line : &[u16], args: &Vec<crate::commandline::Arg>
                 }
                 program = Some(ProgramOpt::Null);
             },
-            x if x == opt_cmd_line_in_arg => {
-                if cmdline_opt.is_some() {
-                    return Err(format!("bad option, cmd line is already initilaized:\n  {}", &arg));
-                }
-                match args_iter.next() {
-                    Some(next_arg) => cmdline_opt = Some(CmdlineOpt::Str(next_arg.arg.clone())),
-                    None => return Err(format!("missing argument for option:\n  {}", &arg)),
-                }
-            },
-            x if x == opt_cmd_line_utf16le_base64 => {
-                if cmdline_opt.is_some() {
-                    return Err(format!("bad option, cmd line is already initilaized:\n  {}", &arg));
-                }
-                match args_iter.next() {
-                    Some(next_arg) => {
-                        match decode_utf16le_base64(&next_arg.arg) {
-                            Ok(p) => cmdline_opt = Some(CmdlineOpt::Str(p)),
-                            Err(err_str) => return Err(format!("bad argument for the following option: {}\n {}\nbad argument:\n {}", &err_str, &arg, &next_arg)),
-                        }
-                    },
-                    None => return Err(format!("missing argument for option:\n  {}", &arg)),
-                }
-            },
-            x if x == opt_cmd_line_is_rest => {
-                if cmdline_opt.is_some() {
-                    return Err(format!("bad option, cmd line is already initilaized:\n  {}", &arg));
-                }
-                cmdline_opt = Some(CmdlineOpt::Str(OsString::from_wide(commandline::get_rest(cmd_line, arg))));
-                break; // break, because all args get consumed
-            },
+            x if parse_cmdline_opt(x, arg, cmd_line, &mut args_iter, &mut cmdline_opt)? => {},
             x if x == opt_cmd_line_is_null => {
                 if cmdline_opt.is_some() {
                     return Err(format!("bad option, cmd line is already initilaized:\n  {}", &arg));
@@ -368,7 +1821,8 @@ pub(super) fn get_options(cmd_line : &[u16], args: &Vec<crate::commandline::Arg>
                     print_opts,
                     main_choice : MainChoice::ExecOpts(
                         ExecOptions{ program, cmdline, prepend_program,
-                                     strip_program, dry_run, split_and_print_inner_cmdline }
+                                     strip_program, dry_run, split_and_print_inner_cmdline, export, lint, virtual_fs,
+                                     convert_program, mounts, env }
                     )
                 }
             ),
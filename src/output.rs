@@ -13,10 +13,18 @@ use serde::{
 };
 
 
-use crate::commandline::Arg;
+use crate::commandline::{
+    Arg,
+    ArgInfo,
+    TraceStep,
+};
+use crate::confusables::{
+    find_confusables,
+    Confusable,
+};
 
 impl<'lifetime_of_slice> Arg<'lifetime_of_slice> {
-    fn write_pretty_json_to_writer<W>(self: &Self, mut writer: &mut W, indent: &str) -> io::Result<()>
+    fn write_pretty_json_to_writer<W>(self: &Self, mut writer: &mut W, indent: &str, confusables: Option<&[Confusable]>) -> io::Result<()>
     where
         W: io::Write + ?Sized,
     {
@@ -69,8 +77,8 @@ impl<'lifetime_of_slice> Arg<'lifetime_of_slice> {
 {indent}  \"arg-lossy\": {},
 {indent}  \"raw-lossy\": {},
 {indent}  \"raw-start\": {},
-{indent}  \"raw-end\": {}
-{indent}}}",
+{indent}  \"raw-end\": {},
+{indent}  \"info\": {}",
                 &serde_json::json!(arg),
                 &serde_json::json!(raw),
                 &serde_json::json!(arg_vec),
@@ -79,8 +87,13 @@ impl<'lifetime_of_slice> Arg<'lifetime_of_slice> {
                 &serde_json::json!(raw_lossy),
                 &serde_json::json!(self.range.start),
                 &serde_json::json!(self.range.end),
+                arg_info_to_json(&self.info),
                 indent = &indent
             )?;
+            if let Some(confusables) = confusables {
+                write!(&mut writer, ",\n{indent}  \"confusables\": {}", confusables_to_json(confusables), indent = &indent)?;
+            }
+            write!(&mut writer, "\n{indent}}}", indent = &indent)?;
         }
 
         Ok(())
@@ -103,7 +116,7 @@ impl<'lifetime_of_slice> serde::Serialize for Arg<'lifetime_of_slice> {
         };
         //let arg_vec : Vec<u16> = self.arg.encode_wide().collect();
         // 3 is the number of fields in the struct.
-        let mut state = serializer.serialize_struct("Arg", 6)?;
+        let mut state = serializer.serialize_struct("Arg", 7)?;
         state.serialize_field("arg", &arg)?;
         state.serialize_field("arg-lossy", &arg_lossy)?;
         //state.serialize_field("arg-utf16", &arg_vec)?;
@@ -112,6 +125,7 @@ impl<'lifetime_of_slice> serde::Serialize for Arg<'lifetime_of_slice> {
         //state.serialize_field("raw-utf16", &self.raw)?;
         state.serialize_field("raw-start", &self.range.start)?;
         state.serialize_field("raw-end", &self.range.end)?;
+        state.serialize_field("info", &arg_info_to_json(&self.info))?;
         state.end()
     }
 }
@@ -173,7 +187,8 @@ where
             } else {
                 stdout.write_all(b",\n")?;
             }
-            x.write_pretty_json_to_writer(&mut stdout, &(indent.to_owned() + "    "))?;
+            let confusables = print_opts.confusables.then(|| find_confusables(x));
+            x.write_pretty_json_to_writer(&mut stdout, &(indent.to_owned() + "    "), confusables.as_deref())?;
         }
         write!(stdout,"\n{indent}  ]\n{indent}}}\n", indent = indent)?;
     }
@@ -186,17 +201,1338 @@ where
                      »{}«\n", cmdline_utf8)?;
         }
         let mut n : usize = 0;
-        for Arg {arg, range, raw, ..} in parsed_args_list {
+        for parsed_arg in parsed_args_list {
+            let Arg {arg, range, raw, ..} = parsed_arg;
             let (lossless_or_lossy, arg) = match arg.to_str() {
                 Some(arg) => ("lossless:", std::borrow::Cow::from(arg)),
                 None      => ("lossy:   ", arg.to_string_lossy()),
             };
             let raw = OsString::from_wide(raw);
             let raw = raw.to_string_lossy();
-            writeln!(&mut writer, "Argument {:2}, {:3} .. {:3}, {} »{}«, raw: »{}«",
-                     n, range.start, range.end, lossless_or_lossy, arg, raw)?;
+            let facts = describe_arg_info(&parsed_arg.info);
+            writeln!(&mut writer, "Argument {:2}, {:3} .. {:3}, {} »{}«, raw: »{}«{}",
+                     n, range.start, range.end, lossless_or_lossy, arg, raw,
+                     if facts.is_empty() { String::new() } else { format!(", {}", facts.join(", ")) })?;
+            if print_opts.confusables {
+                for c in find_confusables(parsed_arg) {
+                    writeln!(&mut writer, "  U+{:04X} {} at {:3} {}", c.code_point, c.name, c.position, c.category.describe())?;
+                }
+            }
             n += 1;
         }
     }
     Ok(())
 }
+
+/// The whitespace in front of the argument is given as its range in the command line and as text.
+fn arg_info_to_json(info: &ArgInfo) -> serde_json::Value {
+    serde_json::json!({
+        "quoted": info.quoted,
+        "escaped-quotes": info.escaped_quotes,
+        "doubled-quotes": info.doubled_quotes,
+        "unterminated": info.unterminated,
+        "arg-zero-form": info.arg_zero_form,
+        "whitespace-start": info.whitespace.start,
+        "whitespace-end": info.whitespace.end,
+        "whitespace-len": info.whitespace.len(),
+        "whitespace": String::from_utf16_lossy(&info.whitespace_units),
+        "backslashes-collapsed": info.backslashes_collapsed,
+    })
+}
+
+/// The facts about an argument, that are worth mentioning. A single space in front of the argument is not.
+/// Other whitespace is shown escaped, so that a tab can be told apart from a space.
+fn describe_arg_info(info: &ArgInfo) -> Vec<String> {
+    let mut facts : Vec<String> = vec![];
+    if info.arg_zero_form {
+        facts.push("arg zero form".to_owned());
+    }
+    if info.quoted {
+        facts.push("quoted".to_owned());
+    }
+    if info.unterminated {
+        facts.push("UNTERMINATED quote".to_owned());
+    }
+    if info.escaped_quotes > 0 {
+        facts.push(format!("escaped quotes: {}", info.escaped_quotes));
+    }
+    if info.doubled_quotes > 0 {
+        facts.push(format!("doubled quotes: {}", info.doubled_quotes));
+    }
+    if info.backslashes_collapsed > 0 {
+        facts.push(format!("backslashes collapsed: {}", info.backslashes_collapsed));
+    }
+    if !info.arg_zero_form && info.whitespace_units != [b' ' as u16] {
+        facts.push(format!("whitespace before: {} »{}«", info.whitespace.len(),
+                           String::from_utf16_lossy(&info.whitespace_units).escape_debug()));
+    }
+    facts
+}
+
+fn confusables_to_json(confusables: &[Confusable]) -> serde_json::Value {
+    serde_json::json!(confusables.iter().map(|c| serde_json::json!({
+        "position": c.position,
+        "code-point": format!("U+{:04X}", c.code_point),
+        "name": c.name,
+        "category": c.category.as_str(),
+    })).collect::<Vec<serde_json::Value>>())
+}
+
+/// Writes the command line in quotes, as the modes do before their results.
+fn write_cmdline_header<W>(cmdline: &[u16], writer: &mut W) -> io::Result<()>
+where
+    W: io::Write + ?Sized
+{
+    writeln!(writer, "The command line is put in quotes (»«). The command line is: \n»{}«\n", utf16_to_string_lossy(cmdline).1)
+}
+
+/// Converts UTF-16 to a string. The boolean is true, if the conversion was lossy.
+pub(super) fn utf16_to_string_lossy(units: &[u16]) -> (bool, String) {
+    match String::from_utf16(units) {
+        Ok(string) => (false, string),
+        Err(_) => (true, String::from_utf16_lossy(units)),
+    }
+}
+
+pub(super) fn print_variants<W>(
+    args: &[Vec<u16>],
+    variants: &[Vec<u16>],
+    print_opts: &crate::options::PrintOptions,
+    mut writer: &mut W
+) -> io::Result<()>
+where
+    W: io::Write + ?Sized
+{
+    if print_opts.json {
+        let json = serde_json::json!({
+            "args": args.iter().map(|a| utf16_to_string_lossy(a).1).collect::<Vec<String>>(),
+            "variants": variants.iter().map(|v| {
+                let (lossy, cmdline) = utf16_to_string_lossy(v);
+                serde_json::json!({
+                    "cmdline": cmdline,
+                    "cmdline-utf16": v,
+                    "cmdline-lossy": lossy,
+                })
+            }).collect::<Vec<serde_json::Value>>(),
+        });
+        serde_json::to_writer_pretty(&mut writer, &json)?;
+        writeln!(&mut writer)?;
+    }
+    else if print_opts.silent {
+        for variant in variants {
+            writeln!(&mut writer, "{}", utf16_to_string_lossy(variant).1)?;
+        }
+    }
+    else {
+        writeln!(&mut writer, "{} command lines, that are split into the same {} arguments. \
+                 Each command line is put in quotes (»«).\n", variants.len(), args.len())?;
+        for (n, variant) in variants.iter().enumerate() {
+            writeln!(&mut writer, "Variant {:3}: »{}«", n, utf16_to_string_lossy(variant).1)?;
+        }
+    }
+    Ok(())
+}
+
+pub(super) fn print_invocations<W>(
+    target: &[u16],
+    invocations: &[crate::invocation::Invocation],
+    print_opts: &crate::options::PrintOptions,
+    mut writer: &mut W
+) -> io::Result<()>
+where
+    W: io::Write + ?Sized
+{
+    if print_opts.json {
+        let (target_lossy, target_str) = utf16_to_string_lossy(target);
+        let json = serde_json::json!({
+            "cmdline": target_str,
+            "cmdline-utf16": target,
+            "cmdline-lossy": target_lossy,
+            "shells": invocations.iter().map(|invocation| serde_json::json!({
+                "shell": invocation.shell.name(),
+                "typed": invocation.typed,
+                "produced-cmdline": invocation.produced.as_deref().map(|p| utf16_to_string_lossy(p).1),
+                "outcome": invocation.outcome.as_str(),
+                "create-process-rust": invocation.create_process_rust,
+                "notes": invocation.notes,
+            })).collect::<Vec<serde_json::Value>>(),
+        });
+        serde_json::to_writer_pretty(&mut writer, &json)?;
+        writeln!(&mut writer)?;
+    }
+    else {
+        if !print_opts.silent {
+            write_cmdline_header(target, &mut writer)?;
+        }
+        for invocation in invocations {
+            writeln!(&mut writer, "{}:", invocation.shell.name())?;
+            match &invocation.typed {
+                Some(typed) => writeln!(&mut writer, "  type:                {}", typed)?,
+                None => writeln!(&mut writer, "  type:                -")?,
+            }
+            if let Some(produced) = &invocation.produced {
+                writeln!(&mut writer, "  command line:        »{}«", utf16_to_string_lossy(produced).1)?;
+            }
+            writeln!(&mut writer, "  result:              {}", invocation.outcome.as_str())?;
+            writeln!(&mut writer, "  create-process-rust: {}", invocation.create_process_rust)?;
+            if !print_opts.silent {
+                for note in &invocation.notes {
+                    writeln!(&mut writer, "  note: {}", note)?;
+                }
+            }
+            writeln!(&mut writer)?;
+        }
+    }
+    Ok(())
+}
+
+pub(super) fn print_snippet<W>(
+    snippet: &str,
+    notes: &[String],
+    print_opts: &crate::options::PrintOptions,
+    mut writer: &mut W
+) -> io::Result<()>
+where
+    W: io::Write + ?Sized
+{
+    if print_opts.json {
+        let json = serde_json::json!({
+            "code": snippet,
+            "notes": notes,
+        });
+        serde_json::to_writer_pretty(&mut writer, &json)?;
+        writeln!(&mut writer)?;
+    }
+    else {
+        write!(&mut writer, "{}", snippet)?;
+        if !print_opts.silent {
+            for note in notes {
+                eprintln!("Warning: {}", note);
+            }
+        }
+    }
+    Ok(())
+}
+
+pub(super) fn print_filled_template<W>(
+    filled: &crate::template::Filled,
+    print_opts: &crate::options::PrintOptions,
+    mut writer: &mut W
+) -> io::Result<()>
+where
+    W: io::Write + ?Sized
+{
+    let to_strings = |args: &[Vec<u16>]| args.iter().map(|a| utf16_to_string_lossy(a).1).collect::<Vec<String>>();
+    if print_opts.json {
+        let (cmdline_lossy, cmdline) = utf16_to_string_lossy(&filled.cmdline);
+        let json = serde_json::json!({
+            "cmdline": cmdline,
+            "cmdline-utf16": filled.cmdline,
+            "cmdline-lossy": cmdline_lossy,
+            "verified": filled.verified,
+            "placeholders": filled.checks.iter().map(|check| serde_json::json!({
+                "name": check.name,
+                "variadic": check.variadic,
+                "ok": check.ok,
+                "expected": to_strings(&check.expected),
+                "actual": to_strings(&check.actual),
+            })).collect::<Vec<serde_json::Value>>(),
+        });
+        serde_json::to_writer_pretty(&mut writer, &json)?;
+        writeln!(&mut writer)?;
+    }
+    else if print_opts.silent {
+        writeln!(&mut writer, "{}", utf16_to_string_lossy(&filled.cmdline).1)?;
+    }
+    else {
+        write_cmdline_header(&filled.cmdline, &mut writer)?;
+        for check in &filled.checks {
+            let name = if check.variadic { format!("{{{}...}}", check.name) } else { format!("{{{}}}", check.name) };
+            let quoted = |args: &[Vec<u16>]| to_strings(args).iter().map(|a| format!("»{}«", a)).collect::<Vec<String>>().join(" ");
+            if check.ok {
+                writeln!(&mut writer, "{:20} ok:       {}", name, quoted(&check.expected))?;
+            } else {
+                writeln!(&mut writer, "{:20} expected: {}", name, quoted(&check.expected))?;
+                writeln!(&mut writer, "{:20} actual:   {}", "", quoted(&check.actual))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub(super) fn print_lint_findings<W>(
+    cmdline: Option<&[u16]>,
+    findings: &[crate::lint::Finding],
+    format: crate::options::LintFormat,
+    mut writer: &mut W
+) -> io::Result<()>
+where
+    W: io::Write + ?Sized
+{
+    use crate::options::LintFormat;
+    let cmdline_str : Option<String> = cmdline.map(|c| utf16_to_string_lossy(c).1);
+    match format {
+        LintFormat::Json => {
+            let json = serde_json::json!({
+                "cmdline": cmdline_str,
+                "cmdline-utf16": cmdline,
+                "findings": findings.iter().map(|f| serde_json::json!({
+                    "rule-id": f.rule.id,
+                    "rule": f.rule.name,
+                    "level": f.level.as_str(),
+                    "message": f.message,
+                    "start": f.range.as_ref().map(|r| r.start),
+                    "end": f.range.as_ref().map(|r| r.end),
+                })).collect::<Vec<serde_json::Value>>(),
+            });
+            serde_json::to_writer_pretty(&mut writer, &json)?;
+            writeln!(&mut writer)?;
+        },
+        LintFormat::Sarif => {
+            let json = serde_json::json!({
+                "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+                "version": "2.1.0",
+                "runs": [{
+                    "tool": {
+                        "driver": {
+                            "name": "create-process-rust",
+                            "version": env!("GIT_HASH"),
+                            "informationUri": "https://github.com/j0le/create-process-rust",
+                            "rules": crate::lint::RULES.iter().map(|rule| serde_json::json!({
+                                "id": rule.id,
+                                "name": rule.name,
+                                "shortDescription": { "text": rule.description },
+                                "defaultConfiguration": { "level": rule.level.as_str() },
+                            })).collect::<Vec<serde_json::Value>>(),
+                        },
+                    },
+                    // The command line is not a file, so it is embedded as the only artifact.
+                    // Offsets and lengths are counted in UTF-16 code units.
+                    // The contents are left out, if the command line is NULL or not valid UTF-16.
+                    "columnKind": "utf16CodeUnits",
+                    "artifacts": [match cmdline.and_then(|c| String::from_utf16(c).ok()) {
+                        Some(text) => serde_json::json!({ "location": { "uri": "cmdline" }, "contents": { "text": text } }),
+                        None => serde_json::json!({ "location": { "uri": "cmdline" } }),
+                    }],
+                    "results": findings.iter().map(|f| {
+                        let mut result = serde_json::json!({
+                            "ruleId": f.rule.id,
+                            "ruleIndex": crate::lint::RULES.iter().position(|rule| rule.id == f.rule.id),
+                            "level": f.level.as_str(),
+                            "message": { "text": f.message },
+                        });
+                        if let Some(range) = &f.range {
+                            result["locations"] = serde_json::json!([{
+                                "physicalLocation": {
+                                    "artifactLocation": { "uri": "cmdline", "index": 0 },
+                                    "region": { "charOffset": range.start, "charLength": range.end - range.start },
+                                },
+                            }]);
+                        }
+                        result
+                    }).collect::<Vec<serde_json::Value>>(),
+                }],
+            });
+            serde_json::to_writer_pretty(&mut writer, &json)?;
+            writeln!(&mut writer)?;
+        },
+        LintFormat::Text => {
+            match cmdline {
+                Some(cmdline) => write_cmdline_header(cmdline, &mut writer)?,
+                None => writeln!(&mut writer, "The command line is NULL.\n")?,
+            }
+            for f in findings {
+                let range = f.range.as_ref().map_or("-".to_owned(), |r| format!("{}..{}", r.start, r.end));
+                writeln!(&mut writer, "{:7} {} {:26} {:>11}  {}", f.level.as_str(), f.rule.id, f.rule.name, range, f.message)?;
+            }
+            writeln!(&mut writer, "\n{} findings", findings.len())?;
+        },
+    }
+    Ok(())
+}
+
+pub(super) fn print_batch_report<W>(
+    report: &crate::batbadbut::BatchReport,
+    print_opts: &crate::options::PrintOptions,
+    mut writer: &mut W
+) -> io::Result<()>
+where
+    W: io::Write + ?Sized
+{
+    if print_opts.json {
+        let (cmdline_lossy, cmdline) = utf16_to_string_lossy(&report.cmdline);
+        let issues_to_json = |issues: &[crate::batbadbut::Issue]| issues.iter().map(|issue| serde_json::json!({
+            "kind": issue.kind.as_str(),
+            "unsafe": issue.kind.is_unsafe(),
+            "position": issue.position,
+            "description": issue.kind.describe(),
+        })).collect::<Vec<serde_json::Value>>();
+        let json = serde_json::json!({
+            "cmdline": cmdline,
+            "cmdline-utf16": report.cmdline,
+            "cmdline-lossy": cmdline_lossy,
+            "cmd-cmdline": utf16_to_string_lossy(&report.cmd_cmdline).1,
+            "safe": report.safe,
+            "args": report.args.iter().map(|arg| serde_json::json!({
+                "number": arg.number,
+                "arg": utf16_to_string_lossy(&arg.arg).1,
+                "raw-start": arg.range.start,
+                "raw-end": arg.range.end,
+                "issues": issues_to_json(&arg.issues),
+            })).collect::<Vec<serde_json::Value>>(),
+            "other-issues": issues_to_json(&report.other_issues),
+            "injected-commands": report.injected_commands.iter().map(|c| utf16_to_string_lossy(c).1).collect::<Vec<String>>(),
+            "reproduction": report.reproduction,
+        });
+        serde_json::to_writer_pretty(&mut writer, &json)?;
+        writeln!(&mut writer)?;
+    }
+    else {
+        if !print_opts.silent {
+            write_cmdline_header(&report.cmdline, &mut writer)?;
+            writeln!(&mut writer, "cmd.exe runs the batch file with this command line:\n»{}«\n",
+                     utf16_to_string_lossy(&report.cmd_cmdline).1)?;
+            let describe_issue = |issue: &crate::batbadbut::Issue| -> String {
+                let character = char::decode_utf16([report.cmdline[issue.position]]).next()
+                    .and_then(|c| c.ok())
+                    .map_or("?".to_owned(), |c| if c.is_control() { c.escape_debug().to_string() } else { c.to_string() });
+                format!("  »{}« at {:3} {}", character, issue.position, issue.kind.describe())
+            };
+            for arg in &report.args {
+                writeln!(&mut writer, "Argument {:2}, {:3} .. {:3}, »{}«: {}", arg.number, arg.range.start, arg.range.end,
+                         utf16_to_string_lossy(&arg.arg).1,
+                         if arg.issues.iter().any(|i| i.kind.is_unsafe()) { "unsafe" } else if arg.issues.is_empty() { "ok" } else { "changed" })?;
+                for issue in &arg.issues {
+                    writeln!(&mut writer, "{}", describe_issue(issue))?;
+                }
+            }
+            if !report.other_issues.is_empty() {
+                writeln!(&mut writer, "In argument zero or between the arguments:")?;
+                for issue in &report.other_issues {
+                    writeln!(&mut writer, "{}", describe_issue(issue))?;
+                }
+            }
+            writeln!(&mut writer)?;
+        }
+        for command in &report.injected_commands {
+            writeln!(&mut writer, "Injected command: »{}«", utf16_to_string_lossy(command).1)?;
+        }
+        writeln!(&mut writer, "Verdict: {}", if report.safe { "safe" } else { "UNSAFE" })?;
+        writeln!(&mut writer, "Reproduction: {}", report.reproduction)?;
+    }
+    Ok(())
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect::<Vec<String>>().join(" ")
+}
+
+pub(super) fn print_narrow_view<W>(
+    cmdline: &[u16],
+    narrow: &crate::codepage::Narrow,
+    code_page: u32,
+    best_fit: bool,
+    default_char: u8,
+    print_opts: &crate::options::PrintOptions,
+    mut writer: &mut W
+) -> Result<(), String>
+where
+    W: io::Write + ?Sized
+{
+    let decode = |bytes: &[u8]| -> Result<String, String> {
+        Ok(String::from_utf16_lossy(&crate::codepage::multibyte_to_wide(code_page, bytes)?))
+    };
+    // The C-Runtime splits the result of `GetCommandLineA` for `argv`.
+    let argv = crate::codepage::parse_narrow_cmd_line(code_page, &narrow.bytes);
+    let write_error = |error: io::Error| error.to_string();
+    if print_opts.json {
+        let json = serde_json::json!({
+            "cmdline": utf16_to_string_lossy(cmdline).1,
+            "cmdline-utf16": cmdline,
+            "code-page": code_page,
+            "best-fit": best_fit,
+            "best-fit-approximate": best_fit && crate::codepage::is_best_fit_approximate(code_page),
+            "default-char": char::from(default_char).to_string(),
+            "ansi-cmdline": decode(&narrow.bytes)?,
+            "ansi-cmdline-bytes": narrow.bytes,
+            "substitutions": narrow.substitutions.iter().map(|s| serde_json::json!({
+                "position": s.position,
+                "utf16": s.units,
+                "bytes": s.bytes,
+                "kind": s.kind.as_str(),
+            })).collect::<Vec<serde_json::Value>>(),
+            "argv": argv.iter().map(|arg| Ok(serde_json::json!({
+                "arg": decode(arg)?,
+                "bytes": arg,
+            }))).collect::<Result<Vec<serde_json::Value>, String>>()?,
+        });
+        serde_json::to_writer_pretty(&mut writer, &json).map_err(|error| error.to_string())?;
+        writeln!(&mut writer).map_err(write_error)?;
+    }
+    else {
+        if !print_opts.silent {
+            write_cmdline_header(cmdline, &mut writer).map_err(write_error)?;
+            writeln!(&mut writer, "Code page {}, best fit {}, default character »{}«\n", code_page,
+                     match (best_fit, crate::codepage::is_best_fit_approximate(code_page)) {
+                         (false, _) => "off",
+                         (true, false) => "on",
+                         (true, true) => "on (approximate)",
+                     }, char::from(default_char)).map_err(write_error)?;
+        }
+        writeln!(&mut writer, "GetCommandLineA: »{}«\n  bytes: {}\n", decode(&narrow.bytes)?, hex_bytes(&narrow.bytes)).map_err(write_error)?;
+        for s in &narrow.substitutions {
+            let units = s.units.iter().map(|u| format!("U+{:04X}", u)).collect::<Vec<String>>().join(" ");
+            writeln!(&mut writer, "Substitution at {:3}: »{}« {} -> {} ({})",
+                     s.position, String::from_utf16_lossy(&s.units), units, hex_bytes(&s.bytes), s.kind.as_str()).map_err(write_error)?;
+        }
+        if !narrow.substitutions.is_empty() {
+            writeln!(&mut writer).map_err(write_error)?;
+        }
+        for (n, arg) in argv.iter().enumerate() {
+            writeln!(&mut writer, "argv[{:2}]: »{}«, bytes: {}", n, decode(arg)?, hex_bytes(arg)).map_err(write_error)?;
+        }
+    }
+    Ok(())
+}
+
+pub(super) fn print_worstfit_report<W>(
+    report: &crate::worstfit::WorstFitReport,
+    print_opts: &crate::options::PrintOptions,
+    mut writer: &mut W
+) -> io::Result<()>
+where
+    W: io::Write + ?Sized
+{
+    let units_str = |units: &[u16]| units.iter().map(|u| format!("U+{:04X}", u)).collect::<Vec<String>>().join(" ");
+    if print_opts.json {
+        let (cmdline_lossy, cmdline) = utf16_to_string_lossy(&report.cmdline);
+        let json = serde_json::json!({
+            "cmdline": cmdline,
+            "cmdline-utf16": report.cmdline,
+            "cmdline-lossy": cmdline_lossy,
+            "code-page": report.code_page,
+            "best-fit-approximate": crate::codepage::is_best_fit_approximate(report.code_page),
+            "converted-cmdline": utf16_to_string_lossy(&report.converted).1,
+            "original-args": report.original_args.iter().map(|a| utf16_to_string_lossy(a).1).collect::<Vec<String>>(),
+            "converted-args": report.converted_args.iter().map(|a| utf16_to_string_lossy(a).1).collect::<Vec<String>>(),
+            "differences": report.differences.iter().map(|d| serde_json::json!({
+                "kind": d.kind.as_str(),
+                "number": d.number,
+                "raw-start": d.range.start,
+                "raw-end": d.range.end,
+                "converted-arg": utf16_to_string_lossy(&d.converted_arg).1,
+                "original-args": d.original_args.iter().map(|(number, arg)| serde_json::json!({
+                    "number": number,
+                    "arg": utf16_to_string_lossy(arg).1,
+                })).collect::<Vec<serde_json::Value>>(),
+                "characters": d.characters.iter().map(|c| serde_json::json!({
+                    "position": c.position,
+                    "character": String::from_utf16_lossy(&c.original),
+                    "utf16": c.original,
+                    "converted": String::from_utf8_lossy(&c.converted),
+                })).collect::<Vec<serde_json::Value>>(),
+            })).collect::<Vec<serde_json::Value>>(),
+        });
+        serde_json::to_writer_pretty(&mut writer, &json)?;
+        writeln!(&mut writer)?;
+    }
+    else {
+        if !print_opts.silent {
+            write_cmdline_header(&report.cmdline, &mut writer)?;
+            writeln!(&mut writer, "Converted to code page {} with {}best fit, the command line is:\n»{}«\n",
+                     report.code_page, if crate::codepage::is_best_fit_approximate(report.code_page) { "approximate " } else { "" },
+                     utf16_to_string_lossy(&report.converted).1)?;
+        }
+        for d in &report.differences {
+            let originals = d.original_args.iter()
+                .map(|(number, arg)| format!("{} »{}«", number, utf16_to_string_lossy(arg).1))
+                .collect::<Vec<String>>().join(", ");
+            writeln!(&mut writer, "Argument {:2}, {:3} .. {:3}, {}: »{}«, originally: {}", d.number, d.range.start, d.range.end,
+                     d.kind.as_str(), utf16_to_string_lossy(&d.converted_arg).1,
+                     if originals.is_empty() { "none".to_owned() } else { originals })?;
+            for c in &d.characters {
+                writeln!(&mut writer, "  »{}« {} at {:3} became »{}«", String::from_utf16_lossy(&c.original), units_str(&c.original),
+                         c.position, String::from_utf8_lossy(&c.converted))?;
+            }
+        }
+        writeln!(&mut writer, "Verdict: {}", if report.differences.is_empty() { "unchanged" } else { "CHANGED" })?;
+    }
+    Ok(())
+}
+
+pub(super) fn print_batches<W>(
+    prefix: &[u16],
+    batches: &[crate::batches::Batch],
+    limit: usize,
+    print_opts: &crate::options::PrintOptions,
+    mut writer: &mut W
+) -> io::Result<()>
+where
+    W: io::Write + ?Sized
+{
+    if print_opts.json {
+        let json = serde_json::json!({
+            "prefix": utf16_to_string_lossy(prefix).1,
+            "limit": limit,
+            "batches": batches.iter().map(|batch| {
+                let (cmdline_lossy, cmdline) = utf16_to_string_lossy(&batch.cmdline);
+                serde_json::json!({
+                    "cmdline": cmdline,
+                    "cmdline-utf16": batch.cmdline,
+                    "cmdline-lossy": cmdline_lossy,
+                    "length": batch.cmdline.len(),
+                    "first-arg": batch.args.start,
+                    "arg-count": batch.args.len(),
+                })
+            }).collect::<Vec<serde_json::Value>>(),
+        });
+        serde_json::to_writer_pretty(&mut writer, &json)?;
+        writeln!(&mut writer)?;
+    }
+    else if print_opts.silent {
+        for batch in batches {
+            writeln!(&mut writer, "{}", utf16_to_string_lossy(&batch.cmdline).1)?;
+        }
+    }
+    else {
+        writeln!(&mut writer, "The arguments are split into {} command lines of at most {} characters \
+                 including the terminating NUL character.\n", batches.len(), limit)?;
+        for (n, batch) in batches.iter().enumerate() {
+            writeln!(&mut writer, "Command line {}, {} characters, arguments {} .. {}:\n»{}«\n",
+                     n + 1, batch.cmdline.len(), batch.args.start, batch.args.end,
+                     utf16_to_string_lossy(&batch.cmdline).1)?;
+        }
+    }
+    Ok(())
+}
+
+/// Shows code units of the command line, so that whitespace and control characters are visible.
+fn visible_code_units(units: &[u16]) -> String {
+    String::from_utf16_lossy(units).chars()
+        .map(|c| if c.is_control() { c.escape_debug().to_string() } else { c.to_string() })
+        .collect()
+}
+
+pub(super) fn print_trace<W>(
+    cmdline: &[u16],
+    args: &[Arg<'_>],
+    steps: &[TraceStep],
+    print_opts: &crate::options::PrintOptions,
+    mut writer: &mut W
+) -> io::Result<()>
+where
+    W: io::Write + ?Sized
+{
+    if print_opts.json {
+        let (cmdline_lossy, cmdline_str) = utf16_to_string_lossy(cmdline);
+        let json = serde_json::json!({
+            "cmdline": cmdline_str,
+            "cmdline-utf16": cmdline,
+            "cmdline-lossy": cmdline_lossy,
+            "steps": steps.iter().enumerate().map(|(n, step)| serde_json::json!({
+                "step": n,
+                "start": step.range.start,
+                "end": step.range.end,
+                "code-units": utf16_to_string_lossy(&cmdline[step.range.clone()]).1,
+                "in-quotes-before": step.in_quotes_before,
+                "in-quotes-after": step.in_quotes_after,
+                "action": step.action.as_str(),
+                "backslash-run": step.backslashes.map(|(run, _)| run),
+                "backslashes-kept": step.backslashes.map(|(_, kept)| kept),
+                "arg-ended": step.arg_ended,
+            })).collect::<Vec<serde_json::Value>>(),
+            "args": args.iter().map(|arg| utf16_to_string_lossy(&arg.to_utf16()).1).collect::<Vec<String>>(),
+        });
+        serde_json::to_writer_pretty(&mut writer, &json)?;
+        writeln!(&mut writer)?;
+    }
+    else {
+        if !print_opts.silent {
+            write_cmdline_header(cmdline, &mut writer)?;
+        }
+        let yes_no = |b: bool| if b { "yes" } else { "no" };
+        let rows : Vec<[String; 6]> = steps.iter().enumerate().map(|(n, step)| [
+            n.to_string(),
+            format!("{}..{}", step.range.start, step.range.end),
+            format!("»{}«", visible_code_units(&cmdline[step.range.clone()])),
+            format!("{} -> {}", yes_no(step.in_quotes_before), yes_no(step.in_quotes_after)),
+            match step.backslashes {
+                Some((run, kept)) => format!("{} -> {}", run, kept),
+                None => String::new(),
+            },
+            match step.arg_ended {
+                Some(number) => format!("{}, ends argument {}", step.action.as_str(), number),
+                None => step.action.as_str().to_owned(),
+            },
+        ]).collect();
+        let header = ["Step", "Code units", "Text", "In quotes", "Backslashes", "Action"];
+        let mut widths : Vec<usize> = header.iter().map(|h| h.chars().count()).collect();
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row.iter()) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        let write_row = |writer: &mut W, row: &[&str]| -> io::Result<()> {
+            let line = row.iter().zip(widths.iter())
+                .map(|(cell, width)| format!("{}{}", cell, " ".repeat(width - cell.chars().count())))
+                .collect::<Vec<String>>().join("  ");
+            writeln!(writer, "{}", line.trim_end())
+        };
+        write_row(writer, &header)?;
+        for row in &rows {
+            write_row(writer, &row.iter().map(String::as_str).collect::<Vec<&str>>())?;
+        }
+        writeln!(&mut writer)?;
+        for arg in args {
+            writeln!(&mut writer, "Argument {:2}: »{}«", arg.number, utf16_to_string_lossy(&arg.to_utf16()).1)?;
+        }
+    }
+    Ok(())
+}
+
+/// Puts the differing code units of an argument in ⟦⟧.
+fn highlight(units: &[u16], ranges: &[std::ops::Range<usize>]) -> String {
+    let mut highlighted = String::new();
+    let mut end = 0;
+    for range in ranges {
+        highlighted.push_str(&utf16_to_string_lossy(&units[end..range.start]).1);
+        highlighted.push('⟦');
+        highlighted.push_str(&utf16_to_string_lossy(&units[range.clone()]).1);
+        highlighted.push('⟧');
+        end = range.end;
+    }
+    highlighted.push_str(&utf16_to_string_lossy(&units[end..]).1);
+    highlighted
+}
+
+pub(super) fn print_diff<W>(
+    first: &[u16],
+    second: &[u16],
+    cmdline_diff: &crate::diff::CmdlineDiff<'_>,
+    print_opts: &crate::options::PrintOptions,
+    mut writer: &mut W
+) -> io::Result<()>
+where
+    W: io::Write + ?Sized
+{
+    use crate::diff::DiffOp;
+    let arg_str = |arg: &Arg<'_>| utf16_to_string_lossy(&arg.to_utf16()).1;
+    let ranges_json = |ranges: &[std::ops::Range<usize>]| -> Vec<[usize; 2]> { ranges.iter().map(|r| [r.start, r.end]).collect() };
+    if print_opts.json {
+        let json = serde_json::json!({
+            "first-cmdline": utf16_to_string_lossy(first).1,
+            "second-cmdline": utf16_to_string_lossy(second).1,
+            "equal": cmdline_diff.is_equal(),
+            "ops": cmdline_diff.ops.iter().map(|op| {
+                let (i, j) = match op {
+                    DiffOp::Same(i, j) | DiffOp::Changed(i, j, _, _) => (Some(*i), Some(*j)),
+                    DiffOp::Removed(i) => (Some(*i), None),
+                    DiffOp::Added(j) => (None, Some(*j)),
+                };
+                let first_arg = i.map(|i| &cmdline_diff.first[i]);
+                let second_arg = j.map(|j| &cmdline_diff.second[j]);
+                let mut value = serde_json::json!({
+                    "op": op.as_str(),
+                    "first-index": i,
+                    "second-index": j,
+                    "first-arg": first_arg.map(arg_str),
+                    "second-arg": second_arg.map(arg_str),
+                    "first-raw": first_arg.map(|a| utf16_to_string_lossy(a.raw).1),
+                    "second-raw": second_arg.map(|a| utf16_to_string_lossy(a.raw).1),
+                });
+                if let DiffOp::Changed(_, _, first_ranges, second_ranges) = op {
+                    value["first-differences"] = serde_json::json!(ranges_json(first_ranges));
+                    value["second-differences"] = serde_json::json!(ranges_json(second_ranges));
+                }
+                value
+            }).collect::<Vec<serde_json::Value>>(),
+        });
+        serde_json::to_writer_pretty(&mut writer, &json)?;
+        writeln!(&mut writer)?;
+    }
+    else {
+        if !print_opts.silent {
+            writeln!(&mut writer, "The command lines are put in quotes (»«). The first command line is: \n»{}«\nThe second command line is: \n»{}«\n",
+                     utf16_to_string_lossy(first).1, utf16_to_string_lossy(second).1)?;
+        }
+        for op in &cmdline_diff.ops {
+            match op {
+                DiffOp::Same(i, j) => {
+                    let (a, b) = (&cmdline_diff.first[*i], &cmdline_diff.second[*j]);
+                    let raw_note = if a.raw == b.raw { String::new() } else {
+                        format!(", raw: »{}« → »{}«", utf16_to_string_lossy(a.raw).1, utf16_to_string_lossy(b.raw).1)
+                    };
+                    if print_opts.silent && raw_note.is_empty() {
+                        continue;
+                    }
+                    writeln!(&mut writer, "  Argument {:2} → {:2}: »{}«{}", i, j, arg_str(a), raw_note)?;
+                },
+                DiffOp::Removed(i) => writeln!(&mut writer, "- Argument {:2}     : »{}«", i, arg_str(&cmdline_diff.first[*i]))?,
+                DiffOp::Added(j) => writeln!(&mut writer, "+ Argument      {:2}: »{}«", j, arg_str(&cmdline_diff.second[*j]))?,
+                DiffOp::Changed(i, j, first_ranges, second_ranges) => {
+                    writeln!(&mut writer, "~ Argument {:2} → {:2}: »{}« → »{}«", i, j,
+                             highlight(&cmdline_diff.first[*i].to_utf16(), first_ranges),
+                             highlight(&cmdline_diff.second[*j].to_utf16(), second_ranges))?;
+                },
+            }
+        }
+        writeln!(&mut writer, "Verdict: {}", if cmdline_diff.is_equal() { "same arguments" } else { "DIFFERENT arguments" })?;
+    }
+    Ok(())
+}
+
+pub(super) fn print_matrix<W>(
+    cmdline: &[u16],
+    matrix: &crate::matrix::Matrix,
+    print_opts: &crate::options::PrintOptions,
+    mut writer: &mut W
+) -> io::Result<()>
+where
+    W: io::Write + ?Sized
+{
+    let columns = matrix.interpretations.len();
+    if print_opts.json {
+        let (cmdline_lossy, cmdline_str) = utf16_to_string_lossy(cmdline);
+        let json = serde_json::json!({
+            "cmdline": cmdline_str,
+            "cmdline-utf16": cmdline,
+            "cmdline-lossy": cmdline_lossy,
+            "interpretations": matrix.interpretations.iter().map(|i| i.name.clone()).collect::<Vec<String>>(),
+            "rows": matrix.agree.iter().enumerate().map(|(row, agree)| serde_json::json!({
+                "index": row,
+                "agree": agree,
+                "cells": (0..columns).map(|column| matrix.cell(row, column).map(|arg| utf16_to_string_lossy(arg).1))
+                    .collect::<Vec<Option<String>>>(),
+            })).collect::<Vec<serde_json::Value>>(),
+        });
+        serde_json::to_writer_pretty(&mut writer, &json)?;
+        writeln!(&mut writer)?;
+    }
+    else {
+        if !print_opts.silent {
+            write_cmdline_header(cmdline, &mut writer)?;
+        }
+        let mut table : Vec<Vec<String>> = vec![
+            std::iter::once("Arg".to_owned()).chain(matrix.interpretations.iter().map(|i| i.name.clone())).collect()
+        ];
+        for (row, agree) in matrix.agree.iter().enumerate() {
+            let mut cells = vec![format!("{}{}", if *agree { " " } else { "≠" }, row)];
+            for column in 0..columns {
+                let cell = matrix.cell(row, column);
+                let mark = if cell == matrix.cell(row, 0) { "" } else { "*" };
+                cells.push(match cell {
+                    Some(arg) => format!("{}»{}«", mark, visible_code_units(arg)),
+                    None => format!("{}-", mark),
+                });
+            }
+            table.push(cells);
+        }
+        let mut widths = vec![0usize; columns + 1];
+        for cells in &table {
+            for (width, cell) in widths.iter_mut().zip(cells.iter()) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        for cells in &table {
+            let line = cells.iter().zip(widths.iter())
+                .map(|(cell, width)| format!("{}{}", cell, " ".repeat(width - cell.chars().count())))
+                .collect::<Vec<String>>().join("  ");
+            writeln!(&mut writer, "{}", line.trim_end())?;
+        }
+    }
+    Ok(())
+}
+
+pub(super) fn print_exe_search<W>(
+    cmdline: &[u16],
+    search: &crate::exesearch::ExeSearch,
+    print_opts: &crate::options::PrintOptions,
+    mut writer: &mut W
+) -> io::Result<()>
+where
+    W: io::Write + ?Sized
+{
+    let path_str = |path: &[u16]| utf16_to_string_lossy(path).1;
+    if print_opts.json {
+        let (cmdline_lossy, cmdline_str) = utf16_to_string_lossy(cmdline);
+        let candidate_to_json = |c: &crate::exesearch::Candidate| serde_json::json!({
+            "name": c.prefix,
+            "location": c.location.as_str(),
+            "path-entry": match c.location { crate::exesearch::Location::Path(index) => Some(index), _ => None },
+            "path": path_str(&c.path),
+            "exists": c.exists,
+        });
+        let json = serde_json::json!({
+            "cmdline": cmdline_str,
+            "cmdline-utf16": cmdline,
+            "cmdline-lossy": cmdline_lossy,
+            "quoted": search.quoted,
+            "names": search.prefixes.iter().map(|p| serde_json::json!({
+                "start": p.range.start,
+                "end": p.range.end,
+                "name": path_str(&p.name),
+            })).collect::<Vec<serde_json::Value>>(),
+            "candidates": search.candidates.iter().map(candidate_to_json).collect::<Vec<serde_json::Value>>(),
+            "launched": search.found.map(|found| path_str(&search.candidates[found].path)),
+            "hijackable": search.hijackable.iter().map(|&i| path_str(&search.candidates[i].path)).collect::<Vec<String>>(),
+            "shadowed": search.shadowed.iter().map(candidate_to_json).collect::<Vec<serde_json::Value>>(),
+        });
+        serde_json::to_writer_pretty(&mut writer, &json)?;
+        writeln!(&mut writer)?;
+        return Ok(());
+    }
+
+    writeln!(&mut writer, "\nSearch for the program, because the program is NULL:")?;
+    if search.quoted {
+        writeln!(&mut writer, "Arg 0 is quoted, so there is only one name.")?;
+    } else {
+        writeln!(&mut writer, "Arg 0 is not quoted, so each part of the command line, that ends before a space or tab, is a name.")?;
+    }
+    for (index, prefix) in search.prefixes.iter().enumerate() {
+        writeln!(&mut writer, "  Name {}: »{}«", index, visible_code_units(&prefix.name))?;
+    }
+    let mut table : Vec<Vec<String>> = vec![
+        vec!["Candidate".to_owned(), "Name".to_owned(), "Location".to_owned(), "Path".to_owned(), "Result".to_owned()]
+    ];
+    for (index, candidate) in search.candidates.iter().enumerate() {
+        let location = match candidate.location {
+            crate::exesearch::Location::Path(entry) => format!("path, entry {}", entry),
+            other => other.as_str().to_owned(),
+        };
+        table.push(vec![
+            index.to_string(),
+            candidate.prefix.to_string(),
+            location,
+            format!("»{}«", visible_code_units(&candidate.path)),
+            (if candidate.exists { "found" } else { "not found" }).to_owned(),
+        ]);
+    }
+    let mut widths = [0usize; 5];
+    for cells in &table {
+        for (width, cell) in widths.iter_mut().zip(cells.iter()) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    for cells in &table {
+        let line = cells.iter().zip(widths.iter())
+            .map(|(cell, width)| format!("{}{}", cell, " ".repeat(width - cell.chars().count())))
+            .collect::<Vec<String>>().join("  ");
+        writeln!(&mut writer, "{}", line.trim_end())?;
+    }
+    match search.found {
+        Some(found) => writeln!(&mut writer, "The file, that would be launched, is: »{}«",
+                                visible_code_units(&search.candidates[found].path))?,
+        None => writeln!(&mut writer, "No file was found, CreateProcessW would fail.")?,
+    }
+    if !search.hijackable.is_empty() {
+        writeln!(&mut writer, "Warning: arg 0 is not quoted. If one of these files is created, it is launched instead:")?;
+        for &index in &search.hijackable {
+            writeln!(&mut writer, "  »{}«", visible_code_units(&search.candidates[index].path))?;
+        }
+    }
+    if !search.shadowed.is_empty() {
+        writeln!(&mut writer, "Warning: arg 0 is not quoted. These files are never tried, because a shorter name was found first:")?;
+        for candidate in &search.shadowed {
+            writeln!(&mut writer, "  »{}«", visible_code_units(&candidate.path))?;
+        }
+    }
+    writeln!(&mut writer)?;
+    Ok(())
+}
+
+pub(super) fn print_cmd_wrapping<W>(
+    wrapping: &crate::batbadbut::CmdWrapping,
+    print_opts: &crate::options::PrintOptions,
+    mut writer: &mut W
+) -> io::Result<()>
+where
+    W: io::Write + ?Sized
+{
+    let issue_to_json = |issue: &crate::batbadbut::Issue| serde_json::json!({
+        "kind": issue.kind.as_str(),
+        "unsafe": issue.kind.is_unsafe(),
+        "position": issue.position,
+        "description": issue.kind.describe(),
+    });
+    if print_opts.json {
+        let json = serde_json::json!({
+            "batch-file": utf16_to_string_lossy(&wrapping.batch_file).1,
+            "program": utf16_to_string_lossy(&wrapping.comspec).1,
+            "cmdline": utf16_to_string_lossy(&wrapping.cmd_cmdline).1,
+            "cmdline-utf16": wrapping.cmd_cmdline,
+            "command": utf16_to_string_lossy(&wrapping.command).1,
+            "commands": wrapping.commands.iter().map(|c| utf16_to_string_lossy(c).1).collect::<Vec<String>>(),
+            "parameters": wrapping.parameters.iter().map(|p| utf16_to_string_lossy(p).1).collect::<Vec<String>>(),
+            "issues": wrapping.issues.iter().map(issue_to_json).collect::<Vec<serde_json::Value>>(),
+        });
+        serde_json::to_writer_pretty(&mut writer, &json)?;
+        writeln!(&mut writer)?;
+        return Ok(());
+    }
+
+    writeln!(&mut writer, "\n»{}« is a batch file, so CreateProcessW starts cmd.exe instead:",
+             visible_code_units(&wrapping.batch_file))?;
+    writeln!(&mut writer, "The program (%ComSpec%) is:      »{}«", visible_code_units(&wrapping.comspec))?;
+    writeln!(&mut writer, "The command line of cmd.exe is:  »{}«", visible_code_units(&wrapping.cmd_cmdline))?;
+    writeln!(&mut writer, "cmd.exe removes the first and the last quote after /c and runs:\n»{}«", visible_code_units(&wrapping.command))?;
+    for (index, command) in wrapping.commands.iter().enumerate() {
+        writeln!(&mut writer, "  Command {}: »{}«{}", index, visible_code_units(command),
+                 if index == 0 { "" } else { " (injected)" })?;
+    }
+    writeln!(&mut writer, "The batch file gets these parameters:")?;
+    for (index, parameter) in wrapping.parameters.iter().enumerate() {
+        writeln!(&mut writer, "  %{}  »{}«", index, visible_code_units(parameter))?;
+    }
+    for issue in &wrapping.issues {
+        let character = char::decode_utf16([wrapping.command[issue.position]]).next()
+            .and_then(|c| c.ok())
+            .map_or("?".to_owned(), |c| if c.is_control() { c.escape_debug().to_string() } else { c.to_string() });
+        writeln!(&mut writer, "{}: »{}« at {:3} {}", if issue.kind.is_unsafe() { "Warning" } else { "Note" },
+                 character, issue.position, issue.kind.describe())?;
+    }
+    writeln!(&mut writer)?;
+    Ok(())
+}
+
+pub(super) fn print_command_resolution<W>(
+    resolution: &crate::resolve::CommandResolution,
+    print_opts: &crate::options::PrintOptions,
+    mut writer: &mut W
+) -> io::Result<()>
+where
+    W: io::Write + ?Sized
+{
+    use crate::resolve::Resolution;
+    if print_opts.json {
+        let json = serde_json::json!({
+            "command": utf16_to_string_lossy(&resolution.command).1,
+            "agree": resolution.agree,
+            "resolvers": resolution.lookups.iter().map(|lookup| serde_json::json!({
+                "resolver": lookup.resolver.as_str(),
+                "builtin": matches!(lookup.resolution, Resolution::Builtin),
+                "path": match &lookup.resolution {
+                    Resolution::File(path) => Some(utf16_to_string_lossy(path).1),
+                    _ => None,
+                },
+                "tried": lookup.tried.iter().map(|path| utf16_to_string_lossy(path).1).collect::<Vec<String>>(),
+            })).collect::<Vec<serde_json::Value>>(),
+        });
+        serde_json::to_writer_pretty(&mut writer, &json)?;
+        writeln!(&mut writer)?;
+        return Ok(());
+    }
+
+    if !print_opts.silent {
+        writeln!(&mut writer, "The command is put in quotes (»«). The command is: \n»{}«\n",
+                 visible_code_units(&resolution.command))?;
+        for lookup in &resolution.lookups {
+            writeln!(&mut writer, "{} tried {} paths:", lookup.resolver.describe(), lookup.tried.len())?;
+            for path in &lookup.tried {
+                writeln!(&mut writer, "  »{}«", visible_code_units(path))?;
+            }
+        }
+        writeln!(&mut writer)?;
+    }
+    let width = resolution.lookups.iter().map(|lookup| lookup.resolver.describe().chars().count()).max().unwrap_or(0);
+    let first = resolution.lookups.first().map(|lookup| &lookup.resolution);
+    for lookup in &resolution.lookups {
+        let result = match &lookup.resolution {
+            Resolution::Builtin => "internal command".to_owned(),
+            Resolution::File(path) => format!("»{}«", visible_code_units(path)),
+            Resolution::NotFound => "not found".to_owned(),
+        };
+        let differs = first.is_some_and(|first| !first.same_as(&lookup.resolution));
+        writeln!(&mut writer, "{}{}  {}{}", lookup.resolver.describe(),
+                 " ".repeat(width - lookup.resolver.describe().chars().count()),
+                 if differs { "*" } else { "" }, result)?;
+    }
+    if !resolution.agree {
+        writeln!(&mut writer, "≠ The resolvers disagree, the cells marked with * differ from the first row.")?;
+    }
+    Ok(())
+}
+
+pub(super) fn print_full_path<W>(
+    full_path: &crate::fullpath::FullPath,
+    print_opts: &crate::options::PrintOptions,
+    mut writer: &mut W
+) -> io::Result<()>
+where
+    W: io::Write + ?Sized
+{
+    if print_opts.json {
+        let (path_lossy, path) = utf16_to_string_lossy(&full_path.path);
+        let json = serde_json::json!({
+            "path": path,
+            "path-utf16": full_path.path,
+            "path-lossy": path_lossy,
+            "kind": full_path.kind.as_str(),
+            "full-path": utf16_to_string_lossy(&full_path.full_path).1,
+            "full-path-utf16": full_path.full_path,
+            "notes": full_path.notes.iter().map(|note| serde_json::json!({
+                "kind": note.as_str(),
+                "problem": note.is_problem(),
+                "description": note.describe(),
+            })).collect::<Vec<serde_json::Value>>(),
+        });
+        serde_json::to_writer_pretty(&mut writer, &json)?;
+        writeln!(&mut writer)?;
+        return Ok(());
+    }
+
+    if !print_opts.silent {
+        writeln!(&mut writer, "The path is put in quotes (»«). The path is: \n»{}«\n", visible_code_units(&full_path.path))?;
+        writeln!(&mut writer, "Kind:       {}", full_path.kind.as_str())?;
+    }
+    writeln!(&mut writer, "Full path:  »{}«", visible_code_units(&full_path.full_path))?;
+    for note in &full_path.notes {
+        if note.is_problem() {
+            writeln!(&mut writer, "Warning: {}", note.describe())?;
+        } else if !print_opts.silent {
+            writeln!(&mut writer, "  {}", note.describe())?;
+        }
+    }
+    Ok(())
+}
+
+pub(super) fn print_path_conversion<W>(
+    conversion: &crate::pathconv::Conversion,
+    print_opts: &crate::options::PrintOptions,
+    mut writer: &mut W
+) -> io::Result<()>
+where
+    W: io::Write + ?Sized
+{
+    if print_opts.json {
+        let (path_lossy, path) = utf16_to_string_lossy(&conversion.path);
+        let json = serde_json::json!({
+            "path": path,
+            "path-utf16": conversion.path,
+            "path-lossy": path_lossy,
+            "from": conversion.from,
+            "to": conversion.to.as_str(),
+            "converted": utf16_to_string_lossy(&conversion.converted).1,
+            "converted-utf16": conversion.converted,
+        });
+        serde_json::to_writer_pretty(&mut writer, &json)?;
+        writeln!(&mut writer)?;
+    }
+    else if print_opts.silent {
+        writeln!(&mut writer, "{}", utf16_to_string_lossy(&conversion.converted).1)?;
+    }
+    else {
+        writeln!(&mut writer, "The path ({}) is:      »{}«", conversion.from, visible_code_units(&conversion.path))?;
+        writeln!(&mut writer, "The converted path ({}) is:  »{}«", conversion.to.as_str(), visible_code_units(&conversion.converted))?;
+    }
+    Ok(())
+}
+
+pub(super) fn print_env_block<W>(
+    env_block: &crate::envblock::EnvBlock,
+    print_opts: &crate::options::PrintOptions,
+    mut writer: &mut W
+) -> io::Result<()>
+where
+    W: io::Write + ?Sized
+{
+    if print_opts.json {
+        let vars : Vec<serde_json::Value> = env_block.vars.iter().map(|(name, value)| {
+            serde_json::json!({
+                "name": utf16_to_string_lossy(name).1,
+                "name-utf16": name,
+                "value": utf16_to_string_lossy(value).1,
+                "value-utf16": value,
+            })
+        }).collect();
+        let json = serde_json::json!({
+            "env-block": vars,
+            "size-in-code-units": env_block.block.len(),
+        });
+        serde_json::to_writer_pretty(&mut writer, &json)?;
+        writeln!(&mut writer)?;
+    }
+    else {
+        writeln!(&mut writer, "The environment block (7th argument to CreateProcessW, {} code units) is:", env_block.block.len())?;
+        for (name, value) in &env_block.vars {
+            writeln!(&mut writer, "  »{}={}«", visible_code_units(name), visible_code_units(value))?;
+        }
+        writeln!(&mut writer)?;
+    }
+    Ok(())
+}
+
+pub(super) fn print_shell_command<W>(
+    shell_command: &crate::shellcmd::ShellCommand,
+    print_opts: &crate::options::PrintOptions,
+    mut writer: &mut W
+) -> io::Result<()>
+where
+    W: io::Write + ?Sized
+{
+    let to_strings = |args: &[Vec<u16>]| args.iter().map(|a| utf16_to_string_lossy(a).1).collect::<Vec<String>>();
+    if print_opts.json {
+        let (cmdline_lossy, cmdline) = utf16_to_string_lossy(&shell_command.cmdline);
+        let json = serde_json::json!({
+            "template": utf16_to_string_lossy(&shell_command.template).1,
+            "file": utf16_to_string_lossy(&shell_command.file).1,
+            "args": to_strings(&shell_command.args),
+            "program": utf16_to_string_lossy(&shell_command.program).1,
+            "cmdline": cmdline,
+            "cmdline-utf16": shell_command.cmdline,
+            "cmdline-lossy": cmdline_lossy,
+            "split": to_strings(&shell_command.split),
+            "warnings": shell_command.warnings.iter().map(|warning| serde_json::json!({
+                "kind": warning.as_str(),
+                "description": warning.describe(),
+            })).collect::<Vec<serde_json::Value>>(),
+        });
+        serde_json::to_writer_pretty(&mut writer, &json)?;
+        writeln!(&mut writer)?;
+    }
+    else if print_opts.silent {
+        writeln!(&mut writer, "{}", utf16_to_string_lossy(&shell_command.cmdline).1)?;
+    }
+    else {
+        writeln!(&mut writer, "The template is:       »{}«", visible_code_units(&shell_command.template))?;
+        writeln!(&mut writer, "The program is:        »{}«", visible_code_units(&shell_command.program))?;
+        writeln!(&mut writer, "The command line is:   »{}«\n", visible_code_units(&shell_command.cmdline))?;
+        for (number, arg) in shell_command.split.iter().enumerate() {
+            writeln!(&mut writer, "Argument {:2}: »{}«", number, visible_code_units(arg))?;
+        }
+        if !shell_command.warnings.is_empty() {
+            writeln!(&mut writer)?;
+        }
+        for warning in &shell_command.warnings {
+            writeln!(&mut writer, "Warning: {}", warning.describe())?;
+        }
+    }
+    Ok(())
+}
+
+pub(super) fn print_audit<W>(
+    entries: &[crate::audit::AuditEntry],
+    print_opts: &crate::options::PrintOptions,
+    mut writer: &mut W
+) -> io::Result<()>
+where
+    W: io::Write + ?Sized
+{
+    let to_strings = |args: &[Vec<u16>]| args.iter().map(|a| utf16_to_string_lossy(a).1).collect::<Vec<String>>();
+    if print_opts.json {
+        let json = serde_json::json!(entries.iter().map(|entry| {
+            let (cmdline_lossy, cmdline) = utf16_to_string_lossy(&entry.cmdline);
+            serde_json::json!({
+                "file": entry.file,
+                "kind": entry.source.as_str(),
+                "source": entry.source.describe(),
+                "cmdline": cmdline,
+                "cmdline-utf16": entry.cmdline,
+                "cmdline-lossy": cmdline_lossy,
+                "args": to_strings(&entry.args),
+                "quoted": entry.quoted,
+                "program": utf16_to_string_lossy(&entry.program).1,
+                "hijack-names": to_strings(&entry.hijack_names),
+                "vulnerable": entry.vulnerable(),
+            })
+        }).collect::<Vec<serde_json::Value>>());
+        serde_json::to_writer_pretty(&mut writer, &json)?;
+        writeln!(&mut writer)?;
+    }
+    else {
+        for entry in entries {
+            writeln!(&mut writer, "{} ({}, {}): {}", entry.source.describe(), entry.source.as_str(), entry.file,
+                     if entry.vulnerable() { "VULNERABLE" } else { "ok" })?;
+            writeln!(&mut writer, "  The command line is: »{}«", visible_code_units(&entry.cmdline))?;
+            if print_opts.silent {
+                continue;
+            }
+            for (number, arg) in entry.args.iter().enumerate() {
+                writeln!(&mut writer, "  Argument {:2}: »{}«", number, visible_code_units(arg))?;
+            }
+            if entry.vulnerable() {
+                writeln!(&mut writer, "  The program »{}« is not in quotes. If one of these files is created, it is launched instead:",
+                         visible_code_units(&entry.program))?;
+                for name in &entry.hijack_names {
+                    writeln!(&mut writer, "    »{}«", visible_code_units(name))?;
+                }
+            }
+            writeln!(&mut writer)?;
+        }
+    }
+    Ok(())
+}
+
+/// Prints the event as one line of JSON.
+pub(super) fn print_process_event<W>(
+    event: &crate::forensic::ProcessEvent,
+    file: &str,
+    mut writer: &mut W
+) -> io::Result<()>
+where
+    W: io::Write + ?Sized
+{
+    let json = serde_json::json!({
+        "file": file,
+        "record-id": event.record_id,
+        "event-id": event.event_id,
+        "image": event.image.as_ref().map(|image| utf16_to_string_lossy(image).1),
+        "cmdline": utf16_to_string_lossy(&event.cmdline).1,
+        "cmdline-utf16": event.cmdline,
+        "args": event.args.iter().map(|arg| utf16_to_string_lossy(arg).1).collect::<Vec<String>>(),
+        "anomalies": event.anomalies.iter().map(|anomaly| serde_json::json!({
+            "kind": anomaly.as_str(),
+            "arg": anomaly.arg(),
+            "description": anomaly.describe(),
+        })).collect::<Vec<serde_json::Value>>(),
+    });
+    serde_json::to_writer(&mut writer, &json)?;
+    writeln!(&mut writer)
+}
+
+pub(super) fn print_shell_link<W>(
+    shell_link: &crate::shelllink::ShellLink,
+    print_opts: &crate::options::PrintOptions,
+    mut writer: &mut W
+) -> io::Result<()>
+where
+    W: io::Write + ?Sized
+{
+    let fields : [(&str, Option<&Vec<u16>>); 9] = [
+        ("target", Some(&shell_link.target)),
+        ("arguments", shell_link.arguments.as_ref()),
+        ("working-dir", shell_link.working_dir.as_ref()),
+        ("env-target", shell_link.env_target.as_ref()),
+        ("local-path", shell_link.local_path.as_ref()),
+        ("network-path", shell_link.network_path.as_ref()),
+        ("relative-path", shell_link.relative_path.as_ref()),
+        ("name", shell_link.name.as_ref()),
+        ("icon-location", shell_link.icon_location.as_ref()),
+    ];
+    if print_opts.json {
+        let (cmdline_lossy, cmdline) = utf16_to_string_lossy(&shell_link.cmdline);
+        let mut json = serde_json::json!({
+            "unicode": shell_link.unicode,
+            "target-source": shell_link.target_source.as_str(),
+            "warning": shell_link.warning,
+            "cmdline": cmdline,
+            "cmdline-utf16": shell_link.cmdline,
+            "cmdline-lossy": cmdline_lossy,
+            "args": shell_link.split.iter().map(|arg| utf16_to_string_lossy(arg).1).collect::<Vec<String>>(),
+        });
+        for (key, value) in fields {
+            json[key] = serde_json::json!(value.map(|v| utf16_to_string_lossy(v).1));
+        }
+        serde_json::to_writer_pretty(&mut writer, &json)?;
+        writeln!(&mut writer)?;
+    }
+    else if !print_opts.silent {
+        writeln!(&mut writer, "The strings of the shortcut are in {}.", if shell_link.unicode { "UTF-16" } else { "the ANSI code page" })?;
+        for (key, value) in fields {
+            if let Some(value) = value {
+                writeln!(&mut writer, "{:14} »{}«", format!("{}:", key), visible_code_units(value))?;
+            }
+        }
+        writeln!(&mut writer, "The target comes from: {}\n", shell_link.target_source.as_str())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,297 @@
+const BACKSLASH: u16 = b'\\' as u16;
+const SLASH: u16 = b'/' as u16;
+const COLON: u16 = b':' as u16;
+
+/// The forms of a path, like `cygpath` knows them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathStyle {
+    /// `C:\x`, `\\server\share\x`
+    Windows,
+    /// `C:/x`, `//server/share/x`
+    Mixed,
+    /// `/c/x`, `//server/share/x`
+    Msys,
+    /// `/cygdrive/c/x`, `//server/share/x`
+    Cygwin,
+    /// `/mnt/c/x`
+    Wsl,
+}
+
+impl PathStyle {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PathStyle::Windows => "windows",
+            PathStyle::Mixed => "mixed",
+            PathStyle::Msys => "msys",
+            PathStyle::Cygwin => "cygwin",
+            PathStyle::Wsl => "wsl",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<PathStyle> {
+        [PathStyle::Windows, PathStyle::Mixed, PathStyle::Msys, PathStyle::Cygwin, PathStyle::Wsl]
+            .into_iter()
+            .find(|style| style.as_str() == name)
+    }
+
+    /// The prefix of a drive, like `/cygdrive` for `/cygdrive/c`.
+    fn drive_prefix(&self) -> Option<&'static str> {
+        match self {
+            PathStyle::Msys => Some(""),
+            PathStyle::Cygwin => Some("/cygdrive"),
+            PathStyle::Wsl => Some("/mnt"),
+            PathStyle::Windows | PathStyle::Mixed => None,
+        }
+    }
+}
+
+/// An entry of the mount table: a POSIX path and the Windows directory, that it stands for.
+#[derive(Debug)]
+pub struct Mount {
+    pub posix: Vec<u16>,
+    pub windows: Vec<u16>,
+}
+
+enum Root {
+    Drive(u16),
+    /// `C:x` is relative to the current directory of the drive.
+    DriveRelative(u16),
+    Unc(Vec<u16>, Vec<u16>),
+    /// `\x` is relative to the root of the current drive.
+    Rooted,
+    Relative,
+}
+
+/// A path in a form, that does not depend on the style.
+struct WindowsPath {
+    root: Root,
+    components: Vec<Vec<u16>>,
+    trailing_separator: bool,
+}
+
+pub struct Conversion {
+    pub path: Vec<u16>,
+    /// How the path was recognized, for example `msys` or `mount`.
+    pub from: &'static str,
+    pub to: PathStyle,
+    pub converted: Vec<u16>,
+}
+
+fn is_separator(c: u16) -> bool {
+    c == BACKSLASH || c == SLASH
+}
+
+fn is_drive_letter(c: u16) -> bool {
+    (b'a' as u16..=b'z' as u16).contains(&c) || (b'A' as u16..=b'Z' as u16).contains(&c)
+}
+
+fn upper(c: u16) -> u16 {
+    if (b'a' as u16..=b'z' as u16).contains(&c) { c - 0x20 } else { c }
+}
+
+fn lower(c: u16) -> u16 {
+    if (b'A' as u16..=b'Z' as u16).contains(&c) { c + 0x20 } else { c }
+}
+
+fn components(path: &[u16]) -> Vec<Vec<u16>> {
+    path.split(|&c| is_separator(c)).filter(|c| !c.is_empty()).map(|c| c.to_vec()).collect()
+}
+
+/// Whether `prefix` is `path` or a parent of it, comparing like the given function.
+fn strip_prefix<'a>(path: &'a [u16], prefix: &[u16], same: impl Fn(u16, u16) -> bool) -> Option<&'a [u16]> {
+    let prefix = match prefix.last() {
+        Some(&last) if is_separator(last) && prefix.len() > 1 => &prefix[..prefix.len() - 1],
+        _ => prefix,
+    };
+    if path.len() < prefix.len() || !path.iter().zip(prefix).all(|(&a, &b)| same(a, b)) {
+        return None;
+    }
+    let rest = &path[prefix.len()..];
+    if rest.is_empty() || is_separator(rest[0]) || prefix.last().is_some_and(|&c| is_separator(c)) {
+        Some(rest)
+    } else {
+        None
+    }
+}
+
+fn same_windows(a: u16, b: u16) -> bool {
+    upper(a) == upper(b) || (is_separator(a) && is_separator(b))
+}
+
+/// Parses a Windows, mixed, verbatim or UNC path.
+fn parse_windows(path: &[u16]) -> Option<(WindowsPath, &'static str)> {
+    let trailing_separator = path.last().is_some_and(|&c| is_separator(c));
+    let verbatim : Vec<u16> = "\\\\?\\".encode_utf16().collect();
+    let verbatim_unc : Vec<u16> = "\\\\?\\UNC\\".encode_utf16().collect();
+    let (path, from) : (Vec<u16>, &'static str) =
+        if path.starts_with(&verbatim_unc) {
+            ([BACKSLASH, BACKSLASH].iter().chain(&path[verbatim_unc.len()..]).copied().collect(), "verbatim")
+        }
+        else if path.starts_with(&verbatim) { (path[verbatim.len()..].to_vec(), "verbatim") }
+        else { (path.to_vec(), "") };
+
+    if path.len() >= 2 && is_drive_letter(path[0]) && path[1] == COLON {
+        if !path.get(2).is_some_and(|&c| is_separator(c)) {
+            let root = Root::DriveRelative(upper(path[0]));
+            return Some((WindowsPath { root, components: components(&path[2..]), trailing_separator }, "drive-relative"));
+        }
+        let from = if !from.is_empty() { from } else if path.contains(&BACKSLASH) { "windows" } else { "mixed" };
+        let root = Root::Drive(upper(path[0]));
+        return Some((WindowsPath { root, components: components(&path[2..]), trailing_separator }, from));
+    }
+    if path.len() >= 2 && is_separator(path[0]) && is_separator(path[1]) {
+        let mut parts = components(&path);
+        if parts.len() < 2 {
+            return None;
+        }
+        let rest = parts.split_off(2);
+        let root = Root::Unc(parts[0].clone(), parts[1].clone());
+        return Some((WindowsPath { root, components: rest, trailing_separator }, if from.is_empty() { "unc" } else { from }));
+    }
+    None
+}
+
+/// Finds the drive in `/c/x`, `/cygdrive/c/x` or `/mnt/c/x`, and returns the length of the matched prefix.
+fn parse_posix_drive(path: &[u16]) -> Option<(u16, usize, &'static str)> {
+    for style in [PathStyle::Cygwin, PathStyle::Wsl, PathStyle::Msys] {
+        let prefix : Vec<u16> = style.drive_prefix()?.encode_utf16().collect();
+        if !path.starts_with(&prefix) {
+            continue;
+        }
+        let rest = &path[prefix.len()..];
+        let is_drive = rest.len() >= 2 && rest[0] == SLASH && is_drive_letter(rest[1])
+            && rest.get(2).is_none_or(|&c| c == SLASH);
+        if is_drive {
+            return Some((upper(rest[1]), prefix.len() + 2, style.as_str()));
+        }
+    }
+    None
+}
+
+fn parse(path: &[u16], mounts: &[Mount]) -> Result<(WindowsPath, &'static str), String> {
+    if let Some(parsed) = parse_windows(path) {
+        return Ok(parsed);
+    }
+    // `\\?\Volume{...}\x` and `\\?\GLOBALROOT\x` have no drive, that could be converted.
+    if path.starts_with(&[BACKSLASH, BACKSLASH, b'?' as u16, BACKSLASH]) {
+        return Err(format!("The verbatim path »{}« has neither a drive nor a UNC share", String::from_utf16_lossy(path)));
+    }
+    let trailing_separator = path.last().is_some_and(|&c| is_separator(c));
+    if path.first() == Some(&BACKSLASH) {
+        return Ok((WindowsPath { root: Root::Rooted, components: components(path), trailing_separator }, "rooted"));
+    }
+    if path.first() != Some(&SLASH) {
+        return Ok((WindowsPath { root: Root::Relative, components: components(path), trailing_separator }, "relative"));
+    }
+
+    // The longest match wins, so a mount of `/c` hides the drive C: in MSYS form.
+    let drive = parse_posix_drive(path);
+    let mount = mounts.iter()
+        .filter_map(|mount| strip_prefix(path, &mount.posix, |a, b| a == b).map(|rest| (mount, path.len() - rest.len())))
+        .max_by_key(|(_, len)| *len);
+    match (drive, mount) {
+        (Some((letter, len, from)), mount) if mount.as_ref().is_none_or(|(_, mount_len)| *mount_len < len) => {
+            let root = Root::Drive(letter);
+            Ok((WindowsPath { root, components: components(&path[len..]), trailing_separator }, from))
+        },
+        (_, Some((mount, len))) => {
+            let (mut target, _) = parse_windows(&mount.windows)
+                .filter(|(target, _)| !matches!(target.root, Root::DriveRelative(_)))
+                .ok_or_else(|| format!("The mount target »{}« is not an absolute Windows path",
+                                       String::from_utf16_lossy(&mount.windows)))?;
+            target.components.extend(components(&path[len..]));
+            target.trailing_separator = trailing_separator;
+            Ok((target, "mount"))
+        },
+        _ => Err(format!("There is no mount for »{}«", String::from_utf16_lossy(path))),
+    }
+}
+
+fn join(components: &[Vec<u16>], separator: u16) -> Vec<u16> {
+    components.join(&separator)
+}
+
+fn format_windows(path: &WindowsPath, separator: u16) -> Vec<u16> {
+    let mut result : Vec<u16> = match &path.root {
+        Root::Drive(letter) => vec![*letter, COLON, separator],
+        Root::DriveRelative(letter) => vec![*letter, COLON],
+        Root::Unc(server, share) => {
+            let mut root = vec![separator, separator];
+            root.extend(server);
+            root.push(separator);
+            root.extend(share);
+            root.push(separator);
+            root
+        },
+        Root::Rooted => vec![separator],
+        Root::Relative => vec![],
+    };
+    result.extend(join(&path.components, separator));
+    if path.trailing_separator && result.last() != Some(&separator) {
+        result.push(separator);
+    }
+    result
+}
+
+/// Converts to a POSIX style. A mount, whose Windows directory contains the path, is preferred over the drive.
+fn format_posix(path: &WindowsPath, style: PathStyle, mounts: &[Mount]) -> Result<Vec<u16>, String> {
+    let windows = format_windows(path, BACKSLASH);
+    let mount = mounts.iter()
+        .filter_map(|mount| strip_prefix(&windows, &mount.windows, same_windows).map(|rest| (mount, rest)))
+        .min_by_key(|(_, rest)| rest.len());
+    let mut result : Vec<u16> = match (&path.root, mount) {
+        (Root::Relative, _) => vec![],
+        (Root::DriveRelative(_), _) | (Root::Rooted, _) => {
+            return Err(format!("»{}« depends on the current drive or directory, so it has no {} form",
+                               String::from_utf16_lossy(&windows), style.as_str()));
+        },
+        (_, Some((mount, rest))) => {
+            let mut result = mount.posix.clone();
+            if result.last() == Some(&SLASH) {
+                result.pop();
+            }
+            let rest_components = components(rest);
+            if !rest_components.is_empty() || result.is_empty() {
+                result.push(SLASH);
+            }
+            result.extend(join(&rest_components, SLASH));
+            if path.trailing_separator && result.last() != Some(&SLASH) {
+                result.push(SLASH);
+            }
+            return Ok(result);
+        },
+        (Root::Drive(letter), None) => {
+            let mut result : Vec<u16> = style.drive_prefix().unwrap_or_default().encode_utf16().collect();
+            result.extend([SLASH, lower(*letter), SLASH]);
+            result
+        },
+        (Root::Unc(server, share), None) => {
+            if style == PathStyle::Wsl {
+                return Err(format!("The UNC path »{}« has no WSL form without a mount", String::from_utf16_lossy(&windows)));
+            }
+            let mut result = vec![SLASH, SLASH];
+            result.extend(server);
+            result.push(SLASH);
+            result.extend(share);
+            result.push(SLASH);
+            result
+        },
+    };
+    result.extend(join(&path.components, SLASH));
+    if path.trailing_separator && result.last() != Some(&SLASH) {
+        result.push(SLASH);
+    }
+    Ok(result)
+}
+
+/// Converts a path in any of the forms to the given style. Relative paths stay relative.
+/// Rooted (`\x`) and drive-relative (`C:x`) paths keep their form in the Windows styles and fail for the others.
+pub fn convert_path(path: &[u16], to: PathStyle, mounts: &[Mount]) -> Result<Conversion, String> {
+    let (parsed, from) = parse(path, mounts)?;
+    let converted = match to {
+        PathStyle::Windows => format_windows(&parsed, BACKSLASH),
+        PathStyle::Mixed => format_windows(&parsed, SLASH),
+        _ => format_posix(&parsed, to, mounts)?,
+    };
+    Ok(Conversion { path: path.to_vec(), from, to, converted })
+}
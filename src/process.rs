@@ -19,7 +19,7 @@ use windows::Win32::Foundation::{
 
 pub fn create_process
     <S1: AsRef<OsStr>, S2: AsRef<OsStr>>
-    (program_opt: Option<S1>, cmd_opt: Option<S2>)
+    (program_opt: Option<S1>, cmd_opt: Option<S2>, env_block: Option<&[u16]>)
     -> Result<u32,String>
 {
     let startup_info : WinThreading::STARTUPINFOW = WinThreading::STARTUPINFOW{
@@ -42,7 +42,10 @@ pub fn create_process
         hStdOutput: HANDLE::default(),
         hStdError: HANDLE::default(),
     };
-    let creation_flags = WinThreading::PROCESS_CREATION_FLAGS(0);
+    let creation_flags = match env_block {
+        None => WinThreading::PROCESS_CREATION_FLAGS(0),
+        Some(_) => WinThreading::CREATE_UNICODE_ENVIRONMENT,
+    };
     let mut process_information = WinThreading::PROCESS_INFORMATION::default();
 
     let mut program_vec_u16 : Vec<u16>;
@@ -72,7 +75,7 @@ pub fn create_process
             None,
             false,
             creation_flags,
-            None,
+            env_block.map(|block| block.as_ptr() as *const std::ffi::c_void),
             PCWSTR::null(),
             &startup_info,
             &mut process_information
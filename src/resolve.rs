@@ -0,0 +1,204 @@
+use std::collections::HashSet;
+
+use crate::exesearch::{
+    cwd,
+    env_var,
+    file_keys,
+    has_path,
+    join,
+    key,
+    path_entries,
+    resolve,
+    search_program,
+};
+use crate::input::VirtualFs;
+
+const QUOTE: u16 = b'"' as u16;
+const BACKSLASH: u16 = b'\\' as u16;
+const SLASH: u16 = b'/' as u16;
+const DOT: u16 = b'.' as u16;
+
+const DEFAULT_PATHEXT: &str = ".COM;.EXE;.BAT;.CMD;.VBS;.VBE;.JS;.JSE;.WSF;.WSH;.MSC";
+
+/// The internal commands of cmd.exe. They are found before any file.
+const BUILTINS: [&str; 45] = [
+    "ASSOC", "BREAK", "CALL", "CD", "CHDIR", "CLS", "COLOR", "COPY", "DATE", "DEL", "DIR", "DPATH",
+    "ECHO", "ENDLOCAL", "ERASE", "EXIT", "FOR", "FTYPE", "GOTO", "IF", "KEYS", "MD", "MKDIR", "MKLINK",
+    "MOVE", "PATH", "PAUSE", "POPD", "PROMPT", "PUSHD", "RD", "REM", "REN", "RENAME", "RMDIR", "SET",
+    "SETLOCAL", "SHIFT", "START", "TIME", "TITLE", "TYPE", "VER", "VERIFY", "VOL",
+];
+
+pub(super) enum Resolver {
+    /// A command typed into cmd.exe or passed to `cmd /c`.
+    Cmd,
+    /// `start` in cmd.exe, which calls `ShellExecuteExW`.
+    Start,
+    /// `Get-Command -CommandType Application` in PowerShell.
+    PowerShell,
+    /// `CreateProcessW` with the command as command line and NULL as program.
+    CreateProcess,
+}
+
+impl Resolver {
+    pub(super) fn as_str(&self) -> &'static str {
+        match self {
+            Resolver::Cmd => "cmd",
+            Resolver::Start => "start",
+            Resolver::PowerShell => "powershell",
+            Resolver::CreateProcess => "create-process",
+        }
+    }
+
+    pub(super) fn describe(&self) -> &'static str {
+        match self {
+            Resolver::Cmd => "cmd.exe",
+            Resolver::Start => "start (ShellExecuteExW)",
+            Resolver::PowerShell => "Get-Command -CommandType Application",
+            Resolver::CreateProcess => "CreateProcessW",
+        }
+    }
+}
+
+pub(super) enum Resolution {
+    Builtin,
+    File(Vec<u16>),
+    NotFound,
+}
+
+impl Resolution {
+    fn key(&self) -> Option<String> {
+        match self {
+            Resolution::Builtin => Some("builtin".to_owned()),
+            Resolution::File(path) => Some(key(path)),
+            Resolution::NotFound => None,
+        }
+    }
+
+    /// Resolutions are the same, if they are the same builtin or file, or if both found nothing.
+    pub(super) fn same_as(&self, other: &Resolution) -> bool {
+        self.key() == other.key()
+    }
+}
+
+pub(super) struct Lookup {
+    pub(super) resolver: Resolver,
+    pub(super) resolution: Resolution,
+    /// The paths, that were tried in this order.
+    pub(super) tried: Vec<Vec<u16>>,
+}
+
+pub(super) struct CommandResolution {
+    pub(super) command: Vec<u16>,
+    pub(super) lookups: Vec<Lookup>,
+    pub(super) agree: bool,
+}
+
+fn has_extension(name: &[u16]) -> bool {
+    let file_name_start = name.iter().rposition(|&c| c == BACKSLASH || c == SLASH).map_or(0, |p| p + 1);
+    name[file_name_start..].contains(&DOT)
+}
+
+/// The names to try: the name itself, if it has an extension, otherwise the name with each extension of `PATHEXT`.
+fn names_with_pathext(name: &[u16], fs: &VirtualFs) -> Vec<Vec<u16>> {
+    if has_extension(name) {
+        return vec![name.to_vec()];
+    }
+    let pathext = env_var(fs, "PATHEXT").unwrap_or(DEFAULT_PATHEXT);
+    pathext.split(';')
+        .filter(|ext| !ext.is_empty())
+        .map(|ext| name.iter().copied().chain(ext.encode_utf16()).collect())
+        .collect()
+}
+
+/// Tries each name in each directory, or only relative to the current directory, if the name contains a path.
+fn search(names: &[Vec<u16>], dirs: &[Vec<u16>], fs: &VirtualFs, files: &HashSet<String>) -> (Resolution, Vec<Vec<u16>>) {
+    let mut tried : Vec<Vec<u16>> = vec![];
+    let candidates : Vec<Vec<u16>> = if names.first().is_some_and(|name| has_path(name)) {
+        names.iter().map(|name| resolve(name, &cwd(fs))).collect()
+    } else {
+        dirs.iter().flat_map(|dir| names.iter().map(move |name| join(dir, name))).collect()
+    };
+    for candidate in candidates {
+        let exists = files.contains(&key(&candidate));
+        tried.push(candidate);
+        if exists {
+            return (Resolution::File(tried.last().unwrap().clone()), tried);
+        }
+    }
+    (Resolution::NotFound, tried)
+}
+
+/// cmd.exe searches the current directory, unless `NoDefaultCurrentDirectoryInExePath` is set, and then `PATH`.
+fn cmd_dirs(fs: &VirtualFs) -> Vec<Vec<u16>> {
+    let mut dirs = vec![];
+    if env_var(fs, "NoDefaultCurrentDirectoryInExePath").is_none() {
+        dirs.push(cwd(fs));
+    }
+    dirs.extend(path_entries(fs));
+    dirs
+}
+
+fn cmd_lookup(command: &[u16], fs: &VirtualFs, files: &HashSet<String>) -> Lookup {
+    let name = String::from_utf16_lossy(command).to_uppercase();
+    if BUILTINS.contains(&name.as_str()) {
+        return Lookup { resolver: Resolver::Cmd, resolution: Resolution::Builtin, tried: vec![] };
+    }
+    let (resolution, tried) = search(&names_with_pathext(command, fs), &cmd_dirs(fs), fs, files);
+    Lookup { resolver: Resolver::Cmd, resolution, tried }
+}
+
+/// Like cmd.exe, but without builtins, and the registry key `App Paths` is used, if no file is found.
+fn start_lookup(command: &[u16], fs: &VirtualFs, files: &HashSet<String>) -> Lookup {
+    let (mut resolution, mut tried) = search(&names_with_pathext(command, fs), &cmd_dirs(fs), fs, files);
+    if matches!(resolution, Resolution::NotFound) && !has_path(command) {
+        let mut app_name = String::from_utf16_lossy(command);
+        if !has_extension(command) {
+            app_name.push_str(".exe");
+        }
+        let app_path = fs.app_paths.iter().find(|(name, _)| name.to_uppercase() == app_name.to_uppercase());
+        if let Some((_, path)) = app_path {
+            let path : Vec<u16> = path.trim_matches('"').encode_utf16().collect();
+            tried.push(path.clone());
+            resolution = Resolution::File(path);
+        }
+    }
+    Lookup { resolver: Resolver::Start, resolution, tried }
+}
+
+/// PowerShell only searches `PATH`, never the current directory.
+fn powershell_lookup(command: &[u16], fs: &VirtualFs, files: &HashSet<String>) -> Lookup {
+    let (resolution, tried) = search(&names_with_pathext(command, fs), &path_entries(fs), fs, files);
+    Lookup { resolver: Resolver::PowerShell, resolution, tried }
+}
+
+fn create_process_lookup(command: &[u16], fs: &VirtualFs) -> Lookup {
+    let mut cmdline : Vec<u16> = vec![];
+    let needs_quotes = command.iter().any(|&c| c == b' ' as u16 || c == b'\t' as u16);
+    if needs_quotes {
+        cmdline.push(QUOTE);
+    }
+    cmdline.extend_from_slice(command);
+    if needs_quotes {
+        cmdline.push(QUOTE);
+    }
+    let search = search_program(&cmdline, fs);
+    let resolution = match search.found {
+        Some(found) => Resolution::File(search.candidates[found].path.clone()),
+        None => Resolution::NotFound,
+    };
+    let tried = search.candidates.into_iter().map(|candidate| candidate.path).collect();
+    Lookup { resolver: Resolver::CreateProcess, resolution, tried }
+}
+
+/// Resolves the command like cmd.exe, `start`, PowerShell and `CreateProcessW` do.
+pub(super) fn resolve_command(command: &[u16], fs: &VirtualFs) -> CommandResolution {
+    let files = file_keys(fs);
+    let lookups = vec![
+        cmd_lookup(command, fs, &files),
+        start_lookup(command, fs, &files),
+        powershell_lookup(command, fs, &files),
+        create_process_lookup(command, fs),
+    ];
+    let agree = lookups.iter().all(|lookup| lookup.resolution.same_as(&lookups[0].resolution));
+    CommandResolution { command: command.to_vec(), lookups, agree }
+}
@@ -0,0 +1,24 @@
+/// Placeholders of a template are replaced by characters of the private use area, so that the template
+/// can be split or expanded without their values. Afterwards each sentinel tells, which placeholder it stands for.
+const FIRST_SENTINEL: u16 = 0xe000;
+const LAST_SENTINEL: u16 = 0xf8ff;
+
+/// The sentinel of the placeholder with the given index, or `None`, if there are too many placeholders.
+pub(super) fn sentinel(index: usize) -> Option<u16> {
+    u16::try_from(index).ok()
+        .filter(|&index| index <= LAST_SENTINEL - FIRST_SENTINEL)
+        .map(|index| FIRST_SENTINEL + index)
+}
+
+/// The index of the placeholder, that the code unit stands for, or `None`, if it is no sentinel.
+pub(super) fn sentinel_index(w: u16) -> Option<usize> {
+    (FIRST_SENTINEL..=LAST_SENTINEL).contains(&w).then(|| usize::from(w - FIRST_SENTINEL))
+}
+
+/// A template must not contain sentinels itself, because they could not be told apart from the placeholders.
+pub(super) fn check_no_sentinels(template: &[u16]) -> Result<(), String> {
+    match template.iter().position(|&w| sentinel_index(w).is_some()) {
+        Some(i) => Err(format!("The template contains a character of the private use area at {}", i)),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,227 @@
+use std::{
+    ffi::OsString,
+    os::windows::ffi::OsStringExt,
+};
+
+use crate::commandline::{
+    append_arg,
+    parse_lp_cmd_line,
+};
+use crate::envblock::same_name;
+use crate::sentinel::{
+    check_no_sentinels,
+    sentinel,
+    sentinel_index,
+};
+
+const PERCENT: u16 = b'%' as u16;
+const QUOTE: u16 = b'"' as u16;
+const SPACE: u16 = b' ' as u16;
+const TAB: u16 = b'\t' as u16;
+const STAR: u16 = b'*' as u16;
+const TILDE: u16 = b'~' as u16;
+
+pub(super) enum Warning {
+    /// `%1` or `%L` is not in quotes. The position is the index of `%` in the template.
+    UnquotedFile(usize),
+    /// The file is not part of a single argument of the command line.
+    FileSplit,
+    /// The program is not in quotes, but a variable expands to a path with spaces, like `%ProgramFiles%`.
+    UnquotedProgram(Vec<u16>),
+    /// `%2` to `%9` refers to an argument, that was not given.
+    MissingArgument(u16),
+    /// `%VAR%` is left as it is, because the variable is not defined.
+    UndefinedVariable(Vec<u16>),
+}
+
+impl Warning {
+    pub(super) fn as_str(&self) -> &'static str {
+        match self {
+            Warning::UnquotedFile(_) => "unquoted-file",
+            Warning::FileSplit => "file-split",
+            Warning::UnquotedProgram(_) => "unquoted-program",
+            Warning::MissingArgument(_) => "missing-argument",
+            Warning::UndefinedVariable(_) => "undefined-variable",
+        }
+    }
+
+    pub(super) fn describe(&self) -> String {
+        match self {
+            Warning::UnquotedFile(position) =>
+                format!("%1 at {} is not in quotes, a path with spaces is split into several arguments", position),
+            Warning::FileSplit =>
+                "The file is not passed as one argument".to_owned(),
+            Warning::UnquotedProgram(program) =>
+                format!("The program »{}« contains spaces, but is not in quotes", String::from_utf16_lossy(program)),
+            Warning::MissingArgument(number) =>
+                format!("%{} is replaced by nothing, because there is no argument {}", number, number),
+            Warning::UndefinedVariable(name) =>
+                format!("%{}% is not expanded, because the variable is not defined", String::from_utf16_lossy(name)),
+        }
+    }
+}
+
+pub(super) struct ShellCommand {
+    pub(super) template: Vec<u16>,
+    pub(super) file: Vec<u16>,
+    pub(super) args: Vec<Vec<u16>>,
+    /// The command line, that `ShellExecuteExW` passes to `CreateProcessW`.
+    pub(super) cmdline: Vec<u16>,
+    /// Argument zero of the command line.
+    pub(super) program: Vec<u16>,
+    /// The command line split by `parse_lp_cmd_line`.
+    pub(super) split: Vec<Vec<u16>>,
+    pub(super) warnings: Vec<Warning>,
+}
+
+/// The arguments from the given number on, quoted like the CRT expects it. Argument 1 is the file.
+fn join_args(file: &[u16], args: &[Vec<u16>], from: usize) -> Result<Vec<u16>, String> {
+    let mut joined : Vec<u16> = vec![];
+    let all = std::iter::once(file).chain(args.iter().map(Vec::as_slice));
+    for arg in all.skip(from.max(1) - 1) {
+        if !joined.is_empty() {
+            joined.push(SPACE);
+        }
+        append_arg(&mut joined, OsString::from_wide(arg), false, false)?;
+    }
+    Ok(joined)
+}
+
+/// Like `ExpandEnvironmentStringsW`: a variable, that is not defined, is kept, and its closing `%`
+/// may open the next variable.
+pub(super) fn expand_variables(text: &[u16], env: &[(Vec<u16>, Vec<u16>)], warnings: &mut Vec<Warning>) -> Vec<u16> {
+    expand_variables_marked(text, env, warnings).into_iter().map(|(c, _)| c).collect()
+}
+
+/// Like `expand_variables`, but each code unit tells, if it comes from `text` itself and not from the value of a variable.
+/// So the sentinels of the parameters can be told apart from the same characters in a value.
+fn expand_variables_marked(text: &[u16], env: &[(Vec<u16>, Vec<u16>)], warnings: &mut Vec<Warning>) -> Vec<(u16, bool)> {
+    let mut expanded : Vec<(u16, bool)> = vec![];
+    let mut i = 0;
+    while i < text.len() {
+        if text[i] != PERCENT {
+            expanded.push((text[i], true));
+            i += 1;
+            continue;
+        }
+        let end = match text[i + 1..].iter().position(|&c| c == PERCENT) {
+            Some(end) => i + 1 + end,
+            None => {
+                expanded.extend(text[i..].iter().map(|&c| (c, true)));
+                break;
+            },
+        };
+        let name = &text[i + 1..end];
+        match env.iter().find(|(n, _)| !name.is_empty() && same_name(n, name)) {
+            Some((_, value)) => {
+                expanded.extend(value.iter().map(|&c| (c, false)));
+                i = end + 1;
+            },
+            None => {
+                if !name.is_empty() && !name.iter().any(|&c| c == SPACE || c == TAB || c == QUOTE) {
+                    warnings.push(Warning::UndefinedVariable(name.to_vec()));
+                }
+                expanded.extend(text[i..end].iter().map(|&c| (c, true)));
+                i = end;
+            },
+        }
+    }
+    expanded
+}
+
+/// Expands a template of `HKCR\<class>\shell\<verb>\command` like `ShellExecuteExW` does:
+/// `%1`, `%0` and `%L` become the file, `%2` to `%9` the arguments, `%*` all arguments and `%~n` the
+/// arguments from `n` on. Then `%VAR%` is expanded with `env`, like for `REG_EXPAND_SZ`.
+pub(super) fn expand_shell_command(
+    template: &[u16],
+    file: &[u16],
+    args: &[Vec<u16>],
+    env: &[(Vec<u16>, Vec<u16>)],
+) -> Result<ShellCommand, String> {
+    let mut warnings : Vec<Warning> = vec![];
+    let mut values : Vec<Vec<u16>> = vec![];
+    let mut with_sentinels : Vec<u16> = vec![];
+    let mut in_quotes = false;
+    let mut uses_file = false;
+    let digit = |c: Option<u16>| c.and_then(|c| char::from_u32(c as u32)).and_then(|c| c.to_digit(10)).map(|d| d as u16);
+    check_no_sentinels(template)?;
+    let mut i = 0;
+    while i < template.len() {
+        let c = template[i];
+        let next = template.get(i + 1).copied();
+        let (value, len) : (Vec<u16>, usize) = match (c, next) {
+            (QUOTE, _) => {
+                in_quotes = !in_quotes;
+                with_sentinels.push(c);
+                i += 1;
+                continue;
+            },
+            (PERCENT, Some(n)) if n == b'L' as u16 || n == b'l' as u16 || digit(Some(n)).is_some_and(|d| d <= 1) => {
+                if !in_quotes {
+                    warnings.push(Warning::UnquotedFile(i));
+                }
+                uses_file = true;
+                (file.to_vec(), 2)
+            },
+            (PERCENT, Some(n)) if digit(Some(n)).is_some() => {
+                let number = digit(Some(n)).unwrap();
+                match args.get(usize::from(number) - 2) {
+                    Some(arg) => (arg.clone(), 2),
+                    None => {
+                        warnings.push(Warning::MissingArgument(number));
+                        (vec![], 2)
+                    },
+                }
+            },
+            (PERCENT, Some(STAR)) => (join_args(file, args, 2)?, 2),
+            (PERCENT, Some(TILDE)) if digit(template.get(i + 2).copied()).is_some() => {
+                let from = digit(template.get(i + 2).copied()).unwrap();
+                uses_file |= from <= 1;
+                (join_args(file, args, usize::from(from))?, 3)
+            },
+            _ => {
+                with_sentinels.push(c);
+                i += 1;
+                continue;
+            },
+        };
+        with_sentinels.push(sentinel(values.len()).ok_or("Too many parameters in the template")?);
+        values.push(value);
+        i += len;
+    }
+
+    if with_sentinels.first() != Some(&QUOTE) {
+        let end = with_sentinels.iter().position(|&c| c == SPACE || c == TAB).unwrap_or(with_sentinels.len());
+        let program = expand_variables(&with_sentinels[..end], env, &mut vec![]);
+        if program.iter().any(|&c| c == SPACE || c == TAB) {
+            warnings.push(Warning::UnquotedProgram(program));
+        }
+    }
+    // Only the sentinels of the template are parameters. The values of variables are taken as they are.
+    let expanded = expand_variables_marked(&with_sentinels, env, &mut warnings);
+    let mut cmdline : Vec<u16> = vec![];
+    for (c, from_template) in expanded {
+        match sentinel_index(c) {
+            Some(index) if from_template => cmdline.extend_from_slice(&values[index]),
+            _ => cmdline.push(c),
+        }
+    }
+
+    let split : Vec<Vec<u16>> = parse_lp_cmd_line(&cmdline, true).iter().map(|arg| arg.to_utf16()).collect();
+    let file_intact = file.is_empty()
+        || split.iter().any(|arg| arg.windows(file.len()).any(|w| w == file));
+    if uses_file && !file_intact {
+        warnings.push(Warning::FileSplit);
+    }
+    let program = split.first().cloned().unwrap_or_default();
+
+    Ok(ShellCommand {
+        template: template.to_vec(),
+        file: file.to_vec(),
+        args: args.to_vec(),
+        cmdline,
+        program,
+        split,
+        warnings,
+    })
+}
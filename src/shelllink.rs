@@ -0,0 +1,347 @@
+use crate::codepage::multibyte_to_wide;
+use crate::commandline::{
+    escape_arg_zero,
+    parse_lp_cmd_line,
+};
+use crate::shellcmd::expand_variables;
+
+const SPACE: u16 = b' ' as u16;
+const BACKSLASH: u16 = b'\\' as u16;
+
+const HEADER_SIZE: usize = 0x4c;
+/// `{00021401-0000-0000-C000-000000000046}`
+const LINK_CLSID: [u8; 16] = [0x01, 0x14, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0xc0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46];
+
+const HAS_LINK_TARGET_ID_LIST: u32 = 0x1;
+const HAS_LINK_INFO: u32 = 0x2;
+const HAS_NAME: u32 = 0x4;
+const HAS_RELATIVE_PATH: u32 = 0x8;
+const HAS_WORKING_DIR: u32 = 0x10;
+const HAS_ARGUMENTS: u32 = 0x20;
+const HAS_ICON_LOCATION: u32 = 0x40;
+const IS_UNICODE: u32 = 0x80;
+const FORCE_NO_LINK_INFO: u32 = 0x100;
+const HAS_EXP_STRING: u32 = 0x200;
+
+const VOLUME_ID_AND_LOCAL_BASE_PATH: u32 = 0x1;
+const COMMON_NETWORK_RELATIVE_LINK_AND_PATH_SUFFIX: u32 = 0x2;
+
+const ENVIRONMENT_VARIABLE_DATA_BLOCK: u32 = 0xa000_0001;
+const MAX_PATH: usize = 260;
+
+/// Where the target comes from.
+#[derive(Clone, Copy)]
+pub(super) enum TargetSource {
+    /// `TargetUnicode` or `TargetAnsi` of the `EnvironmentVariableDataBlock`, with the variables expanded.
+    EnvironmentVariable,
+    /// `LocalBasePath` and `CommonPathSuffix` of the `LinkInfo`.
+    LocalPath,
+    /// `NetName` and `CommonPathSuffix` of the `LinkInfo`.
+    NetworkPath,
+    /// `RELATIVE_PATH` of the string data, if there is no `LinkInfo`.
+    RelativePath,
+}
+
+impl TargetSource {
+    pub(super) fn as_str(&self) -> &'static str {
+        match self {
+            TargetSource::EnvironmentVariable => "environment-variable",
+            TargetSource::LocalPath => "local-path",
+            TargetSource::NetworkPath => "network-path",
+            TargetSource::RelativePath => "relative-path",
+        }
+    }
+}
+
+pub(super) struct ShellLink {
+    /// Whether the strings are in UTF-16. Otherwise they are in the ANSI code page.
+    pub(super) unicode: bool,
+    pub(super) name: Option<Vec<u16>>,
+    pub(super) relative_path: Option<Vec<u16>>,
+    pub(super) working_dir: Option<Vec<u16>>,
+    /// The raw tail of the command line.
+    pub(super) arguments: Option<Vec<u16>>,
+    pub(super) icon_location: Option<Vec<u16>>,
+    pub(super) local_path: Option<Vec<u16>>,
+    pub(super) network_path: Option<Vec<u16>>,
+    /// The target with environment variables like `%windir%\notepad.exe`, as it is stored.
+    pub(super) env_target: Option<Vec<u16>>,
+    pub(super) target: Vec<u16>,
+    pub(super) target_source: TargetSource,
+    /// The target, escaped with `escape_arg_zero`, and the arguments.
+    pub(super) cmdline: Vec<u16>,
+    /// The command line split by `parse_lp_cmd_line`.
+    pub(super) split: Vec<Vec<u16>>,
+    pub(super) warning: Option<&'static str>,
+}
+
+/// Reads little endian numbers and fails at the end of the data.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&self, offset: usize, len: usize) -> Result<&'a [u8], String> {
+        offset.checked_add(len)
+            .and_then(|end| self.data.get(offset..end))
+            .ok_or_else(|| format!("The shortcut ends at {}, before {} bytes at offset {}", self.data.len(), len, offset))
+    }
+
+    fn u16(&self, offset: usize) -> Result<u16, String> {
+        let bytes = self.bytes(offset, 2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32, String> {
+        let bytes = self.bytes(offset, 4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// A string, that ends with NUL, in the ANSI code page.
+    fn ansi_z(&self, offset: usize, code_page: u32) -> Result<Vec<u16>, String> {
+        let rest = self.data.get(offset..).ok_or_else(|| format!("The shortcut ends before offset {}", offset))?;
+        let end = rest.iter().position(|&b| b == 0).ok_or("A string of the shortcut has no NUL character")?;
+        multibyte_to_wide(code_page, &rest[..end])
+    }
+
+    /// A string, that ends with NUL, in UTF-16LE.
+    fn unicode_z(&self, offset: usize) -> Result<Vec<u16>, String> {
+        let mut units : Vec<u16> = vec![];
+        let mut offset = offset;
+        loop {
+            match self.u16(offset)? {
+                0 => return Ok(units),
+                unit => units.push(unit),
+            }
+            offset += 2;
+        }
+    }
+}
+
+fn u32_offset(value: u32) -> usize {
+    usize::try_from(value).unwrap_or(usize::MAX)
+}
+
+/// Puts a path and a suffix together, with a backslash in between, if needed.
+fn join(base: Vec<u16>, suffix: Vec<u16>) -> Vec<u16> {
+    let mut path = base;
+    if !suffix.is_empty() && !path.is_empty() && path.last() != Some(&BACKSLASH) {
+        path.push(BACKSLASH);
+    }
+    path.extend(suffix);
+    path
+}
+
+/// The local path and the network path of the `LinkInfo`.
+type LinkInfoPaths = (Option<Vec<u16>>, Option<Vec<u16>>);
+
+/// Reads the `LinkInfo` structure, and returns the local path and the network path.
+fn read_link_info(reader: &Reader<'_>, start: usize, code_page: u32) -> Result<LinkInfoPaths, String> {
+    let header_size = u32_offset(reader.u32(start + 4)?);
+    let flags = reader.u32(start + 8)?;
+    let local_base_path_offset = u32_offset(reader.u32(start + 16)?);
+    let network_offset = u32_offset(reader.u32(start + 20)?);
+    let suffix_offset = u32_offset(reader.u32(start + 24)?);
+    let has_unicode = header_size >= 0x24;
+
+    let suffix = if has_unicode {
+        reader.unicode_z(start + u32_offset(reader.u32(start + 32)?))?
+    } else {
+        reader.ansi_z(start + suffix_offset, code_page)?
+    };
+
+    let local_path = if flags & VOLUME_ID_AND_LOCAL_BASE_PATH != 0 {
+        let base = if has_unicode {
+            reader.unicode_z(start + u32_offset(reader.u32(start + 28)?))?
+        } else {
+            reader.ansi_z(start + local_base_path_offset, code_page)?
+        };
+        Some(join(base, suffix.clone()))
+    } else {
+        None
+    };
+
+    let network_path = if flags & COMMON_NETWORK_RELATIVE_LINK_AND_PATH_SUFFIX != 0 {
+        let network = start + network_offset;
+        let net_name_offset = u32_offset(reader.u32(network + 8)?);
+        let net_name = if net_name_offset > 0x14 {
+            reader.unicode_z(network + u32_offset(reader.u32(network + 20)?))?
+        } else {
+            reader.ansi_z(network + net_name_offset, code_page)?
+        };
+        Some(join(net_name, suffix))
+    } else {
+        None
+    };
+    Ok((local_path, network_path))
+}
+
+/// Reads the `EnvironmentVariableDataBlock` from the extra data, if there is one.
+fn read_env_target(reader: &Reader<'_>, mut offset: usize, code_page: u32) -> Result<Option<Vec<u16>>, String> {
+    loop {
+        let size = match reader.u32(offset) {
+            Ok(size) => u32_offset(size),
+            // Some shortcuts miss the terminal block.
+            Err(_) => return Ok(None),
+        };
+        if size < 8 {
+            return Ok(None);
+        }
+        if reader.u32(offset + 4)? == ENVIRONMENT_VARIABLE_DATA_BLOCK {
+            let unicode = reader.bytes(offset + 8 + MAX_PATH, MAX_PATH * 2)?;
+            let units : Vec<u16> = unicode.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+            let end = units.iter().position(|&u| u == 0).unwrap_or(units.len());
+            if end != 0 {
+                return Ok(Some(units[..end].to_vec()));
+            }
+            let ansi = reader.bytes(offset + 8, MAX_PATH)?;
+            let end = ansi.iter().position(|&b| b == 0).unwrap_or(ansi.len());
+            return Ok(Some(multibyte_to_wide(code_page, &ansi[..end])?));
+        }
+        offset += size;
+    }
+}
+
+/// Parses a Shell Link (`.lnk`) file like MS-SHLLINK describes it, and puts the command line together,
+/// that the shell passes to `CreateProcessW`. ANSI strings are decoded with the given code page, and the
+/// environment variables in the target are expanded with `env`.
+pub(super) fn read_shell_link(data: &[u8], code_page: u32, env: &[(Vec<u16>, Vec<u16>)]) -> Result<ShellLink, String> {
+    let reader = Reader { data };
+    if reader.u32(0)? != HEADER_SIZE as u32 || reader.bytes(4, 16)? != LINK_CLSID {
+        return Err("The file is not a shortcut (.lnk), the header is wrong".to_owned());
+    }
+    let flags = reader.u32(0x14)?;
+    let unicode = flags & IS_UNICODE != 0;
+    let mut offset = HEADER_SIZE;
+
+    if flags & HAS_LINK_TARGET_ID_LIST != 0 {
+        offset += 2 + usize::from(reader.u16(offset)?);
+    }
+
+    let (mut local_path, mut network_path) = (None, None);
+    if flags & HAS_LINK_INFO != 0 {
+        if flags & FORCE_NO_LINK_INFO == 0 {
+            (local_path, network_path) = read_link_info(&reader, offset, code_page)?;
+        }
+        offset += u32_offset(reader.u32(offset)?);
+    }
+
+    // NAME_STRING, RELATIVE_PATH, WORKING_DIR, COMMAND_LINE_ARGUMENTS and ICON_LOCATION, in this order.
+    let mut strings : Vec<Option<Vec<u16>>> = vec![];
+    for flag in [HAS_NAME, HAS_RELATIVE_PATH, HAS_WORKING_DIR, HAS_ARGUMENTS, HAS_ICON_LOCATION] {
+        if flags & flag == 0 {
+            strings.push(None);
+            continue;
+        }
+        let count = usize::from(reader.u16(offset)?);
+        offset += 2;
+        let string = if unicode {
+            let bytes = reader.bytes(offset, count * 2)?;
+            offset += count * 2;
+            bytes.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect()
+        } else {
+            let bytes = reader.bytes(offset, count)?;
+            offset += count;
+            multibyte_to_wide(code_page, bytes)?
+        };
+        strings.push(Some(string));
+    }
+    let [name, relative_path, working_dir, arguments, icon_location] : [Option<Vec<u16>>; 5] =
+        strings.try_into().map_err(|_| "The shortcut has the wrong number of strings".to_owned())?;
+
+    let env_target = if flags & HAS_EXP_STRING != 0 { read_env_target(&reader, offset, code_page)? } else { None };
+
+    let (target, target_source) = match (&env_target, &local_path, &network_path, &relative_path) {
+        (Some(env_target), _, _, _) => (expand_variables(env_target, env, &mut vec![]), TargetSource::EnvironmentVariable),
+        (None, Some(local_path), _, _) => (local_path.clone(), TargetSource::LocalPath),
+        (None, None, Some(network_path), _) => (network_path.clone(), TargetSource::NetworkPath),
+        (None, None, None, Some(relative_path)) => (relative_path.clone(), TargetSource::RelativePath),
+        (None, None, None, None) => {
+            return Err("The shortcut has no path of the target, only an ID list, which is not supported".to_owned());
+        },
+    };
+
+    let escaped = escape_arg_zero(&target, false)?;
+    let warning = escaped.warning;
+    let mut cmdline : Vec<u16> = escaped.escaped.into_owned();
+    if let Some(arguments) = arguments.as_ref().filter(|arguments| !arguments.is_empty()) {
+        cmdline.push(SPACE);
+        cmdline.extend_from_slice(arguments);
+    }
+    let split : Vec<Vec<u16>> = parse_lp_cmd_line(&cmdline, true).iter().map(|arg| arg.to_utf16()).collect();
+
+    Ok(ShellLink {
+        unicode,
+        name,
+        relative_path,
+        working_dir,
+        arguments,
+        icon_location,
+        local_path,
+        network_path,
+        env_target,
+        target,
+        target_source,
+        cmdline,
+        split,
+        warning,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Generated by scripts/gen-lnk-fixtures.py.
+    const UNICODE_LNK: &[u8] = include_bytes!("../tests/fixtures/unicode.lnk");
+    const ANSI_LNK: &[u8] = include_bytes!("../tests/fixtures/ansi.lnk");
+
+    fn u(s: &str) -> Vec<u16> {
+        s.encode_utf16().collect()
+    }
+
+    fn env() -> Vec<(Vec<u16>, Vec<u16>)> {
+        vec![(u("ProgramFiles"), u("C:\\Program Files")), (u("SystemDrive"), u("C:"))]
+    }
+
+    #[test]
+    fn reads_a_unicode_shortcut() {
+        let link = read_shell_link(UNICODE_LNK, 1252, &env()).unwrap();
+        assert!(link.unicode);
+        assert_eq!(link.name, Some(u("Start the app")));
+        assert_eq!(link.working_dir, Some(u("%USERPROFILE%\\Documents")));
+        assert_eq!(link.arguments, Some(u("--open \"a b.txt\" --ünïcödé")));
+        assert_eq!(link.local_path, Some(u("C:\\Program Files\\App\\app.exe")));
+        assert_eq!(link.network_path, Some(u("\\\\server\\share\\App\\app.exe")));
+        assert_eq!(link.env_target, Some(u("%ProgramFiles%\\App\\app.exe")));
+        assert_eq!(link.target, u("C:\\Program Files\\App\\app.exe"));
+        assert!(matches!(link.target_source, TargetSource::EnvironmentVariable));
+        assert_eq!(link.cmdline, u("\"C:\\Program Files\\App\\app.exe\" --open \"a b.txt\" --ünïcödé"));
+        assert_eq!(link.split, [u("C:\\Program Files\\App\\app.exe"), u("--open"), u("a b.txt"), u("--ünïcödé")]);
+        assert!(link.warning.is_none());
+    }
+
+    #[test]
+    fn reads_an_ansi_shortcut() {
+        let link = read_shell_link(ANSI_LNK, 1252, &env()).unwrap();
+        assert!(!link.unicode);
+        assert_eq!(link.name, Some(u("Café tool")));
+        assert_eq!(link.working_dir, Some(u("C:\\Café")));
+        assert_eq!(link.arguments, Some(u("/x \"é\"")));
+        assert_eq!(link.local_path, Some(u("C:\\Café\\tool.exe")));
+        assert_eq!(link.network_path, Some(u("\\\\server\\share\\tool.exe")));
+        // Only `TargetAnsi` is set in the environment block.
+        assert_eq!(link.env_target, Some(u("%SystemDrive%\\Café\\tool.exe")));
+        assert_eq!(link.target, u("C:\\Café\\tool.exe"));
+        assert_eq!(link.cmdline, u("C:\\Café\\tool.exe /x \"é\""));
+        assert_eq!(link.split, [u("C:\\Café\\tool.exe"), u("/x"), u("é")]);
+    }
+
+    #[test]
+    fn rejects_other_files_and_truncated_shortcuts() {
+        assert!(read_shell_link(b"not a shortcut", 1252, &[]).is_err());
+        for len in [0, 0x20, HEADER_SIZE, 0x80, 0x100] {
+            assert!(read_shell_link(&ANSI_LNK[..len], 1252, &[]).is_err(), "{}", len);
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    os::windows::ffi::OsStringExt,
+};
+
+use crate::commandline::{
+    append_arg,
+    escape_arg_zero,
+    parse_lp_cmd_line,
+};
+use crate::options::QuotingStyle;
+use crate::sentinel::{
+    check_no_sentinels,
+    sentinel,
+    sentinel_index,
+};
+
+const SPACE: u16 = b' ' as u16;
+const OPEN: u16 = b'{' as u16;
+const CLOSE: u16 = b'}' as u16;
+
+/// The values of the placeholders by their names. A variadic placeholder can have any number of values.
+pub(super) type TemplateValues = HashMap<String, Vec<Vec<u16>>>;
+
+pub(super) struct Placeholder {
+    pub(super) name: String,
+    pub(super) variadic: bool,
+    pub(super) style: Option<QuotingStyle>,
+}
+
+enum Piece {
+    Literal(Vec<u16>),
+    Placeholder(usize), // index into the placeholders
+}
+
+/// A template like `tool.exe --in {input} --out {output} -- {rest...}`.
+/// Literal text is kept raw. `{{` and `}}` are a literal `{` and `}`.
+/// A placeholder can select a quoting style, for example `{input:crt-always}`.
+pub(super) struct Template {
+    pieces: Vec<Piece>,
+    pub(super) placeholders: Vec<Placeholder>,
+}
+
+pub(super) struct PlaceholderCheck {
+    pub(super) name: String,
+    pub(super) variadic: bool,
+    pub(super) expected: Vec<Vec<u16>>,
+    pub(super) actual: Vec<Vec<u16>>,
+    pub(super) ok: bool,
+}
+
+pub(super) struct Filled {
+    pub(super) cmdline: Vec<u16>,
+    pub(super) checks: Vec<PlaceholderCheck>,
+    /// True, if the command line is split into exactly the intended arguments.
+    pub(super) verified: bool,
+}
+
+pub(super) fn parse_quoting_style(name: &str) -> Option<QuotingStyle> {
+    match name {
+        "crt" => Some(QuotingStyle::Crt),
+        "crt-always" => Some(QuotingStyle::CrtAlways),
+        "raw" => Some(QuotingStyle::Raw),
+        _ => None,
+    }
+}
+
+pub(super) fn parse_template(template: &[u16]) -> Result<Template, String> {
+    let mut pieces : Vec<Piece> = vec![];
+    let mut placeholders : Vec<Placeholder> = vec![];
+    let mut literal : Vec<u16> = vec![];
+    check_no_sentinels(template)?;
+    let mut i = 0;
+    while i < template.len() {
+        let w = template[i];
+        match (w, template.get(i + 1)) {
+            (OPEN, Some(&OPEN)) | (CLOSE, Some(&CLOSE)) => {
+                literal.push(w);
+                i += 2;
+            },
+            (CLOSE, _) => return Err(format!("Unmatched »}}« at {} in the template, write »}}}}« for a literal »}}«", i)),
+            (OPEN, _) => {
+                let end = match template[i..].iter().position(|&w| w == CLOSE) {
+                    Some(end) => i + end,
+                    None => return Err(format!("Unclosed placeholder at {} in the template", i)),
+                };
+                let inner = String::from_utf16(&template[i + 1..end])
+                    .map_err(|_| format!("Bad placeholder at {} in the template", i))?;
+                let (name, style) = match inner.split_once(':') {
+                    Some((name, style)) => (name, Some(parse_quoting_style(style)
+                        .ok_or_else(|| format!("Unknown quoting style »{}« in the template", style))?)),
+                    None => (inner.as_str(), None),
+                };
+                let (name, variadic) = match name.strip_suffix("...") {
+                    Some(name) => (name, true),
+                    None => (name, false),
+                };
+                if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                    return Err(format!("Bad placeholder name »{}« in the template", name));
+                }
+                if let Some(other) = placeholders.iter().find(|p| p.name == name) {
+                    if other.variadic != variadic {
+                        return Err(format!("The placeholder »{}« is used as variadic and not variadic", name));
+                    }
+                }
+                if !literal.is_empty() {
+                    pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+                }
+                if sentinel(placeholders.len()).is_none() {
+                    return Err("Too many placeholders in the template".to_owned());
+                }
+                pieces.push(Piece::Placeholder(placeholders.len()));
+                placeholders.push(Placeholder { name: name.to_owned(), variadic, style });
+                i = end + 1;
+            },
+            _ => {
+                literal.push(w);
+                i += 1;
+            },
+        }
+    }
+    if !literal.is_empty() {
+        pieces.push(Piece::Literal(literal));
+    }
+    Ok(Template { pieces, placeholders })
+}
+
+fn quote(value: &[u16], style: QuotingStyle, is_arg_zero: bool, out: &mut Vec<u16>) -> Result<(), String> {
+    match (style, is_arg_zero) {
+        (QuotingStyle::Raw, _) => out.extend_from_slice(value),
+        (_, true) => out.extend_from_slice(&escape_arg_zero(value, style == QuotingStyle::CrtAlways)?.escaped),
+        (_, false) => append_arg(out, OsString::from_wide(value), style == QuotingStyle::CrtAlways, false)?,
+    }
+    Ok(())
+}
+
+/// Fills the template with `values` and checks with `parse_lp_cmd_line`, that each placeholder became
+/// exactly the intended argument or arguments.
+pub(super) fn fill_template(
+    template: &Template,
+    values: &TemplateValues,
+    default_style: QuotingStyle,
+) -> Result<Filled, String> {
+    for placeholder in &template.placeholders {
+        match values.get(&placeholder.name) {
+            None if placeholder.variadic => {},
+            None => return Err(format!("There is no value for the placeholder »{}«", placeholder.name)),
+            Some(v) if !placeholder.variadic && v.len() != 1 => {
+                return Err(format!("The placeholder »{}« needs exactly one value, but got {}", placeholder.name, v.len()));
+            },
+            Some(_) => {},
+        }
+    }
+    for name in values.keys() {
+        if !template.placeholders.iter().any(|p| &p.name == name) {
+            return Err(format!("The template has no placeholder »{}«", name));
+        }
+    }
+    let values_of = |index: usize| -> &[Vec<u16>] {
+        values.get(&template.placeholders[index].name).map_or(&[], |v| &v[..])
+    };
+
+    // The template with sentinels tells, which argument each placeholder is part of.
+    // `parse_template` made sure, that there is a sentinel for each placeholder.
+    let sentinel_of = |index: usize| sentinel(index).unwrap();
+    let mut with_sentinels : Vec<u16> = vec![];
+    for piece in &template.pieces {
+        match piece {
+            Piece::Literal(literal) => with_sentinels.extend_from_slice(literal),
+            Piece::Placeholder(index) => with_sentinels.push(sentinel_of(*index)),
+        }
+    }
+    let template_args : Vec<Vec<u16>> = parse_lp_cmd_line(&with_sentinels, true).iter().map(|a| a.to_utf16()).collect();
+    let in_arg_zero = |index: usize| template_args.first().is_some_and(|a| a.contains(&sentinel_of(index)));
+
+    // The intended arguments, and for each the placeholders, that it comes from.
+    let mut expected : Vec<(Vec<u16>, Vec<usize>)> = vec![];
+    for (n, template_arg) in template_args.iter().enumerate() {
+        let indices : Vec<usize> = template_arg.iter().filter_map(|&w| sentinel_index(w)).collect();
+        match indices.iter().find(|&&i| template.placeholders[i].variadic) {
+            Some(&index) => {
+                if template_arg.len() != 1 || n == 0 {
+                    return Err(format!("The variadic placeholder »{}...« must be a whole argument, but not argument zero",
+                                       template.placeholders[index].name));
+                }
+                expected.extend(values_of(index).iter().map(|v| (v.clone(), vec![index])));
+            },
+            None => {
+                let mut arg : Vec<u16> = vec![];
+                for &w in template_arg {
+                    match sentinel_index(w) {
+                        Some(index) => arg.extend_from_slice(&values_of(index)[0]),
+                        None => arg.push(w),
+                    }
+                }
+                expected.push((arg, indices));
+            },
+        }
+    }
+
+    let mut cmdline : Vec<u16> = vec![];
+    for piece in &template.pieces {
+        match piece {
+            Piece::Literal(literal) => cmdline.extend_from_slice(literal),
+            Piece::Placeholder(index) => {
+                let style = template.placeholders[*index].style.unwrap_or(default_style);
+                for (n, value) in values_of(*index).iter().enumerate() {
+                    if n != 0 {
+                        cmdline.push(SPACE);
+                    }
+                    quote(value, style, in_arg_zero(*index), &mut cmdline)?;
+                }
+            },
+        }
+    }
+
+    let actual : Vec<Vec<u16>> = parse_lp_cmd_line(&cmdline, true).iter().map(|a| a.to_utf16()).collect();
+    // After the first difference the arguments cannot be matched anymore.
+    let first_difference : usize = expected.iter().zip(&actual)
+        .position(|((e, _), a)| e != a)
+        .unwrap_or(expected.len().min(actual.len()));
+    let verified = expected.len() == actual.len() && first_difference == expected.len();
+
+    let checks : Vec<PlaceholderCheck> = template.placeholders.iter().enumerate().map(|(index, placeholder)| {
+        let positions : Vec<usize> = expected.iter().enumerate()
+            .filter(|(_, (_, indices))| indices.contains(&index))
+            .map(|(n, _)| n)
+            .collect();
+        PlaceholderCheck {
+            name: placeholder.name.clone(),
+            variadic: placeholder.variadic,
+            expected: positions.iter().map(|&n| expected[n].0.clone()).collect(),
+            actual: positions.iter().filter_map(|&n| actual.get(n).cloned()).collect(),
+            ok: verified || positions.iter().all(|&n| n < first_difference),
+        }
+    }).collect();
+
+    Ok(Filled { cmdline, checks, verified })
+}
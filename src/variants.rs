@@ -0,0 +1,342 @@
+use std::collections::HashSet;
+
+use crate::commandline::parse_lp_cmd_line;
+
+const BACKSLASH: u16 = b'\\' as u16;
+const QUOTE: u16 = b'"' as u16;
+const TAB: u16 = b'\t' as u16;
+const SPACE: u16 = b' ' as u16;
+
+/// A small pseudo random number generator (SplitMix64), so that a seed always gives the same variants.
+pub(super) struct SplitMix64(u64);
+
+impl SplitMix64 {
+    pub(super) fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub(super) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in `0..n`.
+    pub(super) fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % (n as u64)) as usize
+    }
+
+    /// Returns true with a probability of `percent` percent.
+    pub(super) fn chance(&mut self, percent: usize) -> bool {
+        self.below(100) < percent
+    }
+}
+
+/// How one argument is spelled in the command line.
+#[derive(Clone)]
+struct Spelling {
+    /// `quoted[i]` is true, if the i-th code unit of the argument is written inside quotes.
+    quoted: Vec<bool>,
+    /// `double_quote[i]` is true, if a literal quote at i, that is inside quotes, is written as `""` instead of `\"`.
+    double_quote: Vec<bool>,
+    /// `empty_pair[i]` is true, if an empty pair of quotes (`""`) is written before the i-th code unit,
+    /// when it is outside of quotes. After backslashes this doubles them.
+    empty_pair: Vec<bool>,
+    /// Leave away the closing quote, or add an opening quote at the end. Only valid for the last argument.
+    unterminated: bool,
+}
+
+impl Spelling {
+    fn new(quoted: Vec<bool>) -> Self {
+        let len = quoted.len();
+        Self {
+            quoted,
+            double_quote: vec![false; len],
+            empty_pair: vec![false; len],
+            unterminated: false,
+        }
+    }
+}
+
+fn is_blank(w: u16) -> bool {
+    w == SPACE || w == TAB
+}
+
+/// The ways to place quotes, that are used for enumeration.
+/// The first one is the minimal quoting, that `append_arg` produces.
+fn quote_placements(arg: &[u16]) -> Vec<Vec<bool>> {
+    let needs_quotes = arg.iter().any(|&w| is_blank(w));
+    let first_blank = arg.iter().position(|&w| is_blank(w));
+    let last_blank = arg.iter().rposition(|&w| is_blank(w));
+    vec![
+        vec![needs_quotes; arg.len()],
+        vec![true; arg.len()],
+        // only the blanks, for example »Hello" "World«
+        arg.iter().map(|&w| is_blank(w)).collect(),
+        // from the first blank to the end, for example »--input:"Hello World"«
+        (0..arg.len()).map(|i| first_blank.is_some_and(|f| i >= f)).collect(),
+        // from the start to the last blank, for example »"C:\Program Files"\prog.exe«
+        (0..arg.len()).map(|i| last_blank.is_some_and(|l| i <= l)).collect(),
+        // toggle the quotes at every code unit
+        arg.iter().enumerate().map(|(i, &w)| is_blank(w) || i % 2 == 0).collect(),
+    ]
+}
+
+/// All spellings of one argument, that are used for enumeration. Spellings, that give the same
+/// text, like the many spellings of an empty argument, are only kept once.
+fn enumerated_spellings(arg: &[u16], is_arg_zero: bool, is_last: bool) -> Vec<Spelling> {
+    let mut spellings : Vec<Spelling> = vec![];
+    for quoted in quote_placements(arg) {
+        let plain = Spelling::new(quoted);
+        spellings.push(plain.clone());
+        if !is_arg_zero {
+            let mut doubled = plain.clone();
+            doubled.double_quote = vec![true; arg.len()];
+            spellings.push(doubled);
+        }
+        let mut with_pairs = plain.clone();
+        with_pairs.empty_pair = (0..arg.len()).map(|i| i > 0 && arg[i - 1] == BACKSLASH).collect();
+        spellings.push(with_pairs);
+        if is_last {
+            let mut unterminated = plain;
+            unterminated.unterminated = true;
+            spellings.push(unterminated);
+        }
+    }
+    let mut seen : HashSet<Vec<u16>> = HashSet::new();
+    spellings.retain(|spelling| {
+        let mut encoded : Vec<u16> = vec![];
+        if is_arg_zero {
+            encode_arg_zero(arg, spelling, &mut encoded);
+        } else {
+            encode_arg(arg, spelling, &mut encoded);
+        }
+        seen.insert(encoded)
+    });
+    spellings
+}
+
+fn random_spelling(arg: &[u16], is_last: bool, rng: &mut SplitMix64) -> Spelling {
+    let mut in_quotes = rng.chance(50);
+    let mut spelling = Spelling::new(vec![]);
+    for i in 0..arg.len() {
+        if rng.chance(30) {
+            in_quotes = !in_quotes;
+        }
+        spelling.quoted.push(in_quotes || is_blank(arg[i]));
+        spelling.double_quote.push(rng.chance(50));
+        let after_backslash = i > 0 && arg[i - 1] == BACKSLASH;
+        spelling.empty_pair.push(rng.chance(if after_backslash { 50 } else { 10 }));
+    }
+    spelling.unterminated = is_last && rng.chance(25);
+    spelling
+}
+
+/// Writes pending backslashes. If they are followed by a quote, they must be doubled.
+fn flush_backslashes(out: &mut Vec<u16>, backslashes: &mut usize, followed_by_quote: bool) {
+    let n = if followed_by_quote { *backslashes * 2 } else { *backslashes };
+    out.extend(std::iter::repeat_n(BACKSLASH, n));
+    *backslashes = 0;
+}
+
+fn encode_arg(arg: &[u16], spelling: &Spelling, out: &mut Vec<u16>) {
+    if arg.is_empty() {
+        out.push(QUOTE);
+        if !spelling.unterminated {
+            out.push(QUOTE);
+        }
+        return;
+    }
+
+    let mut in_quotes = false;
+    let mut backslashes : usize = 0;
+    for (i, &w) in arg.iter().enumerate() {
+        if w == BACKSLASH {
+            backslashes += 1;
+            continue;
+        }
+        let want_quotes = spelling.quoted[i];
+        if spelling.empty_pair[i] && !in_quotes && !want_quotes {
+            flush_backslashes(out, &mut backslashes, true);
+            out.extend([QUOTE, QUOTE]);
+        }
+        if want_quotes != in_quotes {
+            flush_backslashes(out, &mut backslashes, true);
+            out.push(QUOTE);
+            in_quotes = want_quotes;
+        }
+        if w == QUOTE {
+            flush_backslashes(out, &mut backslashes, true);
+            if in_quotes && spelling.double_quote[i] {
+                out.extend([QUOTE, QUOTE]);
+            } else {
+                out.extend([BACKSLASH, QUOTE]);
+            }
+        } else {
+            flush_backslashes(out, &mut backslashes, false);
+            out.push(w);
+        }
+    }
+
+    match (in_quotes, spelling.unterminated) {
+        (true, false) | (false, true) => {
+            flush_backslashes(out, &mut backslashes, true);
+            out.push(QUOTE);
+        },
+        (true, true) | (false, false) => flush_backslashes(out, &mut backslashes, false),
+    }
+}
+
+/// Argument zero has no escape characters, quotes only toggle.
+fn encode_arg_zero(arg: &[u16], spelling: &Spelling, out: &mut Vec<u16>) {
+    let mut in_quotes = false;
+    for (i, &w) in arg.iter().enumerate() {
+        if spelling.empty_pair[i] && !in_quotes && !spelling.quoted[i] {
+            out.extend([QUOTE, QUOTE]);
+        }
+        if spelling.quoted[i] != in_quotes {
+            out.push(QUOTE);
+            in_quotes = spelling.quoted[i];
+        }
+        out.push(w);
+    }
+    if arg.is_empty() || in_quotes != spelling.unterminated {
+        out.push(QUOTE);
+    }
+    if arg.is_empty() && !spelling.unterminated {
+        out.push(QUOTE);
+    }
+}
+
+fn assemble(args: &[Vec<u16>], spellings: &[Spelling], separators: &[&[u16]]) -> Vec<u16> {
+    let mut cmdline : Vec<u16> = vec![];
+    for (i, (arg, spelling)) in args.iter().zip(spellings).enumerate() {
+        if i == 0 {
+            encode_arg_zero(arg, spelling, &mut cmdline);
+        } else {
+            cmdline.extend_from_slice(separators[(i - 1) % separators.len()]);
+            encode_arg(arg, spelling, &mut cmdline);
+        }
+    }
+    cmdline
+}
+
+fn splits_into(cmdline: &[u16], args: &[Vec<u16>]) -> bool {
+    let parsed = parse_lp_cmd_line(cmdline, true);
+    parsed.len() == args.len()
+        && parsed.iter().zip(args).all(|(p, a)| p.to_utf16() == *a)
+}
+
+fn check_args(args: &[Vec<u16>]) -> Result<(), String> {
+    if args.is_empty() {
+        return Err("At least argument zero is needed to generate variants".to_owned());
+    }
+    if args[0].contains(&QUOTE) {
+        return Err("Quotes are not allowed in Argument zero".to_owned());
+    }
+    if args.iter().any(|a| a.contains(&0u16)) {
+        return Err("An argument contains a NUL character".to_owned());
+    }
+    Ok(())
+}
+
+/// Enumerates up to `count` distinct command lines, that are split into `args`.
+/// The first variant is the minimal quoting. Then each argument is varied on its own,
+/// and after that combinations are tried, until there are no more or too many were tried.
+pub(super) fn enumerate_variants(args: &[Vec<u16>], count: usize) -> Result<Vec<Vec<u16>>, String> {
+    check_args(args)?;
+    const SEPARATORS: [&[u16]; 4] = [&[SPACE], &[TAB], &[SPACE, SPACE], &[SPACE, TAB]];
+
+    let all_spellings : Vec<Vec<Spelling>> = args.iter().enumerate()
+        .map(|(i, a)| enumerated_spellings(a, i == 0, i + 1 == args.len()))
+        .collect();
+    let minimal : Vec<Spelling> = all_spellings.iter().map(|s| s[0].clone()).collect();
+
+    let mut seen : HashSet<Vec<u16>> = HashSet::new();
+    let mut variants : Vec<Vec<u16>> = vec![];
+    let mut try_push = |cmdline: Vec<u16>, variants: &mut Vec<Vec<u16>>| {
+        if variants.len() < count && !seen.contains(&cmdline) && splits_into(&cmdline, args) {
+            seen.insert(cmdline.clone());
+            variants.push(cmdline);
+        }
+    };
+
+    for separator in SEPARATORS {
+        try_push(assemble(args, &minimal, &[separator]), &mut variants);
+    }
+    for (i, spellings) in all_spellings.iter().enumerate() {
+        for spelling in spellings {
+            let mut current = minimal.clone();
+            current[i] = spelling.clone();
+            try_push(assemble(args, &current, &[SEPARATORS[0]]), &mut variants);
+        }
+    }
+
+    // All combinations, counted like a number with mixed radix. There are exponentially many,
+    // so give up, like `sample_variants`, if no new variants are found.
+    let max_attempts = count.saturating_mul(20).max(1000);
+    let mut digits : Vec<usize> = vec![0; args.len() + 1];
+    'outer: for _ in 0..max_attempts {
+        if variants.len() >= count {
+            break;
+        }
+        let current : Vec<Spelling> = all_spellings.iter().zip(&digits)
+            .map(|(s, &d)| s[d].clone())
+            .collect();
+        let separator = SEPARATORS[digits[args.len()]];
+        try_push(assemble(args, &current, &[separator]), &mut variants);
+
+        let mut position = 0;
+        loop {
+            let radix = if position == args.len() { SEPARATORS.len() } else { all_spellings[position].len() };
+            digits[position] += 1;
+            if digits[position] < radix {
+                break;
+            }
+            digits[position] = 0;
+            position += 1;
+            if position == digits.len() {
+                break 'outer;
+            }
+        }
+    }
+    Ok(variants)
+}
+
+/// Randomly samples up to `count` distinct command lines, that are split into `args`.
+pub(super) fn sample_variants(args: &[Vec<u16>], count: usize, seed: u64) -> Result<Vec<Vec<u16>>, String> {
+    check_args(args)?;
+    let mut rng = SplitMix64::new(seed);
+    let mut seen : HashSet<Vec<u16>> = HashSet::new();
+    let mut variants : Vec<Vec<u16>> = vec![];
+
+    // Give up, if no new variants are found. For example there is only one variant for a single empty argument.
+    let max_attempts = count.saturating_mul(20).max(1000);
+    for _ in 0..max_attempts {
+        if variants.len() >= count {
+            break;
+        }
+        let spellings : Vec<Spelling> = args.iter().enumerate()
+            .map(|(i, a)| {
+                let mut spelling = random_spelling(a, i + 1 == args.len(), &mut rng);
+                if i == 0 {
+                    // there is no escaping in argument zero
+                    spelling.double_quote = vec![false; a.len()];
+                }
+                spelling
+            })
+            .collect();
+        let separators : Vec<Vec<u16>> = (1..args.len().max(2))
+            .map(|_| (0..=rng.below(3)).map(|_| if rng.chance(70) { SPACE } else { TAB }).collect())
+            .collect();
+        let separators : Vec<&[u16]> = separators.iter().map(|s| &s[..]).collect();
+        let cmdline = assemble(args, &spellings, &separators);
+        if !seen.contains(&cmdline) && splits_into(&cmdline, args) {
+            seen.insert(cmdline.clone());
+            variants.push(cmdline);
+        }
+    }
+    Ok(variants)
+}
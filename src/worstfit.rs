@@ -0,0 +1,135 @@
+use std::ops::Range;
+
+use crate::codepage::{
+    multibyte_to_wide,
+    wide_to_multibyte,
+    Substitution,
+    SubstitutionKind,
+};
+use crate::commandline::{
+    parse_lp_cmd_line,
+    Arg,
+};
+
+/// Bytes, that change how the C-Runtime splits the command line.
+const STRUCTURAL_BYTES: [u8; 4] = [b'"', b'\\', b' ', b'\t'];
+/// Bytes, that start an option.
+const OPTION_BYTES: [u8; 2] = [b'-', b'/'];
+
+pub(super) enum DifferenceKind {
+    /// The converted command line is split at other places. Arguments are merged, split or shifted.
+    Boundary,
+    /// An argument of the converted command line starts an option, but the original argument does not.
+    OptionInjection,
+    /// An argument has the same place, but another value.
+    Changed,
+}
+
+impl DifferenceKind {
+    pub(super) fn as_str(&self) -> &'static str {
+        match self {
+            DifferenceKind::Boundary => "boundary",
+            DifferenceKind::OptionInjection => "option-injection",
+            DifferenceKind::Changed => "changed",
+        }
+    }
+}
+
+/// A character, that best fit conversion replaced.
+pub(super) struct Character {
+    /// The index of the UTF-16 code unit in the command line. It is the same in both command lines.
+    pub(super) position: usize,
+    pub(super) original: Vec<u16>,
+    pub(super) converted: Vec<u8>,
+}
+
+pub(super) struct Difference {
+    pub(super) kind: DifferenceKind,
+    /// The number of the argument in the converted command line.
+    pub(super) number: usize,
+    pub(super) range: Range<usize>,
+    /// The arguments of the original command line, that overlap with `range`.
+    pub(super) original_args: Vec<(usize, Vec<u16>)>,
+    pub(super) converted_arg: Vec<u16>,
+    pub(super) characters: Vec<Character>,
+}
+
+pub(super) struct WorstFitReport {
+    pub(super) code_page: u32,
+    pub(super) cmdline: Vec<u16>,
+    /// The command line after `WideCharToMultiByte` with best fit and back to UTF-16.
+    pub(super) converted: Vec<u16>,
+    pub(super) original_args: Vec<Vec<u16>>,
+    pub(super) converted_args: Vec<Vec<u16>>,
+    pub(super) differences: Vec<Difference>,
+}
+
+fn characters(substitutions: &[Substitution], range: Range<usize>, relevant: &[u8]) -> Vec<Character> {
+    substitutions.iter()
+        .filter(|s| matches!(s.kind, SubstitutionKind::BestFit) && range.contains(&s.position))
+        .filter(|s| s.bytes.iter().any(|b| relevant.contains(b)))
+        .map(|s| Character { position: s.position, original: s.units.clone(), converted: s.bytes.clone() })
+        .collect()
+}
+
+fn starts_option(arg: &[u16]) -> bool {
+    arg.first().is_some_and(|&w| OPTION_BYTES.iter().any(|&b| w == u16::from(b)))
+}
+
+/// Converts the command line like a program, that uses `GetCommandLineA` or `main`, gets it, and splits both
+/// command lines with `parse_lp_cmd_line`. Every argument of the converted command line, that is not the same
+/// as in the original command line, is a difference.
+/// Each character is converted to as many UTF-16 code units as it has, so positions are the same in both command lines.
+pub(super) fn analyze_cmdline(code_page: u32, cmdline: &[u16]) -> Result<WorstFitReport, String> {
+    let narrow = wide_to_multibyte(code_page, cmdline, true, b'?')?;
+    let converted = multibyte_to_wide(code_page, &narrow.bytes)?;
+    if converted.len() != cmdline.len() {
+        return Err(format!("The converted command line has {} instead of {} UTF-16 code units",
+                           converted.len(), cmdline.len()));
+    }
+    let original_args : Vec<Arg<'_>> = parse_lp_cmd_line(cmdline, true);
+    let converted_args : Vec<Arg<'_>> = parse_lp_cmd_line(&converted, true);
+
+    let all_bytes : Vec<u8> = (0..=255).collect();
+    let mut differences : Vec<Difference> = vec![];
+    for arg in &converted_args {
+        let overlapping : Vec<&Arg<'_>> = original_args.iter()
+            .filter(|a| a.range.start < arg.range.end.max(arg.range.start + 1) && arg.range.start < a.range.end.max(a.range.start + 1))
+            .collect();
+        let same_place = overlapping.iter().find(|a| a.range == arg.range);
+        let (kind, relevant, span) = match same_place {
+            None => {
+                // The responsible characters may be in any of the merged or split arguments.
+                let start = overlapping.iter().map(|a| a.range.start).chain([arg.range.start]).min().unwrap_or(arg.range.start);
+                let end = overlapping.iter().map(|a| a.range.end).chain([arg.range.end]).max().unwrap_or(arg.range.end);
+                (DifferenceKind::Boundary, &STRUCTURAL_BYTES[..], start..end)
+            },
+            Some(original) if original.arg == arg.arg => continue,
+            Some(original) if arg.number > 0 && starts_option(&arg.to_utf16()) && !starts_option(&original.to_utf16()) =>
+                (DifferenceKind::OptionInjection, &OPTION_BYTES[..], arg.range.start..arg.range.start + 1),
+            Some(_) => (DifferenceKind::Changed, &all_bytes[..], arg.range.clone()),
+        };
+        let mut chars = characters(&narrow.substitutions, span.clone(), relevant);
+        if chars.is_empty() {
+            // For example a best fit quote in an earlier argument, which makes a later quote close the quoting.
+            chars = characters(&narrow.substitutions, 0..span.end, &STRUCTURAL_BYTES);
+        }
+        differences.push(Difference {
+            kind,
+            number: arg.number,
+            range: arg.range.clone(),
+            original_args: overlapping.iter().map(|a| (a.number, a.to_utf16())).collect(),
+            converted_arg: arg.to_utf16(),
+            characters: chars,
+        });
+    }
+
+    Ok(WorstFitReport {
+        code_page,
+        cmdline: cmdline.to_vec(),
+        original_args: original_args.iter().map(|a| a.to_utf16()).collect(),
+        converted_args: converted_args.iter().map(|a| a.to_utf16()).collect(),
+        converted,
+        differences,
+    })
+}